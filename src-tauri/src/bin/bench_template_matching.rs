@@ -0,0 +1,52 @@
+use clap::Parser;
+use std::path::PathBuf;
+use tauri_app_lib::monster_recognition::benchmark_template_matching;
+
+/// 对比串行匹配与 par_match_templates 在预打包怪物模板上的耗时
+#[derive(Parser, Debug)]
+#[command(about = "Benchmark serial vs parallel ORB template matching", long_about = None)]
+struct Args {
+    /// 怪物特征缓存文件
+    #[arg(long, default_value = "resources/monster_features_opencv_v2.bin")]
+    cache: PathBuf,
+
+    /// 场景图片，默认取 resources/images_monster_char 下的第一张怪物图
+    #[arg(long)]
+    image: Option<PathBuf>,
+
+    /// 每种方式重复的轮数
+    #[arg(long, default_value_t = 5)]
+    rounds: usize,
+}
+
+fn main() -> Result<(), String> {
+    let args = Args::parse();
+    let image_path = match args.image {
+        Some(path) => path,
+        None => first_monster_image(&PathBuf::from("resources/images_monster_char"))
+            .ok_or("No image found in resources/images_monster_char, pass --image")?,
+    };
+    let scene = image::open(&image_path).map_err(|e| format!("Failed to open {:?}: {}", image_path, e))?;
+
+    println!("模板: {:?}  场景: {:?}  线程: {}", args.cache, image_path, rayon::current_num_threads());
+    let result = benchmark_template_matching(&args.cache, &scene, args.rounds)?;
+    println!("{} 个模板，{} 轮", result.templates, result.rounds);
+    println!("串行: {:.1} ms/轮", result.serial_ms);
+    println!("并行: {:.1} ms/轮 (加速 {:.2}x)", result.parallel_ms, result.serial_ms / result.parallel_ms.max(f64::EPSILON));
+    println!("最佳匹配: {:?}", result.best);
+
+    if !result.same_best {
+        println!("✗ 串行与并行的最佳匹配不一致");
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn first_monster_image(dir: &PathBuf) -> Option<PathBuf> {
+    let mut images: Vec<PathBuf> = std::fs::read_dir(dir).ok()?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().map_or(false, |ext| ext == "webp" || ext == "png"))
+        .collect();
+    images.sort();
+    images.into_iter().next()
+}
@@ -0,0 +1,45 @@
+use tauri_app_lib::monster_recognition::letterbox_image;
+
+// 用合成的 2560x1080（21:9）超宽屏图检查 letterbox：等比缩放、上下灰边，
+// 以及检测框 x1/y1/x2/y2 经模型坐标映射后能还原回原图
+fn main() {
+    let img = image::DynamicImage::ImageRgb8(image::RgbImage::from_pixel(2560, 1080, image::Rgb([200, 30, 30])));
+    let (canvas, lb) = letterbox_image(&img, 640);
+    let mut failed = false;
+
+    println!("2560x1080 letterbox: scale={:.4} pad=({}, {})", lb.scale, lb.pad_x, lb.pad_y);
+    if (lb.scale - 0.25).abs() > 1e-6 || lb.pad_x != 0.0 || lb.pad_y != 185.0 {
+        println!("  ✗ 期望 scale=0.25 pad=(0, 185)");
+        failed = true;
+    }
+    if canvas.get_pixel(320, 100).0 != [114, 114, 114] || canvas.get_pixel(320, 320).0 != [200, 30, 30] {
+        println!("  ✗ 灰边或内容区域像素不正确");
+        failed = true;
+    }
+
+    // (原图框, 期望还原结果)：最后一个框伸进上下灰边，应裁到原图范围内
+    let cases = [
+        ((400, 200, 1200, 900), (400, 200, 1200, 900)),
+        ((0, 0, 2560, 1080), (0, 0, 2560, 1080)),
+        ((1800, 40, 2520, 1060), (1800, 40, 2520, 1060)),
+        ((1000, -200, 1400, 1300), (1000, 0, 1400, 1080)),
+    ];
+    for ((x1, y1, x2, y2), expected) in cases {
+        // 原图 -> 模型输入坐标，再按模型输出的 (xc, yc, w, h) 还原
+        let to_model = |x: i32, y: i32| (x as f32 * lb.scale + lb.pad_x, y as f32 * lb.scale + lb.pad_y);
+        let (mx1, my1) = to_model(x1, y1);
+        let (mx2, my2) = to_model(x2, y2);
+        let got = lb.box_to_original((mx1 + mx2) / 2.0, (my1 + my2) / 2.0, mx2 - mx1, my2 - my1, 2560, 1080);
+        println!("({}, {}, {}, {}) -> {:?}", x1, y1, x2, y2, got);
+        let close = |a: i32, b: i32| (a - b).abs() <= 1;
+        if !(close(got.0, expected.0) && close(got.1, expected.1) && close(got.2, expected.2) && close(got.3, expected.3)) {
+            println!("  ✗ 期望 {:?}", expected);
+            failed = true;
+        }
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+    println!("✓ 超宽屏 letterbox 正确");
+}
@@ -0,0 +1,57 @@
+use std::path::PathBuf;
+use tauri_app_lib::{detect_day_from_log_files, replay_day_from_log_files};
+
+const RUN_INIT: &str = "[10:00:00.000] [NetMessageRunInitialized] Run initialized";
+const TO_ENCOUNTER: &str = "[10:01:00.000] State changed from [ChoiceState] to [EncounterState]";
+const TO_PVP: &str = "[10:02:00.000] State changed from [ChoiceState] to [PVPCombatState]";
+const PVP_TO_CHOICE: &str = "[10:03:00.000] State changed from [PVPCombatState] to [ChoiceState]";
+const PVP_TO_LEVEL_UP: &str = "[10:03:00.000] State changed from [PVPCombatState] to [LevelUpState]";
+
+// 合成的 Player-prev.log / Player.log，检查跨日志轮转时启动回放与日志扫描得到的天数
+fn main() {
+    let dir = std::env::temp_dir().join(format!("bazaar_log_rotation_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("create temp dir");
+    let prev_path = dir.join("Player-prev.log");
+    let log_path = dir.join("Player.log");
+
+    let cases: [(&str, Vec<&str>, Vec<&str>, u32); 4] = [
+        // 开局和第一场 PVP 在 prev，第二场在 current：天数跨文件延续
+        ("run spans rotation", vec![RUN_INIT, TO_ENCOUNTER, TO_PVP, PVP_TO_CHOICE], vec![TO_ENCOUNTER, TO_PVP, PVP_TO_LEVEL_UP], 3),
+        // PVP 在 prev 末尾开始，在 current 开头结束
+        ("pvp split by rotation", vec![RUN_INIT, TO_PVP], vec![PVP_TO_CHOICE, TO_ENCOUNTER], 2),
+        // current 开了新局：prev 里的天数不再累加
+        ("new run in current", vec![RUN_INIT, TO_PVP, PVP_TO_CHOICE, TO_PVP, PVP_TO_CHOICE], vec![RUN_INIT, TO_PVP, PVP_TO_CHOICE], 2),
+        // 没有 prev 日志
+        ("no prev log", vec![], vec![RUN_INIT, TO_PVP, PVP_TO_CHOICE], 2),
+    ];
+
+    let mut failed = false;
+    for (name, prev_lines, log_lines, expected) in cases {
+        write_log(&prev_path, &prev_lines);
+        write_log(&log_path, &log_lines);
+        let prev = (!prev_lines.is_empty()).then_some(&prev_path);
+
+        let replay_day = replay_day_from_log_files(prev, &log_path);
+        let scan_day = detect_day_from_log_files(&log_path, prev.unwrap_or(&PathBuf::new()), 6, false)
+            .unwrap_or_else(|e| panic!("{}: {}", name, e));
+        println!("{}: replay={} scan={:?} (期望 {})", name, replay_day, scan_day, expected);
+        if replay_day != expected || scan_day != Some(expected) {
+            println!("  ✗ 天数不符");
+            failed = true;
+        }
+    }
+
+    let _ = std::fs::remove_dir_all(&dir);
+    if failed {
+        std::process::exit(1);
+    }
+    println!("✓ 跨日志轮转的天数计算正确");
+}
+
+fn write_log(path: &PathBuf, lines: &[&str]) {
+    if lines.is_empty() {
+        let _ = std::fs::remove_file(path);
+        return;
+    }
+    std::fs::write(path, format!("{}\n", lines.join("\n"))).expect("write log");
+}
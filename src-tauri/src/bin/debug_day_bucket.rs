@@ -0,0 +1,31 @@
+use tauri_app_lib::{canonical_day_label, day_bucket, day_bucket_label};
+
+// 检查天数分组：第 10 天及以后应归入同一个 "Day 10+" 池
+fn main() {
+    let mut failed = false;
+
+    for (day, bucket, label) in [(9, 9, "Day 9"), (10, 10, "Day 10+"), (15, 10, "Day 10+")] {
+        let got_bucket = day_bucket(day);
+        let got_label = day_bucket_label(day);
+        println!("day {:>2}: bucket={} label={:?}", day, got_bucket, got_label);
+        if got_bucket != bucket || got_label != label {
+            println!("  ✗ 期望 bucket={} label={:?}", bucket, label);
+            failed = true;
+        }
+    }
+
+    // 数据中的原始标签规范化后应与分组标签一致
+    for (raw, expected) in [("Day 9", "Day 9"), ("Day 10", "Day 10+"), ("Day 15", "Day 10+"), ("Day 10+", "Day 10+")] {
+        let got = canonical_day_label(raw);
+        println!("{:?} -> {:?}", raw, got);
+        if got != expected {
+            println!("  ✗ 期望 {:?}", expected);
+            failed = true;
+        }
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+    println!("✓ 天数分组标签正确");
+}
@@ -0,0 +1,48 @@
+use tauri_app_lib::monster_recognition::{
+    nms, yolo_class_id, yolo_class_name, NmsStrategy, YoloDetection, CLASS_EVENT, CLASS_ITEM,
+};
+
+// 构造 item 与 event 完全重叠的样例，检查两种 NMS 策略下保留的框
+fn main() {
+    let item = yolo_class_id(CLASS_ITEM).expect("item class id");
+    let event = yolo_class_id(CLASS_EVENT).expect("event class id");
+    let boxed = |class_id: usize, confidence: f32| YoloDetection {
+        x1: 100,
+        y1: 100,
+        x2: 300,
+        y2: 260,
+        confidence,
+        class_id,
+    };
+
+    let detections = vec![
+        boxed(item, 0.91),
+        boxed(event, 0.84),
+        // 同类别的重复框仍应被抑制
+        boxed(item, 0.60),
+    ];
+
+    let mut failed = false;
+    for (strategy, expected) in [(NmsStrategy::PerClass, 2), (NmsStrategy::ClassAgnostic, 1)] {
+        let kept = nms(detections.clone(), 0.45, strategy);
+        println!("{:?}: 保留 {} 个框", strategy, kept.len());
+        for d in &kept {
+            println!("  {} conf={:.2} ({},{})-({},{})", yolo_class_name(d.class_id), d.confidence, d.x1, d.y1, d.x2, d.y2);
+        }
+        if kept.len() != expected {
+            println!("  ✗ 期望保留 {} 个框", expected);
+            failed = true;
+        }
+    }
+
+    let per_class = nms(detections, 0.45, NmsStrategy::PerClass);
+    if !(per_class.iter().any(|d| d.class_id == item) && per_class.iter().any(|d| d.class_id == event)) {
+        println!("✗ PerClass 未同时保留 item 与 event");
+        failed = true;
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+    println!("✓ 跨类别重叠框均被保留");
+}
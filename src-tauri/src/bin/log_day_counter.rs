@@ -3,6 +3,7 @@ use clap::Parser;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::path::PathBuf;
+use tauri_app_lib::{detect_day_from_log_files, replay_day_from_log_files};
 
 /// Simple CLI to infer current in-game day from Player.log
 #[derive(Parser, Debug)]
@@ -12,6 +13,10 @@ struct Args {
     #[arg(short, long)]
     log: PathBuf,
 
+    /// Optional Player-prev.log, replayed before --log (runs that span a log rotation)
+    #[arg(short, long)]
+    prev: Option<PathBuf>,
+
     /// Hours per in-game day (default: 6)
     #[arg(short = 'h', long, default_value_t = 6)]
     hours_per_day: u32,
@@ -88,14 +93,24 @@ impl GameStatus {
 fn main() -> Result<()> {
     let args = Args::parse();
 
-    let file = File::open(&args.log).with_context(|| format!("Failed to open log file: {:?}", args.log))?;
-    let reader = BufReader::new(file);
+    // prev -> current treated as one continuous stream, state carries over the boundary
+    let mut files = Vec::new();
+    if let Some(prev) = &args.prev {
+        files.push(prev.clone());
+    }
+    files.push(args.log.clone());
 
     // Optionally find the most recent NetMessageRunInitialized
     let mut lines: Vec<String> = Vec::new();
-    for line in reader.lines() {
-        let l = line.unwrap_or_default();
-        lines.push(l);
+    for path in &files {
+        let file = File::open(path).with_context(|| format!("Failed to open log file: {:?}", path))?;
+        let reader = BufReader::new(file);
+        let before = lines.len();
+        for line in reader.lines() {
+            let l = line.unwrap_or_default();
+            lines.push(l);
+        }
+        println!("Loaded {} lines from {:?}", lines.len() - before, path);
     }
 
     let start_idx = if args.retro {
@@ -117,5 +132,18 @@ fn main() -> Result<()> {
         println!("Final detected day: {}", status.current_day);
     }
 
+    // Compare against the app's own code paths: the monitor's startup replay and detect_current_day's log scan
+    let replay_day = replay_day_from_log_files(args.prev.as_ref(), &args.log);
+    let prev_for_scan = args.prev.clone().unwrap_or_default();
+    let scan_day = detect_day_from_log_files(&args.log, &prev_for_scan, args.hours_per_day, args.retro)
+        .map_err(anyhow::Error::msg)?;
+    println!("App replay (resync_from_logs) day: {}", replay_day);
+    println!("App log scan (detect_current_day) day: {:?}", scan_day);
+
+    if status.current_day != 0 && status.current_day != replay_day {
+        println!("✗ CLI day {} differs from the app replay day {}", status.current_day, replay_day);
+        std::process::exit(1);
+    }
+
     Ok(())
 }
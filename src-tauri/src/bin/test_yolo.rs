@@ -1,7 +1,8 @@
-use image::{imageops::FilterType, GenericImageView};
+use image::GenericImageView;
 use ndarray::Array;
 use ort::session::{builder::GraphOptimizationLevel, Session};
 use std::time::Instant;
+use tauri_app_lib::monster_recognition::letterbox_image;
 use opencv::{
     core::{Point, Scalar, Rect},
     highgui,
@@ -49,9 +50,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
         let start_inference = Instant::now();
         
-        // 1. 预处理 (640x640)
-        let resized = img.resize_exact(640, 640, FilterType::Lanczos3);
-        let rgb_img = resized.to_rgb8();
+        // 1. 预处理：与主程序共用 letterbox_image
+        let (rgb_img, letterbox) = letterbox_image(&img, 640);
         
         let mut input_array = Array::zeros((1, 3, 640, 640));
         for (x, y, pixel) in rgb_img.enumerate_pixels() {
@@ -94,16 +94,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
 
             if max_score > conf_threshold {
-                let x1 = (xc - w / 2.0) * (orig_w as f32 / 640.0);
-                let y1 = (yc - h / 2.0) * (orig_h as f32 / 640.0);
-                let x2 = (xc + w / 2.0) * (orig_w as f32 / 640.0);
-                let y2 = (yc + h / 2.0) * (orig_h as f32 / 640.0);
+                let (x1, y1, x2, y2) = letterbox.box_to_original(xc, yc, w, h, orig_w, orig_h);
 
                 candidates.push(Detection {
-                    x1: x1 as i32,
-                    y1: y1 as i32,
-                    x2: x2 as i32,
-                    y2: y2 as i32,
+                    x1,
+                    y1,
+                    x2,
+                    y2,
                     confidence: max_score,
                     class_id,
                 });
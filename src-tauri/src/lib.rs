@@ -1,14 +1,14 @@
 use std::sync::{Arc, RwLock, OnceLock};
 use std::sync::atomic::{AtomicBool, Ordering};
-use tauri::{State, Manager, Emitter};
+use tauri::{State, Manager, Emitter, Listener};
 
-#[cfg(target_os = "macos")]
+#[cfg(any(target_os = "macos", target_os = "windows"))]
 use tauri::menu::{Menu, MenuItem};
-#[cfg(target_os = "macos")]
+#[cfg(any(target_os = "macos", target_os = "windows"))]
 use tauri::tray::{TrayIconBuilder, TrayIconEvent, MouseButton, MouseButtonState};
 
 use serde::{Serialize, Deserialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::path::PathBuf;
 use regex::Regex;
 use std::io::{Read, BufRead, BufReader, Seek, SeekFrom, Write};
@@ -19,7 +19,7 @@ use chrono::Local;
 
 // Windows 特定导入
 #[cfg(target_os = "windows")]
-use windows::Win32::UI::Input::KeyboardAndMouse::{GetAsyncKeyState, VK_RBUTTON, VK_MENU};
+use windows::Win32::UI::Input::KeyboardAndMouse::{GetAsyncKeyState, VK_RBUTTON, VK_MENU, VK_XBUTTON1, VK_XBUTTON2};
 #[cfg(target_os = "windows")]
 use windows::Win32::UI::WindowsAndMessaging::{
     GetWindowLongW, SetWindowLongW, SetWindowPos,
@@ -62,15 +62,89 @@ use tauri_nspanel::WebviewWindowExt as NSPanelExt;
 const VK_RBUTTON: i32 = 2;    // 右键
 #[cfg(not(target_os = "windows"))]
 const VK_MENU: i32 = 18;      // Alt 键
+// 鼠标侧键（后退 / 前进）
+const VK_XBUTTON1_CODE: i32 = 5;
+const VK_XBUTTON2_CODE: i32 = 6;
+
+/// 热键：主键 + 可选修饰键（均为 Windows 虚拟键码），例如 Ctrl+Q = { modifiers: [17], key: 81 }
+/// 序列化时无修饰键写成单个 i32，与旧配置及旧前端兼容
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "HotkeyRepr", into = "HotkeyRepr")]
+pub struct Hotkey {
+    pub modifiers: Vec<i32>,
+    pub key: i32,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum HotkeyRepr {
+    Code(i32),
+    Combo {
+        #[serde(default)]
+        modifiers: Vec<i32>,
+        key: i32,
+    },
+}
+
+impl From<HotkeyRepr> for Hotkey {
+    fn from(repr: HotkeyRepr) -> Self {
+        match repr {
+            HotkeyRepr::Code(key) => Hotkey::single(key),
+            HotkeyRepr::Combo { modifiers, key } => Hotkey { modifiers, key },
+        }
+    }
+}
+
+impl From<Hotkey> for HotkeyRepr {
+    fn from(hotkey: Hotkey) -> Self {
+        if hotkey.modifiers.is_empty() {
+            HotkeyRepr::Code(hotkey.key)
+        } else {
+            HotkeyRepr::Combo { modifiers: hotkey.modifiers, key: hotkey.key }
+        }
+    }
+}
+
+impl Hotkey {
+    fn single(key: i32) -> Self {
+        Hotkey { modifiers: Vec::new(), key }
+    }
 
-/// 跨平台按键检测
+    /// 是否为不带修饰键的鼠标左/右键
+    fn is_plain_mouse_button(&self) -> bool {
+        self.modifiers.is_empty() && (self.key == 1 || self.key == 2)
+    }
+}
+
+impl std::fmt::Display for Hotkey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for m in &self.modifiers {
+            write!(f, "{}+", m)?;
+        }
+        write!(f, "{}", self.key)
+    }
+}
+
+/// 跨平台热键检测：主键和所有修饰键同时按下才算触发
+fn is_key_pressed(hotkey: &Hotkey, device_state: &DeviceState, mouse_state: &MouseState) -> bool {
+    hotkey.modifiers.iter().all(|&m| is_vk_pressed(m, device_state, mouse_state))
+        && is_vk_pressed(hotkey.key, device_state, mouse_state)
+}
+
+/// 单个按键检测
 /// key_code: Windows 虚拟键码
 /// device_state: device_query 状态
 /// mouse_state: 鼠标状态
-fn is_key_pressed(key_code: i32, _device_state: &DeviceState, _mouse_state: &MouseState) -> bool {
+fn is_vk_pressed(key_code: i32, _device_state: &DeviceState, _mouse_state: &MouseState) -> bool {
     #[cfg(target_os = "windows")]
     {
-        unsafe { (GetAsyncKeyState(key_code) as i16) < 0 }
+        // 鼠标侧键与普通键码一样可直接用 GetAsyncKeyState 查询
+        let vk = match key_code {
+            VK_XBUTTON1_CODE => VK_XBUTTON1.0 as i32,
+            VK_XBUTTON2_CODE => VK_XBUTTON2.0 as i32,
+            other => other,
+        };
+        unsafe { (GetAsyncKeyState(vk) as i16) < 0 }
     }
 
     #[cfg(not(target_os = "windows"))]
@@ -78,12 +152,16 @@ fn is_key_pressed(key_code: i32, _device_state: &DeviceState, _mouse_state: &Mou
         let device_state = _device_state;
         let mouse_state = _mouse_state;
         // 映射 Windows 虚拟键码到 device_query
-        // Windows VK codes: VK_LBUTTON=1, VK_RBUTTON=2, VK_MBUTTON=4
-        // device_query indices: 0=left, 1=middle, 2=right (varies by platform)
+        // Windows VK codes: VK_LBUTTON=1, VK_RBUTTON=2, VK_MBUTTON=4, VK_XBUTTON1=5, VK_XBUTTON2=6
+        // device_query indices: 0=left, 1=middle, 2=right, 3=back, 4=forward (varies by platform)
         match key_code {
             1 => mouse_state.button_pressed.get(0).copied().unwrap_or(false), // VK_LBUTTON -> index 0 (left)
             2 => mouse_state.button_pressed.get(2).copied().unwrap_or(false), // VK_RBUTTON -> index 2 (right)
             4 => mouse_state.button_pressed.get(1).copied().unwrap_or(false), // VK_MBUTTON -> index 1 (middle)
+            VK_XBUTTON1_CODE => mouse_state.button_pressed.get(3).copied().unwrap_or(false), // VK_XBUTTON1 -> index 3 (side back)
+            VK_XBUTTON2_CODE => mouse_state.button_pressed.get(4).copied().unwrap_or(false), // VK_XBUTTON2 -> index 4 (side forward)
+            16 => device_state.get_keys().contains(&Keycode::LShift) || device_state.get_keys().contains(&Keycode::RShift), // Shift
+            17 => device_state.get_keys().contains(&Keycode::LControl) || device_state.get_keys().contains(&Keycode::RControl), // Ctrl
             18 => device_state.get_keys().contains(&Keycode::LAlt) || device_state.get_keys().contains(&Keycode::RAlt), // Alt
             81 => device_state.get_keys().contains(&Keycode::Q), // Q
             192 => device_state.get_keys().contains(&Keycode::Grave), // ` (反引号)
@@ -116,6 +194,7 @@ fn is_key_pressed(key_code: i32, _device_state: &DeviceState, _mouse_state: &Mou
 }
 
 // 获取默认热键值（跨平台）
+// 热键可配置为任意 Windows 虚拟键码，包括鼠标左/右/中键 (1/2/4) 和鼠标侧键 VK_XBUTTON1 (5) / VK_XBUTTON2 (6)
 fn default_monster_hotkey() -> i32 {
     #[cfg(target_os = "windows")]
     { VK_RBUTTON.0 as i32 }
@@ -353,9 +432,10 @@ fn fallback_setup_macos_overlay(window: &tauri::WebviewWindow) {
     }
 }
 
-use crate::monster_recognition::{scan_and_identify_monster_at_mouse, YoloDetection};
+use crate::monster_recognition::{scan_and_identify_monster_candidates, pick_confident_monster, YoloDetection, CLASS_EVENT, CLASS_ITEM, CLASS_MONSTER_ICON, CLASS_SKILL};
 
 pub mod monster_recognition;
+mod local_api;
 
 #[derive(Clone, serde::Deserialize, serde::Serialize)]
 struct BoundsRect {
@@ -375,15 +455,46 @@ fn update_overlay_bounds(bounds: Vec<BoundsRect>, state: State<'_, OverlayState>
 }
 
 static YOLO_SCAN_RESULTS: OnceLock<RwLock<Vec<YoloDetection>>> = OnceLock::new();
-static YOLO_SCAN_IMAGE: OnceLock<RwLock<Option<image::DynamicImage>>> = OnceLock::new();
+// 保存的截图已按 YOLO_STORED_IMAGE_MAX_SIDE 降采样，检测框坐标与之一致；读取方拿 Arc 快照，不复制整张图
+static YOLO_SCAN_IMAGE: OnceLock<RwLock<Option<Arc<image::DynamicImage>>>> = OnceLock::new();
+// 保存的截图相对原始截图的缩放比例（<= 1）
+static YOLO_SCAN_IMAGE_SCALE: RwLock<f32> = RwLock::new(1.0);
+const YOLO_STORED_IMAGE_MAX_SIDE: u32 = 2560;
 static YOLO_WINDOW_OFFSET: OnceLock<RwLock<(i32, i32)>> = OnceLock::new();
 static ABORT_YOLO: AtomicBool = AtomicBool::new(false);
+// 日志追赶状态：单次读取超过 5000 字节时进入，直到某个轮询周期日志不再增长才退出。
+// 期间所有自动触发的扫描都应跳过，前端据此显示“正在同步日志…”
+static LOG_CATCHING_UP: AtomicBool = AtomicBool::new(false);
+
+fn set_log_catching_up(app: &tauri::AppHandle, catching_up: bool) {
+    if LOG_CATCHING_UP.swap(catching_up, Ordering::SeqCst) != catching_up {
+        println!("[LogMonitor] Catching up: {}", catching_up);
+        let _ = app.emit("log-catching-up", catching_up);
+    }
+}
+
+#[tauri::command]
+fn is_log_catching_up() -> bool {
+    LOG_CATCHING_UP.load(Ordering::SeqCst)
+}
+
+// overlay_show_on_first_scan 开启时为 false，首次识别出结果后置为 true 并显示 overlay
+static OVERLAY_REVEALED: AtomicBool = AtomicBool::new(true);
+
+fn reveal_overlay_on_first_scan(app: &tauri::AppHandle) {
+    if !OVERLAY_REVEALED.swap(true, Ordering::SeqCst) {
+        println!("[Overlay] First scan produced a result, showing overlay");
+        if let Some(overlay) = app.get_webview_window("overlay") {
+            let _ = overlay.show();
+        }
+    }
+}
 
 fn get_yolo_scan_results() -> &'static RwLock<Vec<YoloDetection>> {
     YOLO_SCAN_RESULTS.get_or_init(|| RwLock::new(Vec::new()))
 }
 
-fn get_yolo_scan_image() -> &'static RwLock<Option<image::DynamicImage>> {
+fn get_yolo_scan_image() -> &'static RwLock<Option<Arc<image::DynamicImage>>> {
     YOLO_SCAN_IMAGE.get_or_init(|| RwLock::new(None))
 }
 
@@ -397,6 +508,38 @@ fn abort_yolo_scan() {
     ABORT_YOLO.store(true, Ordering::SeqCst);
 }
 
+#[tauri::command]
+fn validate_yolo_model(path: String) -> Result<monster_recognition::YoloModelValidation, String> {
+    monster_recognition::validate_yolo_model(&PathBuf::from(path))
+}
+
+// 返回最近一次 YOLO 扫描的检测框（截图像素坐标）和窗口偏移量，overlay 可直接绘制而无需重新扫描
+#[tauri::command]
+fn get_yolo_detections() -> serde_json::Value {
+    use image::GenericImageView;
+    let detections = get_yolo_scan_results().read().unwrap().clone();
+    let (window_x, window_y) = *get_yolo_window_offset().read().unwrap();
+    let image_size = get_yolo_scan_image().read().unwrap().as_ref().map(|img| img.dimensions());
+
+    let boxes: Vec<serde_json::Value> = detections.iter().map(|d| serde_json::json!({
+        "x1": d.x1,
+        "y1": d.y1,
+        "x2": d.x2,
+        "y2": d.y2,
+        "confidence": d.confidence,
+        "class_id": d.class_id,
+        "class_name": monster_recognition::yolo_class_name(d.class_id)
+    })).collect();
+
+    serde_json::json!({
+        "detections": boxes,
+        "window_x": window_x,
+        "window_y": window_y,
+        "image_width": image_size.map(|(w, _)| w),
+        "image_height": image_size.map(|(_, h)| h)
+    })
+}
+
 #[tauri::command]
 fn set_show_yolo_monitor(app: tauri::AppHandle, show: bool) -> Result<(), String> {
     // Broadcast the show/hide event to all windows; overlay will handle it
@@ -408,19 +551,147 @@ fn set_show_yolo_monitor(app: tauri::AppHandle, show: bool) -> Result<(), String
     Ok(())
 }
 
+// overlay 详情面板布局：x/y 为面板中心相对 overlay 的百分比，scale 为百分比缩放，width/height 为 CSS 像素
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct OverlayDetailLayout {
+    pub x: i32,
+    pub y: i32,
+    pub scale: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
 #[tauri::command]
-fn update_overlay_detail_position(app: tauri::AppHandle, x: i32, y: i32, scale: i32, width: Option<i32>, height: Option<i32>) -> Result<(), String> {
-    // Broadcast the position update to overlay window
+fn get_overlay_detail_layout() -> Option<OverlayDetailLayout> {
+    load_state().overlay_detail_layout
+}
+
+// 应答 overlay 的同步请求：把保存的布局推给 overlay，坐标按当前显示器重新夹到可见区域内
+fn restore_overlay_detail_layout(app: &tauri::AppHandle) {
+    let Some(layout) = load_state().overlay_detail_layout else { return };
+    let (x, y) = clamp_detail_position(app, layout.x, layout.y, layout.scale, layout.width, layout.height).unwrap_or((layout.x, layout.y));
+    if (x, y) != (layout.x, layout.y) {
+        println!("[Overlay] Restored detail position clamped: ({}, {}) -> ({}, {})", layout.x, layout.y, x, y);
+    }
     let _ = app.emit("update-overlay-detail-position", serde_json::json!({
         "x": x,
         "y": y,
+        "scale": layout.scale,
+        "width": layout.width,
+        "height": layout.height,
+        "requested_x": layout.x,
+        "requested_y": layout.y
+    }));
+}
+
+#[tauri::command]
+fn update_overlay_detail_position(app: tauri::AppHandle, x: i32, y: i32, scale: i32, width: Option<i32>, height: Option<i32>) -> Result<(), String> {
+    let width = width.unwrap_or(420);
+    let height = height.unwrap_or(600);
+    let (clamped_x, clamped_y) = clamp_detail_position(&app, x, y, scale, width, height).unwrap_or((x, y));
+    if (clamped_x, clamped_y) != (x, y) {
+        println!("[Overlay] Detail position clamped: ({}, {}) -> ({}, {})", x, y, clamped_x, clamped_y);
+    }
+
+    // 保存用户设置的原始值（未夹取），换到更大的显示器时可以恢复；同步请求重复广播时不重复写盘
+    let layout = OverlayDetailLayout { x, y, scale, width, height };
+    let mut state = load_state();
+    if state.overlay_detail_layout != Some(layout) {
+        state.overlay_detail_layout = Some(layout);
+        save_state(&state);
+    }
+
+    // Broadcast the position update to overlay window
+    let _ = app.emit("update-overlay-detail-position", serde_json::json!({
+        "x": clamped_x,
+        "y": clamped_y,
         "scale": scale,
-        "width": width.unwrap_or(420),
-        "height": height.unwrap_or(600)
+        "width": width,
+        "height": height,
+        "requested_x": x,
+        "requested_y": y
     }));
     Ok(())
 }
 
+// 游戏窗口中心点所在显示器的 (x, y, w, h)；xcap 的窗口与显示器坐标处于同一坐标系（macOS 为逻辑点，Windows 为物理像素）
+fn game_monitor_rect(game: &xcap::Window) -> Option<(i32, i32, u32, u32)> {
+    let cx = game.x() + game.width() as i32 / 2;
+    let cy = game.y() + game.height() as i32 / 2;
+    xcap::Monitor::all().ok()?.iter()
+        .map(|m| (m.x(), m.y(), m.width(), m.height()))
+        .find(|&(x, y, w, h)| cx >= x && cx < x + w as i32 && cy >= y && cy < y + h as i32)
+}
+
+// 把 overlay 移动并缩放到游戏窗口中心所在的显示器；current 记录上次应用的显示器原点，未变化时不重复设置
+fn move_overlay_to_game_monitor(overlay: &tauri::WebviewWindow, game: &xcap::Window, current: &mut Option<(i32, i32)>) {
+    let Some((x, y, w, h)) = game_monitor_rect(game) else { return };
+    if *current == Some((x, y)) {
+        return;
+    }
+    println!("[Overlay] Game is on monitor at ({}, {}) {}x{}, moving overlay", x, y, w, h);
+    #[cfg(target_os = "macos")]
+    {
+        let _ = overlay.set_size(tauri::LogicalSize::new(w as f64, h as f64));
+        let _ = overlay.set_position(tauri::LogicalPosition::new(x as f64, y as f64));
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = overlay.set_size(tauri::PhysicalSize::new(w, h));
+        let _ = overlay.set_position(tauri::PhysicalPosition::new(x, y));
+    }
+    *current = Some((x, y));
+}
+
+// 托盘“显示”/左键单击：恢复主窗口，overlay 已揭示时一并显示
+#[cfg(target_os = "windows")]
+fn show_main_from_tray(app: &tauri::AppHandle) {
+    if let Some(main_win) = app.get_webview_window("main") {
+        let _ = main_win.show();
+        let _ = main_win.unminimize();
+        let _ = main_win.set_focus();
+    }
+    if OVERLAY_REVEALED.load(Ordering::SeqCst) {
+        if let Some(overlay_win) = app.get_webview_window("overlay") {
+            let _ = overlay_win.show();
+        }
+    }
+}
+
+// x/y 是详情面板中心点在 overlay 上的百分比坐标；把面板整体限制在 overlay 所在显示器的工作区内
+fn clamp_detail_position(app: &tauri::AppHandle, x: i32, y: i32, scale: i32, width: i32, height: i32) -> Option<(i32, i32)> {
+    let overlay = app.get_webview_window("overlay")?;
+    let ov_pos = overlay.outer_position().ok()?;
+    let ov_size = overlay.outer_size().ok()?;
+    if ov_size.width == 0 || ov_size.height == 0 { return None; }
+
+    let monitors = overlay.available_monitors().ok()?;
+    let monitor = monitors.iter().find(|m| {
+        let p = m.position();
+        let s = m.size();
+        ov_pos.x >= p.x && ov_pos.x < p.x + s.width as i32 && ov_pos.y >= p.y && ov_pos.y < p.y + s.height as i32
+    }).or_else(|| monitors.first())?;
+
+    let work = monitor.work_area();
+    let factor = monitor.scale_factor() * scale.max(1) as f64 / 100.0;
+    let half_w = width as f64 * factor / 2.0;
+    let half_h = height as f64 * factor / 2.0;
+
+    let clamp_axis = |pct: i32, ov_origin: i32, ov_len: u32, area_origin: i32, area_len: u32, half: f64| -> i32 {
+        let min_c = area_origin as f64 + half;
+        let max_c = area_origin as f64 + area_len as f64 - half;
+        let center = ov_origin as f64 + pct as f64 / 100.0 * ov_len as f64;
+        // 面板比工作区还大时居中
+        let center = if min_c > max_c { area_origin as f64 + area_len as f64 / 2.0 } else { center.clamp(min_c, max_c) };
+        ((center - ov_origin as f64) / ov_len as f64 * 100.0).round() as i32
+    };
+
+    Some((
+        clamp_axis(x, ov_pos.x, ov_size.width, work.position.x, work.size.width, half_w),
+        clamp_axis(y, ov_pos.y, ov_size.height, work.position.y, work.size.height, half_h),
+    ))
+}
+
 #[tauri::command]
 #[allow(non_snake_case)]
 async fn trigger_yolo_scan(app: tauri::AppHandle, useGpu: bool) -> Result<usize, String> {
@@ -429,36 +700,26 @@ async fn trigger_yolo_scan(app: tauri::AppHandle, useGpu: bool) -> Result<usize,
     
     // Frontend and backend now use canonical `useGpu` parameter
     let use_gpu_flag = useGpu;
-    use xcap::{Window, Monitor};
+    use xcap::Monitor;
     
     // Notify frontend scan started
     let _ = app.emit("yolo-scan-start", ());
 
     let result = (|| -> Result<usize, String> {
         let resources_path = app.path().resource_dir().map_err(|e| e.to_string())?;
-        let model_path = resources_path.join("resources").join("models").join("best.onnx");
+        let model_path = monster_recognition::active_yolo_model_path(&resources_path);
 
         if ABORT_YOLO.load(Ordering::SeqCst) { return Err("Aborted".into()); }
 
         // 1. 获取 The Bazaar 窗口截图，如果未找到则使用主屏幕截图
-        let windows = Window::all().map_err(|e| e.to_string())?;
+        // 优先寻找游戏窗口
+        let target_window = monster_recognition::find_game_window(None)?;
         
         if ABORT_YOLO.load(Ordering::SeqCst) { return Err("Aborted".into()); }
 
-        // 优先寻找游戏窗口
-        let target_window = windows.iter().find(|w| {
-            let title = w.title().to_lowercase();
-            let app_name = w.app_name().to_lowercase();
-            let is_bazaar = title.contains("the bazaar") || app_name.contains("the bazaar") || 
-                            title.contains("thebazaar") || app_name.contains("thebazaar");
-            is_bazaar && !title.contains("bazaarhelper")
-        });
-
-        let (screenshot, window_x, window_y) = if let Some(w) = target_window {
+        let (screenshot, window_x, window_y) = if let Some(w) = &target_window {
             println!("[YOLO] Found Game Window: '{}' at ({},{})", w.title(), w.x(), w.y());
-            let wx = w.x();
-            let wy = w.y();
-            (w.capture_image().map_err(|e| e.to_string())?, wx, wy)
+            monster_recognition::capture_game_window(w)?
         } else {
             println!("[YOLO] The Bazaar window not found, falling back to primary monitor scan.");
             let monitors = Monitor::all().map_err(|e| e.to_string())?;
@@ -472,7 +733,8 @@ async fn trigger_yolo_scan(app: tauri::AppHandle, useGpu: bool) -> Result<usize,
         
         // 2. YOLO 识别
         println!("[YOLO] Starting manual scan with GPU acceleration: {}...", use_gpu_flag);
-        let detections = monster_recognition::run_yolo_inference(&img, &model_path, use_gpu_flag)?;
+        let state = load_state();
+        let detections = monster_recognition::run_yolo_on_capture(&img, &model_path, use_gpu_flag, state.nms_strategy, state.yolo_conf_threshold, state.yolo_iou_threshold)?;
         
         if ABORT_YOLO.load(Ordering::SeqCst) { return Err("Aborted".into()); }
 
@@ -481,13 +743,50 @@ async fn trigger_yolo_scan(app: tauri::AppHandle, useGpu: bool) -> Result<usize,
         // ... (rest of the debug printing and saving)
         // (existing code)
         // 3. 保存结果和窗口偏移量
-        {
+        // 截图降采样到最长边不超过 YOLO_STORED_IMAGE_MAX_SIDE，检测框同步缩放
+        let (img, detections, stored_scale) = {
+            use image::GenericImageView;
+            let (w, h) = img.dimensions();
+            let max_side = w.max(h);
+            if max_side > YOLO_STORED_IMAGE_MAX_SIDE {
+                let scale = YOLO_STORED_IMAGE_MAX_SIDE as f32 / max_side as f32;
+                let (nw, nh) = (((w as f32 * scale).round() as u32).max(1), ((h as f32 * scale).round() as u32).max(1));
+                let resized = img.resize_exact(nw, nh, image::imageops::FilterType::Triangle);
+                drop(img);
+                let scaled = detections.into_iter().map(|d| YoloDetection {
+                    x1: (d.x1 as f32 * scale).round() as i32,
+                    y1: (d.y1 as f32 * scale).round() as i32,
+                    x2: (d.x2 as f32 * scale).round() as i32,
+                    y2: (d.y2 as f32 * scale).round() as i32,
+                    ..d
+                }).collect::<Vec<_>>();
+                println!("[YOLO] Downsampled stored screenshot {}x{} -> {}x{}", w, h, nw, nh);
+                (resized, scaled, scale)
+            } else {
+                (img, detections, 1.0)
+            }
+        };
+        let detections = {
+            use image::GenericImageView;
+            // 只有截图尺寸不变时框坐标才可比较
+            let same_size = get_yolo_scan_image().read().unwrap().as_ref().map(|prev| prev.dimensions()) == Some(img.dimensions());
             let mut results = get_yolo_scan_results().write().unwrap();
+            let detections = if load_state().merge_scan_detections && same_size && !results.is_empty() {
+                let (merged, kept, added, removed) = monster_recognition::merge_detections(&results, detections, 0.7);
+                println!("[YOLO] Merged with previous scan: kept {}, added {}, removed {}", kept, added, removed);
+                merged
+            } else {
+                detections
+            };
             *results = detections.clone();
-        }
+            detections
+        };
         {
+            // 先释放旧图再存新图，连续扫描时不会同时持有两张截图
             let mut saved_img = get_yolo_scan_image().write().unwrap();
-            *saved_img = Some(img);
+            drop(saved_img.take());
+            *saved_img = Some(Arc::new(img));
+            *YOLO_SCAN_IMAGE_SCALE.write().unwrap() = stored_scale;
         }
         {
             let mut offset = get_yolo_window_offset().write().unwrap();
@@ -501,6 +800,9 @@ async fn trigger_yolo_scan(app: tauri::AppHandle, useGpu: bool) -> Result<usize,
     match &result {
         Ok(count) => {
             println!("[YOLO] Scan succeeded with {} detections", count);
+            if *count > 0 {
+                reveal_overlay_on_first_scan(&app);
+            }
             let _ = app.emit("yolo-scan-end", ());
         }
         Err(e) if e == "Aborted" => {
@@ -524,16 +826,7 @@ async fn handle_overlay_right_click(app: tauri::AppHandle, x: i32, y: i32) -> Re
     
     // 动态获取游戏窗口位置，如果找不到则使用保存的偏移量
     let (window_x, window_y, window_logical_width, window_logical_height) = {
-        let game_window = xcap::Window::all()
-            .ok()
-            .and_then(|windows| {
-                windows.into_iter().find(|w| {
-                    let title = w.title().to_lowercase();
-                    let app_name = w.app_name().to_lowercase();
-                    title.contains("the bazaar") || app_name.contains("the bazaar") || 
-                    title.contains("thebazaar") || app_name.contains("thebazaar")
-                })
-            });
+        let game_window = monster_recognition::find_game_window(None).ok().flatten();
         
         if let Some(window) = game_window {
             (window.x(), window.y(), window.width(), window.height())
@@ -563,16 +856,15 @@ async fn handle_overlay_right_click(app: tauri::AppHandle, x: i32, y: i32) -> Re
         // 取平均值，通常两个方向的缩放比例应该相同
         (scale_x + scale_y) / 2.0
     } else {
-        // 降级方案：根据图像大小估算
+        // 降级方案：根据原始截图大小估算，再乘以保存时的降采样比例
+        let stored_scale = *YOLO_SCAN_IMAGE_SCALE.read().unwrap();
+        let orig_w = (img_w as f32 / stored_scale).round() as u32;
         #[cfg(target_os = "macos")]
-        {
-            if img_w > 1920 { 2.0 } else { 1.0 }
-        }
+        let estimated = if orig_w > 1920 { 2.0 } else { 1.0 };
+        // Windows: 常见的DPI缩放比例
         #[cfg(not(target_os = "macos"))]
-        {
-            // Windows: 常见的DPI缩放比例
-            if img_w > 3000 { 1.5 } else { 1.0 }
-        }
+        let estimated = if orig_w > 3000 { 1.5 } else { 1.0 };
+        estimated * stored_scale
     };
     
     let rel_x = (rel_x_logical as f32 * scale_factor) as i32;
@@ -583,117 +875,248 @@ async fn handle_overlay_right_click(app: tauri::AppHandle, x: i32, y: i32) -> Re
     println!("[DEBUG] Image dimensions: {}x{}, Total detections: {}", img_w, img_h, detections.len());
     
     for (i, d) in detections.iter().enumerate() {
-        println!("[DEBUG] Detection {}: class={}, bounds=[{},{},{},{}], size={}x{}", 
-                 i, d.class_id, d.x1, d.y1, d.x2, d.y2, d.x2 - d.x1, d.y2 - d.y1);
+        println!("[DEBUG] Detection {}: class={} ({}), bounds=[{},{},{},{}], size={}x{}", 
+                 i, d.class_id, monster_recognition::yolo_class_name(d.class_id), d.x1, d.y1, d.x2, d.y2, d.x2 - d.x1, d.y2 - d.y1);
     }
 
-    // Check for any detection hit (使用物理像素坐标)
-    let target_detection = detections.iter().find(|d| {
+    // 收集所有命中的框 (使用物理像素坐标)，重叠时按类别优先级、再按面积从小到大（更精确的框优先）
+    let mut hits: Vec<&YoloDetection> = detections.iter().filter(|d| {
         rel_x >= d.x1 && rel_x <= d.x2 && rel_y >= d.y1 && rel_y <= d.y2
-    });
+    }).collect();
+    hits.sort_by_key(|d| (hit_priority(d), detection_area(d)));
+
+    let Some(&best) = hits.first() else { return Ok(None) };
+    for det in &hits {
+        let msg = format!("[YOLO Click] Hit Class {} ({}) at [{}, {}, {}, {}]", det.class_id, monster_recognition::yolo_class_name(det.class_id), det.x1, det.y1, det.x2, det.y2);
+        println!("{}", msg);
+        log_to_file(&msg);
+    }
 
-    if let Some(det) = target_detection {
-        println!("[YOLO Click] Clicked on Class {} at [{}, {}, {}, {}]", det.class_id, det.x1, det.y1, det.x2, det.y2);
-        
-        let w = (det.x2 - det.x1).max(50) as u32;
-        let h = (det.y2 - det.y1).max(50) as u32;
-        let crop_x = det.x1.max(0) as u32;
-        let crop_y = det.y1.max(0) as u32;
-        
-        let (img_w, img_h) = img.dimensions();
-        let final_w = if crop_x + w > img_w { img_w - crop_x } else { w };
-        let final_h = if crop_y + h > img_h { img_h - crop_y } else { h };
-        
-        let cropped = img.crop_imm(crop_x, crop_y, final_w, final_h);
-        let scene_desc = monster_recognition::extract_features_from_dynamic_image(&cropped, 1000)
-            .map_err(|e| e.to_string())?;
-            
-        if scene_desc.empty() {
-            return Ok(None);
+    // 同级命中：优先级相同且面积接近最小框，无法判断用户点的是哪一个
+    let best_area = detection_area(best).max(1) as f32;
+    let tied: Vec<&YoloDetection> = hits.iter().copied()
+        .filter(|d| hit_priority(d) == hit_priority(best) && detection_area(d) as f32 / best_area <= SAME_LEVEL_AREA_RATIO)
+        .collect();
+    if tied.len() == 1 {
+        return resolve_detection(&app, &img, best, &detections).await;
+    }
+
+    // 多个同级命中分别识别，识别成功不止一个时返回候选列表由前端弹出选择菜单
+    let mut resolved = Vec::new();
+    for det in tied {
+        if let Some(result) = resolve_detection(&app, &img, det, &detections).await? {
+            resolved.push(result);
+        }
+    }
+    match resolved.len() {
+        0 => Ok(None),
+        1 => Ok(resolved.pop()),
+        n => {
+            println!("[YOLO Click] {} overlapping detections resolved, returning candidates", n);
+            Ok(Some(serde_json::json!({ "type": "candidates", "data": resolved })))
         }
+    }
+}
+
+// 面积不超过最小命中框的该倍数时视为同级
+const SAME_LEVEL_AREA_RATIO: f32 = 1.2;
+
+// 右键命中的类别优先级（越小越优先）：卡牌 > 怪物图标 > 事件 > 其他无法识别的类别
+fn hit_priority(det: &YoloDetection) -> u8 {
+    if det.is_class(CLASS_ITEM) || det.is_class(CLASS_SKILL) {
+        0
+    } else if det.is_class(CLASS_MONSTER_ICON) {
+        1
+    } else if det.is_class(CLASS_EVENT) {
+        2
+    } else {
+        3
+    }
+}
 
-        // names: ['day', 'event', 'item', 'monstericon', 'randomicon', 'shopicon', 'skill']
-        // 0: day, 1: event, 2: item, 3: monstericon, 4: randomicon, 5: shopicon, 6: skill
+fn detection_area(det: &YoloDetection) -> i64 {
+    (det.x2 - det.x1).max(0) as i64 * (det.y2 - det.y1).max(0) as i64
+}
 
-        if det.class_id == 2 || det.class_id == 6 {
-            // Item (2) or Skill (6) -> Card Recognition
-            let match_result = monster_recognition::match_card_descriptors(&scene_desc)?;
-            if let Some(cards) = match_result {
-                let card_list = cards.as_array().unwrap();
-                if !card_list.is_empty() {
-                    let card_id = card_list[0]["id"].as_str().unwrap_or("").to_string();
-                    let db_state = app.state::<DbState>();
-                    if let Some(info) = get_item_info_internal(&db_state, card_id).await {
-                        return Ok(Some(serde_json::json!({ "type": "item", "data": info })));
-                    }
+// 按检测框类别裁剪并分派到对应的 ORB 识别，返回 { type: item | monster | event, data }
+async fn resolve_detection(app: &tauri::AppHandle, img: &image::DynamicImage, det: &YoloDetection, detections: &[YoloDetection]) -> Result<Option<serde_json::Value>, String> {
+    use image::GenericImageView;
+    
+    // 过小的检测框按配置 min_crop_size 扩到最小边长，与其他裁剪入口一致
+    let min_size = load_state().min_crop_size as i32;
+    let w = (det.x2 - det.x1).max(min_size) as u32;
+    let h = (det.y2 - det.y1).max(min_size) as u32;
+    let crop_x = det.x1.max(0) as u32;
+    let crop_y = det.y1.max(0) as u32;
+    
+    let (img_w, img_h) = img.dimensions();
+    let final_w = if crop_x + w > img_w { img_w - crop_x } else { w };
+    let final_h = if crop_y + h > img_h { img_h - crop_y } else { h };
+    
+    let cropped = img.crop_imm(crop_x, crop_y, final_w, final_h);
+    let scene_desc = monster_recognition::extract_features_from_dynamic_image(&cropped, 1000)
+        .map_err(|e| e.to_string())?;
+        
+    if scene_desc.empty() {
+        return Ok(None);
+    }
+
+    // 类别 id 由 yolo_classes.json 决定，这里只按类别名判断
+
+    if det.is_class(CLASS_ITEM) || det.is_class(CLASS_SKILL) {
+        // Item or Skill -> Card Recognition
+        let match_result = monster_recognition::match_card_descriptors(&scene_desc)?;
+        if let Some(cards) = match_result {
+            let card_list = cards.as_array().unwrap();
+            if !card_list.is_empty() {
+                let card_id = card_list[0]["id"].as_str().unwrap_or("").to_string();
+                let db_state = app.state::<DbState>();
+                if let Some(info) = get_item_info_internal(&db_state, card_id).await {
+                    return Ok(Some(serde_json::json!({ "type": "item", "data": info })));
                 }
             }
-        } else if det.class_id == 1 {
-            // Event (1) -> Check for Monster Icon (3) overlap
-            // Logic: Is there any Icon (3) inside this Event (1) with > 50% area overlap (relative to Icon)?
-            let monster_icons: Vec<&YoloDetection> = detections.iter().filter(|d| d.class_id == 3).collect();
-            let mut is_monster = false;
+        }
+    } else if det.is_class(CLASS_EVENT) {
+        // Event -> Check for Monster Icon overlap
+        // Logic: Is there any Icon inside this Event with > monster_overlap_ratio area overlap (relative to Icon)?
+        let overlap_ratio = load_state().monster_overlap_ratio;
+        let monster_icons: Vec<&YoloDetection> = detections.iter().filter(|d| d.is_class(CLASS_MONSTER_ICON)).collect();
+        let mut is_monster = false;
+        
+        for icon in monster_icons {
+            // Calculate Intersection
+            let ix1 = det.x1.max(icon.x1);
+            let iy1 = det.y1.max(icon.y1);
+            let ix2 = det.x2.min(icon.x2);
+            let iy2 = det.y2.min(icon.y2);
             
-            for icon in monster_icons {
-                // Calculate Intersection
-                let ix1 = det.x1.max(icon.x1);
-                let iy1 = det.y1.max(icon.y1);
-                let ix2 = det.x2.min(icon.x2);
-                let iy2 = det.y2.min(icon.y2);
-                
-                let i_area = (ix2 - ix1).max(0) * (iy2 - iy1).max(0);
-                let icon_full_area = (icon.x2 - icon.x1) * (icon.y2 - icon.y1);
-                
-                if icon_full_area > 0 && (i_area as f32 / icon_full_area as f32) > 0.5 {
-                    is_monster = true;
-                    break;
-                }
-            }
+            let i_area = (ix2 - ix1).max(0) * (iy2 - iy1).max(0);
+            let icon_full_area = (icon.x2 - icon.x1) * (icon.y2 - icon.y1);
             
-            if is_monster {
-                let monster_match = monster_recognition::match_monster_descriptors_from_mat(&scene_desc)?;
-                if let Some(monster_name) = monster_match {
-                    let db_state = app.state::<DbState>();
-                    let monsters = db_state.monsters.read().unwrap();
-                    if let Some(m) = monsters.get(&monster_name) {
-                        return Ok(Some(serde_json::json!({ "type": "monster", "data": m })));
-                    }
-                }
-            } else {
-                // Pure event (no monster icon) -> Event Recognition
-                let event_match = monster_recognition::match_event_descriptors_from_mat(&scene_desc)?;
-                if let Some(event_id) = event_match {
-                    // 读取 event_encounters.json 获取完整事件数据
-                    let event_json_path = app.path().resolve("resources/event_encounters.json", tauri::path::BaseDirectory::Resource)
-                        .map_err(|e| format!("Failed to resolve event_encounters.json: {}", e))?;
-                    
-                    if let Ok(json_data) = std::fs::read_to_string(&event_json_path) {
-                        if let Ok(events) = serde_json::from_str::<Vec<serde_json::Value>>(&json_data) {
-                            if let Some(event) = events.iter().find(|e| e.get("Id").and_then(|v| v.as_str()) == Some(&event_id)) {
-                                return Ok(Some(serde_json::json!({ "type": "event", "data": event })));
-                            }
-                        }
-                    }
+            if icon_full_area > 0 && (i_area as f32 / icon_full_area as f32) > overlap_ratio {
+                is_monster = true;
+                break;
+            }
+        }
+        
+        if is_monster {
+            let monster_match = monster_recognition::match_monster_descriptors_from_mat(&scene_desc)?;
+            if let Some(monster_name) = monster_match {
+                let db_state = app.state::<DbState>();
+                let monsters = db_state.monsters.read().unwrap();
+                if let Some(m) = monsters.get(&monster_name) {
+                    return Ok(Some(serde_json::json!({ "type": "monster", "data": m })));
                 }
             }
         } else {
-             // Fallback or other classes (e.g. 3 directly?)
-             // Monster recognition for direct MonsterIcon (3) or others if needed
-             if det.class_id == 3 {
-                 let monster_match = monster_recognition::match_monster_descriptors_from_mat(&scene_desc)?;
-                 if let Some(monster_name) = monster_match {
-                     let db_state = app.state::<DbState>();
-                     let monsters = db_state.monsters.read().unwrap();
-                     if let Some(m) = monsters.get(&monster_name) {
-                         return Ok(Some(serde_json::json!({ "type": "monster", "data": m })));
-                     }
+            // Pure event (no monster icon) -> Event Recognition
+            // 相似事件容易混淆：返回最佳事件，同时在 alternatives 里附上 Top-3 候选的详情供前端切换
+            let candidates = monster_recognition::match_event_candidates(&scene_desc)?;
+            // 从启动时加载的事件库获取完整事件数据
+            let db_state = app.state::<DbState>();
+            let events = db_state.events.read().unwrap();
+            let alternatives: Vec<serde_json::Value> = candidates.iter()
+                .filter_map(|c| events.get(&c.id).map(|event| serde_json::json!({
+                    "id": c.id,
+                    "name": c.name,
+                    "match_count": c.match_count,
+                    "confidence": c.confidence,
+                    "data": event,
+                })))
+                .collect();
+            if let Some(best) = alternatives.first() {
+                return Ok(Some(serde_json::json!({ "type": "event", "data": best["data"], "alternatives": alternatives })));
+            }
+        }
+    } else {
+         // Fallback or other classes (e.g. 3 directly?)
+         // Monster recognition for direct MonsterIcon or others if needed
+         if det.is_class(CLASS_MONSTER_ICON) {
+             let monster_match = monster_recognition::match_monster_descriptors_from_mat(&scene_desc)?;
+             if let Some(monster_name) = monster_match {
+                 let db_state = app.state::<DbState>();
+                 let monsters = db_state.monsters.read().unwrap();
+                 if let Some(m) = monsters.get(&monster_name) {
+                     return Ok(Some(serde_json::json!({ "type": "monster", "data": m })));
                  }
              }
-        }
+         }
     }
     Ok(None)
 }
 
+// “这是什么”：截取鼠标周围区域，先用 YOLO 判断鼠标下物体的类别，再分派到对应的 ORB 识别
+// 返回与右键识别相同的 { type: item | monster | event, data }
+#[tauri::command]
+async fn identify_at_cursor(app: tauri::AppHandle, use_gpu: Option<bool>) -> Result<Option<serde_json::Value>, String> {
+    use image::GenericImageView;
+    use xcap::Monitor;
+
+    let (mouse_x, mouse_y) = monster_recognition::get_cursor_position()?;
+
+    let bazaar_window = monster_recognition::find_game_window(None)?;
+
+    let (screenshot, win_x, win_y) = if let Some(window) = bazaar_window {
+        monster_recognition::capture_game_window(&window)?
+    } else {
+        let monitors = Monitor::all().map_err(|e| e.to_string())?;
+        let target_monitor = monitors.into_iter().find(|m| {
+            mouse_x >= m.x() && mouse_x < m.x() + m.width() as i32 &&
+            mouse_y >= m.y() && mouse_y < m.y() + m.height() as i32
+        }).ok_or("Mouse is not within any monitor bounds")?;
+        (target_monitor.capture_image().map_err(|e| e.to_string())?, target_monitor.x(), target_monitor.y())
+    };
+
+    let img = image::DynamicImage::ImageRgba8(screenshot);
+    let (img_w, img_h) = img.dimensions();
+    let rel_x = mouse_x - win_x;
+    let rel_y = mouse_y - win_y;
+    if rel_x < 0 || rel_y < 0 || rel_x >= img_w as i32 || rel_y >= img_h as i32 {
+        return Err("Cursor is outside the captured area".into());
+    }
+    let (inset_x, inset_y, inset_w, inset_h) = load_state().capture_inset.region(img_w, img_h);
+    if rel_x < inset_x as i32 || rel_y < inset_y as i32 || rel_x >= (inset_x + inset_w) as i32 || rel_y >= (inset_y + inset_h) as i32 {
+        return Err("Cursor is inside the excluded capture inset".into());
+    }
+
+    // 以鼠标为中心截取边长为屏幕高度 60% 的正方形，足以容纳一张事件/怪物卡
+    let side = ((img_h as f32 * 0.6).round() as u32).min(img_w).min(img_h);
+    let crop_x = (rel_x - side as i32 / 2).clamp(0, (img_w - side) as i32) as u32;
+    let crop_y = (rel_y - side as i32 / 2).clamp(0, (img_h - side) as i32) as u32;
+    let crop = img.crop_imm(crop_x, crop_y, side, side);
+    let local_x = rel_x - crop_x as i32;
+    let local_y = rel_y - crop_y as i32;
+
+    let resources_path = app.path().resource_dir().map_err(|e| e.to_string())?;
+    let model_path = monster_recognition::active_yolo_model_path(&resources_path);
+    let state = load_state();
+    let detections = monster_recognition::run_yolo_inference(&crop, &model_path, use_gpu.unwrap_or(false), state.nms_strategy, state.yolo_conf_threshold, state.yolo_iou_threshold)?;
+
+    let contains_cursor = |d: &&YoloDetection| local_x >= d.x1 && local_x <= d.x2 && local_y >= d.y1 && local_y <= d.y2;
+    let area = |d: &&YoloDetection| (d.x2 - d.x1) * (d.y2 - d.y1);
+    // 物品在事件框内时取面积最小的框；怪物图标只在没有事件框时才直接使用
+    let target = detections.iter()
+        .filter(contains_cursor)
+        .filter(|d| d.is_class(CLASS_ITEM) || d.is_class(CLASS_SKILL) || d.is_class(CLASS_EVENT))
+        .min_by_key(area)
+        .or_else(|| detections.iter().filter(contains_cursor).filter(|d| d.is_class(CLASS_MONSTER_ICON)).min_by_key(area));
+
+    let det = match target {
+        Some(d) => d,
+        None => {
+            println!("[Identify] No object under cursor ({} detections in crop)", detections.len());
+            return Ok(None);
+        }
+    };
+    println!("[Identify] Cursor over class {} ({}) at [{}, {}, {}, {}]", det.class_id, monster_recognition::yolo_class_name(det.class_id), det.x1, det.y1, det.x2, det.y2);
+
+    let result = resolve_detection(&app, &crop, det, &detections).await?;
+    if result.is_some() {
+        reveal_overlay_on_first_scan(&app);
+    }
+    Ok(result)
+}
+
 async fn get_item_info_internal(state: &DbState, id: String) -> Option<ItemData> {
     let db = state.items.read().unwrap();
     if let Some(&idx) = db.id_map.get(&id) {
@@ -703,17 +1126,77 @@ async fn get_item_info_internal(state: &DbState, id: String) -> Option<ItemData>
 }
 
 // --- Logger Helper ---
-pub fn log_to_file(msg: &str) {
-    if let Ok(mut exe_path) = std::env::current_exe() {
-        exe_path.pop();
-        exe_path.push("app_debug.txt");
-        if let Ok(mut f) = std::fs::OpenOptions::new().create(true).append(true).open(exe_path) {
-            let _ = writeln!(f, "[{}] {}", get_time_str(), msg);
-            let _ = f.flush();
+// 调试日志写到 app_log_dir（setup 时由 init_log_dir 设置），此前的早期日志仍写到 exe 目录；
+// 超过 LOG_MAX_BYTES 时轮转为 app_debug.1.txt ~ app_debug.{LOG_MAX_BACKUPS}.txt
+static LOG_DIR: OnceLock<PathBuf> = OnceLock::new();
+// 多线程同时写入时保证轮转和追加不交错
+static LOG_WRITE_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+const LOG_MAX_BYTES: u64 = 5 * 1024 * 1024;
+const LOG_MAX_BACKUPS: usize = 3;
+
+pub fn init_log_dir(app: &tauri::AppHandle) {
+    let dir = match app.path().app_log_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            eprintln!("[Log] app_log_dir unavailable: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = std::fs::create_dir_all(&dir) {
+        eprintln!("[Log] Failed to create log dir {:?}: {}", dir, e);
+        return;
+    }
+    let _ = LOG_DIR.set(dir);
+}
+
+fn debug_log_dir() -> Option<PathBuf> {
+    if let Some(dir) = LOG_DIR.get() {
+        return Some(dir.clone());
+    }
+    let mut exe_path = std::env::current_exe().ok()?;
+    exe_path.pop();
+    Some(exe_path)
+}
+
+fn debug_log_file(dir: &std::path::Path, index: usize) -> PathBuf {
+    if index == 0 {
+        dir.join("app_debug.txt")
+    } else {
+        dir.join(format!("app_debug.{}.txt", index))
+    }
+}
+
+// app_debug.txt -> .1 -> .2 -> ...，最旧的一份被覆盖
+fn rotate_debug_logs(dir: &std::path::Path) {
+    let _ = std::fs::remove_file(debug_log_file(dir, LOG_MAX_BACKUPS));
+    for i in (0..LOG_MAX_BACKUPS).rev() {
+        let from = debug_log_file(dir, i);
+        if from.exists() {
+            let _ = std::fs::rename(&from, debug_log_file(dir, i + 1));
         }
     }
 }
 
+pub fn log_to_file(msg: &str) {
+    let line = format!("[{}] {}", get_time_str(), msg);
+    let Some(dir) = debug_log_dir() else {
+        eprintln!("{}", line);
+        return;
+    };
+
+    let _guard = LOG_WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let path = debug_log_file(&dir, 0);
+    if std::fs::metadata(&path).map(|m| m.len() >= LOG_MAX_BYTES).unwrap_or(false) {
+        rotate_debug_logs(&dir);
+    }
+    let result = std::fs::OpenOptions::new().create(true).append(true).open(&path)
+        .and_then(|mut f| writeln!(f, "{}", line));
+    if let Err(e) = result {
+        // 目录无写权限等情况退回 stderr
+        eprintln!("[Log] Failed to write {:?} ({}): {}", path, e, line);
+    }
+}
+
 fn get_time_str() -> String {
     Local::now().format("%Y-%m-%d %H:%M:%S%.3f").to_string()
 }
@@ -756,6 +1239,7 @@ pub fn log_system_info(app_handle: &tauri::AppHandle) {
     log_to_file(&format!("Resource Dir: {:?}", app_handle.path().resource_dir().ok()));
     log_to_file(&format!("App Config Dir: {:?}", app_handle.path().app_config_dir().ok()));
     log_to_file(&format!("App Local Data Dir: {:?}", app_handle.path().app_local_data_dir().ok()));
+    log_to_file(&format!("Debug Log Dir: {:?}", debug_log_dir()));
     
     // Log environment variables that might affect execution
     for var in ["PATH", "USERNAME", "APPDATA", "LOCALAPPDATA"] {
@@ -779,17 +1263,116 @@ pub struct PersistentState {
     pub current_hand: HashSet<String>,
     pub current_stash: HashSet<String>,
     #[serde(default)]
-    pub detection_hotkey: Option<i32>,
+    pub detection_hotkey: Option<Hotkey>,
     #[serde(default)]
-    pub card_detection_hotkey: Option<i32>,
+    pub card_detection_hotkey: Option<Hotkey>,
     #[serde(default)]
-    pub toggle_collapse_hotkey: Option<i32>,
+    pub toggle_collapse_hotkey: Option<Hotkey>,
     #[serde(default)]
-    pub yolo_hotkey: Option<i32>,
+    pub yolo_hotkey: Option<Hotkey>,
     #[serde(default)]
-    pub detail_display_hotkey: Option<i32>,
+    pub detail_display_hotkey: Option<Hotkey>,
     #[serde(default = "default_show_yolo_monitor")]
     pub show_yolo_monitor: bool,
+    // monstericon 与 event 框的重叠面积占 icon 面积的比例超过该值时，判定该 event 为怪物
+    #[serde(default = "default_monster_overlap_ratio")]
+    pub monster_overlap_ratio: f32,
+    // 识别前裁剪区域的最小边长（像素），小于该值说明鼠标贴近屏幕/窗口边缘
+    #[serde(default = "default_min_crop_size")]
+    pub min_crop_size: u32,
+    // ORB 提取前对灰度图做 CLAHE 对比度增强（模板会用独立缓存重新生成，重启后生效）
+    #[serde(default)]
+    pub enhance_contrast: bool,
+    // 启动时不显示 overlay，直到第一次识别/YOLO 扫描出结果
+    #[serde(default)]
+    pub overlay_show_on_first_scan: bool,
+    // 该时间窗口（毫秒）内相同的识别结果/错误不重复推送给前端，0 表示不去重
+    #[serde(default = "default_toast_dedup_ms")]
+    pub toast_dedup_ms: u64,
+    // 启动时是否自动检查更新（按流量计费的网络可关闭）
+    #[serde(default = "default_auto_check_updates")]
+    pub auto_check_updates: bool,
+    // YOLO 后处理的 NMS 策略
+    #[serde(default)]
+    pub nms_strategy: monster_recognition::NmsStrategy,
+    // NMS 后只保留置信度最高的 N 个检测框，0 表示不限制
+    #[serde(default)]
+    pub max_detections: usize,
+    // YOLO 置信度阈值与 NMS IoU 阈值，范围 (0, 1]
+    #[serde(default = "default_yolo_conf_threshold")]
+    pub yolo_conf_threshold: f32,
+    #[serde(default = "default_yolo_iou_threshold")]
+    pub yolo_iou_threshold: f32,
+    // 新扫描结果与上一次按 IoU 合并，稳定的框保持不动，避免连续扫描时 overlay 闪烁
+    #[serde(default)]
+    pub merge_scan_detections: bool,
+    // 窗口截图为空白（独占全屏）时自动改为截取显示器
+    #[serde(default = "default_fullscreen_capture_fallback")]
+    pub fullscreen_capture_fallback: bool,
+    // 全库怪物识别前先用 dHash 预筛选，只对最相似的 phash_top_k 个模板做 ORB 匹配
+    #[serde(default)]
+    pub phash_prefilter: bool,
+    #[serde(default = "default_phash_top_k")]
+    pub phash_top_k: usize,
+    // 最近一次识别怪物后自动跳转的上下文，重启后可恢复（restore_last_jump 开启时）
+    #[serde(default)]
+    pub last_jumped_day: Option<u32>,
+    #[serde(default)]
+    pub last_recognized_monster: Option<String>,
+    #[serde(default)]
+    pub restore_last_jump: bool,
+    // YOLO 扫描前裁掉截图四周的百分比边距（排除常驻 HUD）
+    #[serde(default)]
+    pub capture_inset: monster_recognition::CaptureInset,
+    // ORB 特征提取参数（模板与截图共用，修改后重启生效并自动重建缓存）
+    #[serde(default)]
+    pub orb_params: monster_recognition::OrbParams,
+    // 查找游戏窗口时排除的标题/进程名关键字（不区分大小写），避免截到标题里带 "The Bazaar" 的其他程序
+    #[serde(default = "default_excluded_window_titles")]
+    pub excluded_window_titles: Vec<String>,
+    // Windows: 关闭主窗口时隐藏到托盘而不是退出
+    #[serde(default)]
+    pub minimize_to_tray: bool,
+    // 日志进入商店/事件等状态时自动请求一次 YOLO 扫描（默认关闭）
+    #[serde(default)]
+    pub auto_yolo_on_state_change: bool,
+    // 自定义 Player.log 路径（Steam 库迁移 / 自定义 APPDATA 时使用），文件不存在则回退默认路径
+    #[serde(default)]
+    pub custom_log_path: Option<String>,
+    // overlay 详情框的边框颜色（十六进制）与背景不透明度
+    #[serde(default = "default_overlay_box_color")]
+    pub overlay_box_color: String,
+    #[serde(default = "default_overlay_opacity")]
+    pub overlay_opacity: f32,
+    // 模板特征提取算法："orb"（默认）或 "akaze"，修改后重启生效并使用对应的缓存文件
+    #[serde(default = "default_feature_algorithm")]
+    pub feature_algorithm: String,
+    // resources/models 下当前使用的 YOLO 模型文件名，切换后下次扫描即生效
+    #[serde(default = "default_active_yolo_model")]
+    pub active_yolo_model: String,
+    // 热键识别完成并发出事件后自动把焦点还给游戏，避免插件抢焦点导致游戏暂停
+    #[serde(default = "default_auto_restore_focus")]
+    pub auto_restore_focus: bool,
+    // 用户调整后的 overlay 详情面板位置与尺寸，未调整过时为 None（前端使用默认 420x600 居中）
+    #[serde(default)]
+    pub overlay_detail_layout: Option<OverlayDetailLayout>,
+    // recognize_monsters 的怪物区域（相对比例），None 时使用默认三格布局
+    #[serde(default)]
+    pub monster_scan_region: Option<monster_recognition::MonsterScanRegion>,
+    // 事件 ORB 匹配阈值（匹配点数 >= min_matches 且置信度 > min_confidence），与怪物阈值分开配置
+    #[serde(default = "default_event_min_matches")]
+    pub event_min_matches: usize,
+    #[serde(default = "default_event_min_confidence")]
+    pub event_min_confidence: f32,
+    // 鼠标指向识别结果的短时缓存有效期（毫秒），0 表示不缓存
+    #[serde(default = "default_recognition_cache_ms")]
+    pub recognition_cache_ms: u64,
+    // 临时禁用的热键（动作名同 get_all_hotkeys 的 key），禁用时保留原键码
+    #[serde(default)]
+    pub disabled_hotkeys: HashSet<String>,
+    // overlay 默认点击穿透，关闭后 overlay 常驻可交互（初始化时据此设定）
+    #[serde(default = "default_overlay_click_through")]
+    pub overlay_click_through: bool,
 }
 
 // 跨平台虚拟键常量
@@ -803,17 +1386,72 @@ impl Default for PersistentState {
             inst_to_temp: HashMap::new(),
             current_hand: HashSet::new(),
             current_stash: HashSet::new(),
-            detection_hotkey: Some(VK_RBUTTON_CODE),
-            card_detection_hotkey: Some(VK_MENU_CODE),
-            toggle_collapse_hotkey: Some(192), // Default: ~ key (Backtick) (VK_OEM_3 is 192 usually, or 0xC0)
-            yolo_hotkey: Some(81), // Default: Q key (VK_Q = 81)
-            detail_display_hotkey: Some(VK_RBUTTON_CODE), // Default: Right mouse button
+            detection_hotkey: Some(Hotkey::single(VK_RBUTTON_CODE)),
+            card_detection_hotkey: Some(Hotkey::single(VK_MENU_CODE)),
+            toggle_collapse_hotkey: Some(Hotkey::single(192)), // Default: ~ key (Backtick) (VK_OEM_3 is 192 usually, or 0xC0)
+            yolo_hotkey: Some(Hotkey::single(81)), // Default: Q key (VK_Q = 81)
+            detail_display_hotkey: Some(Hotkey::single(VK_RBUTTON_CODE)), // Default: Right mouse button
             show_yolo_monitor: true,
+            monster_overlap_ratio: default_monster_overlap_ratio(),
+            min_crop_size: default_min_crop_size(),
+            enhance_contrast: false,
+            overlay_show_on_first_scan: false,
+            toast_dedup_ms: default_toast_dedup_ms(),
+            auto_check_updates: default_auto_check_updates(),
+            nms_strategy: monster_recognition::NmsStrategy::default(),
+            max_detections: 0,
+            yolo_conf_threshold: default_yolo_conf_threshold(),
+            yolo_iou_threshold: default_yolo_iou_threshold(),
+            merge_scan_detections: false,
+            fullscreen_capture_fallback: default_fullscreen_capture_fallback(),
+            phash_prefilter: false,
+            phash_top_k: default_phash_top_k(),
+            last_jumped_day: None,
+            last_recognized_monster: None,
+            restore_last_jump: false,
+            capture_inset: monster_recognition::CaptureInset::default(),
+            orb_params: monster_recognition::OrbParams::default(),
+            excluded_window_titles: default_excluded_window_titles(),
+            minimize_to_tray: false,
+            auto_yolo_on_state_change: false,
+            custom_log_path: None,
+            overlay_box_color: default_overlay_box_color(),
+            overlay_opacity: default_overlay_opacity(),
+            feature_algorithm: default_feature_algorithm(),
+            active_yolo_model: default_active_yolo_model(),
+            auto_restore_focus: default_auto_restore_focus(),
+            overlay_detail_layout: None,
+            monster_scan_region: None,
+            event_min_matches: default_event_min_matches(),
+            event_min_confidence: default_event_min_confidence(),
+            recognition_cache_ms: default_recognition_cache_ms(),
+            disabled_hotkeys: HashSet::new(),
+            overlay_click_through: default_overlay_click_through(),
         }
     }
 }
 
 fn default_show_yolo_monitor() -> bool { true }
+fn default_monster_overlap_ratio() -> f32 { 0.5 }
+fn default_min_crop_size() -> u32 { 50 }
+fn default_toast_dedup_ms() -> u64 { 3000 }
+fn default_auto_check_updates() -> bool { true }
+fn default_fullscreen_capture_fallback() -> bool { true }
+fn default_phash_top_k() -> usize { 40 }
+fn default_yolo_conf_threshold() -> f32 { 0.25 }
+fn default_yolo_iou_threshold() -> f32 { 0.45 }
+fn default_overlay_box_color() -> String { "#FFCD19".to_string() }
+fn default_overlay_opacity() -> f32 { 0.98 }
+fn default_feature_algorithm() -> String { "orb".to_string() }
+fn default_active_yolo_model() -> String { monster_recognition::DEFAULT_YOLO_MODEL.to_string() }
+fn default_auto_restore_focus() -> bool { true }
+fn default_event_min_matches() -> usize { 12 }
+fn default_event_min_confidence() -> f32 { 0.12 }
+fn default_recognition_cache_ms() -> u64 { 1000 }
+fn default_overlay_click_through() -> bool { true }
+fn default_excluded_window_titles() -> Vec<String> {
+    ["visual studio code", "obs", "mediaplayer", "bazaarhelper"].iter().map(|s| s.to_string()).collect()
+}
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct RawSkill {
     pub en: Option<String>,
@@ -903,6 +1541,19 @@ pub struct ItemData {
     pub image: Option<String>, // Added this
 }
 
+// 原始标签 "Weapon / 武器 | Tool / 工具" -> ["武器", "工具"]：按 | 拆分，取 " / " 后的最后一段，过滤隐藏标记
+pub fn process_tags(raw: &str) -> Vec<String> {
+    raw.split('|')
+        .map(|s| {
+            let part = s.trim();
+            // Pick the last part after / if it exists
+            part.split(" / ").last().unwrap_or(part).trim().to_string()
+        })
+        .filter(|s| !s.is_empty())
+        .filter(|s| !s.contains("隐藏") && !s.contains("Hide") && !s.contains("Hidden"))
+        .collect()
+}
+
 impl From<RawItem> for ItemData {
     fn from(raw: RawItem) -> Self {
         let name_en = raw.name_en.clone().unwrap_or_else(|| "Unknown".to_string());
@@ -915,16 +1566,7 @@ impl From<RawItem> for ItemData {
             h_str.split('|').map(|s| s.trim().to_string()).collect()
         };
 
-        let processed_tags = raw.tags.as_deref().unwrap_or_default()
-            .split('|')
-            .map(|s| {
-                let part = s.trim();
-                // Pick the last part after / if it exists
-                part.split(" / ").last().unwrap_or(part).trim().to_string()
-            })
-            .filter(|s| !s.is_empty())
-            .filter(|s| !s.contains("隐藏") && !s.contains("Hide") && !s.contains("Hidden"))
-            .collect();
+        let processed_tags = process_tags(raw.tags.as_deref().unwrap_or_default());
 
         // 提取隐藏标签
         let hidden_tags = raw.hidden_tags.unwrap_or_default();
@@ -1085,12 +1727,16 @@ pub struct SyncPayload {
     pub hand_items: Vec<ItemData>,
     pub stash_items: Vec<ItemData>,
     pub all_tags: Vec<String>,
+    // 日志里追踪到、但在物品/技能库中找不到的模板 ID（去重排序），用于排查数据缺失
+    #[serde(default)]
+    pub unresolved_template_ids: Vec<String>,
 }
 
 pub struct ItemDb {
     pub list: Vec<ItemData>,
     pub id_map: HashMap<String, usize>,
     pub unique_tags: Vec<String>,
+    pub unique_heroes: Vec<String>,
 }
 
 pub struct SkillDb {
@@ -1102,6 +1748,69 @@ pub struct DbState {
     pub items: Arc<RwLock<ItemDb>>,
     pub skills: Arc<RwLock<SkillDb>>,
     pub monsters: Arc<RwLock<serde_json::Map<String, serde_json::Value>>>,
+    pub events: Arc<RwLock<HashMap<String, serde_json::Value>>>, // event_encounters.json，按 Id 索引
+}
+
+fn load_events_db(app: &tauri::AppHandle) -> Result<HashMap<String, serde_json::Value>, String> {
+    let event_json_path = app.path().resolve("resources/event_encounters.json", tauri::path::BaseDirectory::Resource)
+        .map_err(|e| format!("Failed to resolve event_encounters.json: {}", e))?;
+    let json_data = std::fs::read_to_string(&event_json_path)
+        .map_err(|e| format!("Failed to read event_encounters.json: {}", e))?;
+    let events = serde_json::from_str::<Vec<serde_json::Value>>(&json_data)
+        .map_err(|e| format!("Failed to parse event_encounters.json: {}", e))?;
+
+    Ok(events.into_iter()
+        .filter_map(|e| {
+            let id = e.get("Id").and_then(|v| v.as_str())?.to_string();
+            Some((id, e))
+        })
+        .collect())
+}
+
+// 数据文件的版本信息：优先读取 JSON 顶层的 version/build_date 字段，没有则退回文件修改时间
+#[derive(Debug, Serialize, Clone)]
+pub struct DataFileVersion {
+    pub file: String,
+    pub version: Option<String>,
+    pub build_date: Option<String>,
+    pub source: String, // "field" | "mtime"
+}
+
+static DATA_VERSIONS: OnceLock<RwLock<HashMap<String, DataFileVersion>>> = OnceLock::new();
+
+fn get_data_versions() -> &'static RwLock<HashMap<String, DataFileVersion>> {
+    DATA_VERSIONS.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+fn record_data_version(kind: &str, path: &PathBuf, json: &str) {
+    let mut version = None;
+    let mut build_date = None;
+    // 只有对象结构的 JSON 才可能带版本字段（items/skills 是数组）
+    if json.trim_start().starts_with('{') {
+        if let Ok(serde_json::Value::Object(obj)) = serde_json::from_str::<serde_json::Value>(json) {
+            let field = |keys: &[&str]| keys.iter()
+                .find_map(|k| obj.get(*k))
+                .and_then(|v| v.as_str().map(|s| s.to_string()).or_else(|| v.as_u64().map(|n| n.to_string())));
+            version = field(&["version", "data_version"]);
+            build_date = field(&["build_date", "date", "generated_at"]);
+        }
+    }
+
+    let source = if version.is_some() || build_date.is_some() { "field" } else { "mtime" };
+    if build_date.is_none() {
+        build_date = std::fs::metadata(path).and_then(|m| m.modified()).ok()
+            .map(|t| chrono::DateTime::<Local>::from(t).format("%Y-%m-%d %H:%M:%S").to_string());
+    }
+
+    log_to_file(&format!("[Init] {} data version: {:?} ({:?}, from {})", kind, version, build_date, source));
+    if let Ok(mut versions) = get_data_versions().write() {
+        versions.insert(kind.to_string(), DataFileVersion {
+            file: path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+            version,
+            build_date,
+            source: source.to_string(),
+        });
+    }
 }
 
 fn construct_monster_sub_item(item_data: Option<ItemData>, fallback_name_cn: &str, fallback_name_en: &str, current_tier: &str, override_size: Option<&str>) -> serde_json::Value {
@@ -1269,7 +1978,19 @@ fn construct_monster_sub_item(item_data: Option<ItemData>, fallback_name_cn: &st
     serde_json::Value::Object(sub)
 }
 
+// 日志路径统一入口：优先使用配置的 custom_log_path（文件存在时），否则为游戏默认位置
 fn get_log_path() -> PathBuf {
+    if let Some(custom) = load_state().custom_log_path {
+        let path = PathBuf::from(custom);
+        if path.is_file() {
+            return path;
+        }
+        log_to_file(&format!("[LogPath] Custom log path {:?} not found, using default", path));
+    }
+    default_log_path()
+}
+
+fn default_log_path() -> PathBuf {
     if cfg!(target_os = "macos") {
         let home = std::env::var("HOME").unwrap_or_default();
         PathBuf::from(home)
@@ -1315,16 +2036,20 @@ async fn start_template_loading(app: tauri::AppHandle) -> Result<(), String> {
     Ok(())
 }
 
-// #[tauri::command]
-// #[allow(dead_code)]
-// async fn clear_monster_cache() -> Result<(), String> {
-//     let cache_dir = get_cache_path().parent().unwrap().to_path_buf();
-//     let cache_file = cache_dir.join("monster_features.bin");
-//     if cache_file.exists() {
-//         std::fs::remove_file(cache_file).map_err(|e| e.to_string())?;
-//     }
-//     Ok(())
-// }
+// 更新怪物图片后强制重建 ORB 特征缓存，返回重建后的模板数
+#[tauri::command]
+async fn rebuild_monster_cache(app: tauri::AppHandle) -> Result<usize, String> {
+    let res_dir = app.path().resource_dir().map_err(|e| e.to_string())?.join("resources");
+    let cache_dir = get_cache_path().parent().ok_or("Failed to get cache parent dir")?.to_path_buf();
+
+    log_to_file("[Cache] Rebuilding monster template cache...");
+    monster_recognition::reset_monster_templates(&cache_dir)?;
+    monster_recognition::preload_templates_async(res_dir, cache_dir).await?;
+
+    let count = monster_recognition::monster_template_count();
+    log_to_file(&format!("[Cache] Monster template cache rebuilt: {} templates", count));
+    Ok(count)
+}
 
 #[tauri::command]
 async fn get_item_info(state: tauri::State<'_, DbState>, id: String) -> Result<Option<ItemData>, String> {
@@ -1340,16 +2065,210 @@ async fn get_item_info(state: tauri::State<'_, DbState>, id: String) -> Result<O
     Ok(None)
 }
 
-#[tauri::command]
-async fn set_overlay_ignore_cursor(app: tauri::AppHandle, ignore: bool) -> Result<(), String> {
+const COMPARE_TIERS: [&str; 5] = ["Bronze", "Silver", "Gold", "Diamond", "Legendary"];
+const NOT_APPLICABLE: &str = "不适用";
+
+// items_db.json 用 0 表示物品没有该属性（非武器 damage: 0、被动物品 cooldown: 0）：
+// 基础值缺失或为 0，且分阶数值为空或全为 0 时视为缺失
+fn stat_missing(base: Option<f64>, tiers: &str) -> bool {
+    let has_tier_value = tiers.split('/').filter_map(|v| v.trim().parse::<f64>().ok()).any(|v| v != 0.0);
+    base.unwrap_or(0.0) == 0.0 && !has_tier_value
+}
+
+// 按 0 存储的缺失属性转成 None，供过滤与排序使用
+fn present_stat<T: Copy + Into<f64>>(value: Option<T>, tiers: &str) -> Option<T> {
+    value.filter(|_| !stat_missing(value.map(Into::into), tiers))
+}
+
+// 某数值字段在各品阶的取值：available_tiers 与 "5/15/30/50" 一一对应，
+// 只有一个值时各品阶相同，没有分阶数据时退回基础值；物品不存在该品阶时为 None
+fn stat_by_tier(item: &ItemData, base: Option<f64>, tiers: &str) -> Vec<(&'static str, Option<f64>)> {
+    let available: Vec<&str> = item.available_tiers.split('/').map(|t| t.trim()).filter(|t| !t.is_empty()).collect();
+    let values: Vec<f64> = tiers.split('/').filter_map(|v| v.trim().parse().ok()).collect();
+    COMPARE_TIERS.iter().map(|&tier| {
+        let value = available.iter().position(|&t| t == tier).and_then(|i| match values.len() {
+            0 => base,
+            1 => Some(values[0]),
+            _ => values.get(i).copied(),
+        });
+        (tier, value)
+    }).collect()
+}
+
+// 单个数值的对比：任一侧缺失时标注「不适用」，差值与百分比置空；百分比以 A 为基准
+fn compare_values(a: Option<f64>, b: Option<f64>) -> serde_json::Value {
+    let show = |v: Option<f64>| v.map(|x| serde_json::json!(x)).unwrap_or_else(|| serde_json::json!(NOT_APPLICABLE));
+    let (diff, percent) = match (a, b) {
+        (Some(a), Some(b)) => (Some(b - a), if a != 0.0 { Some((b - a) / a.abs() * 100.0) } else { None }),
+        _ => (None, None),
+    };
+    serde_json::json!({
+        "a": show(a),
+        "b": show(b),
+        "diff": diff,
+        "percent": percent,
+        "applicable": a.is_some() && b.is_some(),
+    })
+}
+
+/// 并排对比两件物品在各品阶的数值，缺失字段标注为「不适用」而不是按 0 计算
+#[tauri::command]
+fn compare_items(id_a: String, id_b: String, state: tauri::State<'_, DbState>) -> Result<serde_json::Value, String> {
+    let find = |id: &str| -> Result<ItemData, String> {
+        let db = state.items.read().map_err(|_| "DB Busy")?;
+        if let Some(&idx) = db.id_map.get(id) {
+            return Ok(db.list[idx].clone());
+        }
+        let sdb = state.skills.read().map_err(|_| "DB Busy")?;
+        sdb.id_map.get(id).map(|&idx| sdb.list[idx].clone()).ok_or_else(|| format!("物品不存在: {}", id))
+    };
+    let a = find(&id_a)?;
+    let b = find(&id_b)?;
+
+    let stats = |item: &ItemData| -> Vec<(&'static str, Option<f64>, String)> {
+        let to_f = |v: Option<i32>| v.map(f64::from);
+        let fields = [
+            ("cooldown", item.cooldown.map(f64::from), item.cooldown_tiers.clone()),
+            ("damage", to_f(item.damage), item.damage_tiers.clone()),
+            ("heal", to_f(item.heal), item.heal_tiers.clone()),
+            ("shield", to_f(item.shield), item.shield_tiers.clone()),
+            ("ammo", to_f(item.ammo), item.ammo_tiers.clone()),
+            ("crit", to_f(item.crit), item.crit_tiers.clone()),
+            ("multicast", to_f(item.multicast), item.multicast_tiers.clone()),
+            ("burn", to_f(item.burn), item.burn_tiers.clone()),
+            ("poison", to_f(item.poison), item.poison_tiers.clone()),
+            ("regen", to_f(item.regen), item.regen_tiers.clone()),
+            ("lifesteal", to_f(item.lifesteal), item.lifesteal_tiers.clone()),
+        ];
+        // 缺失（按 0 存储）的属性置为 None，对比时标注「不适用」而不是按 0 计算
+        fields.into_iter()
+            .map(|(field, base, tiers)| if stat_missing(base, &tiers) { (field, None, String::new()) } else { (field, base, tiers) })
+            .collect()
+    };
+
+    let fields: Vec<serde_json::Value> = stats(&a).into_iter().zip(stats(&b))
+        .filter(|((_, base_a, tiers_a), (_, base_b, tiers_b))| {
+            // 两件都没有的字段不输出
+            base_a.is_some() || base_b.is_some() || !tiers_a.is_empty() || !tiers_b.is_empty()
+        })
+        .map(|((field, base_a, tiers_a), (_, base_b, tiers_b))| {
+            let tiers: Vec<serde_json::Value> = stat_by_tier(&a, base_a, &tiers_a).into_iter()
+                .zip(stat_by_tier(&b, base_b, &tiers_b))
+                .filter(|((_, va), (_, vb))| va.is_some() || vb.is_some())
+                .map(|((tier, va), (_, vb))| {
+                    let mut cmp = compare_values(va, vb);
+                    cmp["tier"] = serde_json::json!(tier);
+                    cmp
+                })
+                .collect();
+            let mut cmp = compare_values(base_a, base_b);
+            cmp["field"] = serde_json::json!(field);
+            cmp["tiers"] = serde_json::json!(tiers);
+            cmp
+        })
+        .collect();
+
+    let summary = |item: &ItemData| serde_json::json!({
+        "uuid": item.uuid,
+        "name": item.name,
+        "name_cn": item.name_cn,
+        "tier": item.tier,
+        "available_tiers": item.available_tiers,
+        "size": item.size,
+    });
+    Ok(serde_json::json!({
+        "a": summary(&a),
+        "b": summary(&b),
+        "fields": fields,
+    }))
+}
+
+// 批量解析 ID（例如导入的构筑），按输入顺序返回，找不到的位置为 None
+#[tauri::command]
+async fn get_items_by_ids(state: tauri::State<'_, DbState>, ids: Vec<String>) -> Result<Vec<Option<ItemData>>, String> {
+    let items_db = state.items.read().map_err(|_| "DB Busy")?;
+    let skills_db = state.skills.read().map_err(|_| "DB Busy")?;
+    Ok(ids.iter().map(|id| lookup_item(id, &items_db, &skills_db)).collect())
+}
+
+// 调试单个追踪物品：实例 ID -> inst_to_temp 中的模板 ID -> ItemData（带 instance_id）
+#[tauri::command]
+async fn get_item_by_instance(state: tauri::State<'_, DbState>, instance_id: String) -> Result<ItemData, String> {
+    let persisted = load_state();
+    let template_id = persisted.inst_to_temp.get(&instance_id)
+        .ok_or_else(|| format!("Instance {} not found in inst_to_temp", instance_id))?;
+    let items_db = state.items.read().map_err(|_| "DB Busy")?;
+    let skills_db = state.skills.read().map_err(|_| "DB Busy")?;
+    let mut item = lookup_item(template_id, &items_db, &skills_db)
+        .ok_or_else(|| format!("Template {} (instance {}) not found in items/skills DB", template_id, instance_id))?;
+    item.instance_id = Some(instance_id);
+    Ok(item)
+}
+
+// overlay 当前是否点击穿透，以及用户偏好（overlay_click_through，启动时从配置加载）
+static OVERLAY_IGNORE_CURSOR: AtomicBool = AtomicBool::new(true);
+static OVERLAY_CLICK_THROUGH: AtomicBool = AtomicBool::new(true);
+
+// 实际生效的穿透状态 = 请求值 && 用户偏好；关闭穿透偏好后 overlay 常驻可交互，前端悬停逻辑的穿透请求被忽略
+fn apply_overlay_ignore_cursor(app: &tauri::AppHandle, ignore: bool) -> Result<(), String> {
+    let effective = ignore && OVERLAY_CLICK_THROUGH.load(Ordering::SeqCst);
     if let Some(overlay) = app.get_webview_window("overlay") {
-        overlay.set_ignore_cursor_events(ignore).map_err(|e| e.to_string())?;
+        overlay.set_ignore_cursor_events(effective).map_err(|e| e.to_string())?;
+    }
+    if OVERLAY_IGNORE_CURSOR.swap(effective, Ordering::SeqCst) != effective {
+        let _ = app.emit("overlay-ignore-cursor-changed", effective);
     }
     Ok(())
 }
 
+#[tauri::command]
+async fn set_overlay_ignore_cursor(app: tauri::AppHandle, ignore: bool) -> Result<(), String> {
+    apply_overlay_ignore_cursor(&app, ignore)
+}
+
+#[tauri::command]
+fn get_overlay_ignore_cursor() -> bool {
+    OVERLAY_IGNORE_CURSOR.load(Ordering::SeqCst)
+}
+
+#[tauri::command]
+fn get_overlay_click_through() -> bool {
+    load_state().overlay_click_through
+}
+
+/// 设置 overlay 默认是否点击穿透并立即应用；关闭后 overlay 常驻可交互
+#[tauri::command]
+fn set_overlay_click_through(app: tauri::AppHandle, enabled: bool) -> Result<(), String> {
+    let mut state = load_state();
+    state.overlay_click_through = enabled;
+    save_state(&state);
+    OVERLAY_CLICK_THROUGH.store(enabled, Ordering::SeqCst);
+    println!("[Config] Overlay click-through updated to: {}", enabled);
+    apply_overlay_ignore_cursor(&app, enabled)
+}
+
 #[tauri::command]
 async fn restore_game_focus() -> Result<(), String> {
+    focus_game_window()
+}
+
+/// 热键触发的识别结果送达后由前端调用（卡牌识别在前端执行），受 auto_restore_focus 控制
+#[tauri::command]
+async fn restore_game_focus_after_hotkey() {
+    restore_game_focus_if_enabled();
+}
+
+// 鼠标监听线程识别完成后调用，受 auto_restore_focus 控制
+fn restore_game_focus_if_enabled() {
+    if !load_state().auto_restore_focus {
+        return;
+    }
+    if let Err(e) = focus_game_window() {
+        log_to_file(&format!("[Focus] Failed to restore game focus: {}", e));
+    }
+}
+
+fn focus_game_window() -> Result<(), String> {
     #[cfg(target_os = "windows")]
     {
         use windows::Win32::UI::WindowsAndMessaging::{FindWindowW, SetForegroundWindow, ShowWindow, SW_SHOW};
@@ -1366,9 +2285,75 @@ async fn restore_game_focus() -> Result<(), String> {
             }
         }
     }
+    #[cfg(target_os = "macos")]
+    {
+        if let Err(e) = activate_game_app_macos() {
+            log_to_file(&format!("[Focus] macOS activate failed: {}", e));
+            return Err(e);
+        }
+    }
     Ok(())
 }
 
+/// macOS: 在 NSWorkspace.runningApplications 中找到游戏进程并通过 NSRunningApplication.activate 置前
+#[cfg(target_os = "macos")]
+#[allow(unexpected_cfgs)] // msg_send! macro uses cargo-clippy cfg
+fn activate_game_app_macos() -> Result<(), String> {
+    use objc::{class, msg_send, sel, sel_impl};
+    use objc::runtime::{Object, BOOL, NO};
+    use std::ffi::CStr;
+    use std::os::raw::c_char;
+
+    // NSApplicationActivateIgnoringOtherApps
+    const ACTIVATE_IGNORING_OTHER_APPS: usize = 1 << 1;
+
+    // 优先使用 xcap 找到的游戏窗口所属应用名，找不到窗口时按默认进程名匹配
+    let target = monster_recognition::find_game_window(None).ok().flatten()
+        .map(|w| w.app_name().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "The Bazaar".to_string());
+
+    unsafe {
+        let workspace: *mut Object = msg_send![class!(NSWorkspace), sharedWorkspace];
+        let apps: *mut Object = msg_send![workspace, runningApplications];
+        if apps.is_null() {
+            return Err("无法获取正在运行的应用列表".to_string());
+        }
+        let count: usize = msg_send![apps, count];
+        for i in 0..count {
+            let running_app: *mut Object = msg_send![apps, objectAtIndex: i];
+            let name: *mut Object = msg_send![running_app, localizedName];
+            if name.is_null() {
+                continue;
+            }
+            let utf8: *const c_char = msg_send![name, UTF8String];
+            if utf8.is_null() || !CStr::from_ptr(utf8).to_string_lossy().eq_ignore_ascii_case(&target) {
+                continue;
+            }
+            let activated: BOOL = msg_send![running_app, activateWithOptions: ACTIVATE_IGNORING_OTHER_APPS];
+            if activated == NO {
+                return Err(format!("{} 拒绝激活", target));
+            }
+            println!("[Focus] Activated {}", target);
+            return Ok(());
+        }
+    }
+    Err(format!("未找到正在运行的 {} 进程", target))
+}
+
+#[tauri::command]
+fn get_auto_restore_focus() -> bool {
+    load_state().auto_restore_focus
+}
+
+#[tauri::command]
+fn set_auto_restore_focus(enabled: bool) {
+    let mut state = load_state();
+    state.auto_restore_focus = enabled;
+    save_state(&state);
+    println!("[Config] Auto restore focus updated to: {}", enabled);
+}
+
 fn get_cache_path() -> PathBuf {
     if cfg!(target_os = "macos") {
         let home = std::env::var("HOME").unwrap_or_default();
@@ -1393,423 +2378,2236 @@ fn get_show_yolo_monitor() -> Result<bool, String> {
     Ok(state.show_yolo_monitor)
 }
 
-fn get_prev_log_path() -> PathBuf {
-    let mut p = get_log_path();
-    p.set_file_name("Player-prev.log");
-    p
+#[tauri::command]
+fn get_monster_overlap_ratio() -> f32 {
+    load_state().monster_overlap_ratio
 }
 
-fn save_state(state: &PersistentState) {
-    let path = get_cache_path();
-    if let Some(parent) = path.parent() {
-        let _ = std::fs::create_dir_all(parent);
-    }
-    if let Ok(json) = serde_json::to_string(state) {
-        let _ = std::fs::write(path, json);
+#[tauri::command]
+fn set_monster_overlap_ratio(ratio: f32) -> Result<(), String> {
+    if !(ratio > 0.0 && ratio <= 1.0) {
+        return Err(format!("Overlap ratio must be in (0, 1], got {}", ratio));
     }
+    let mut state = load_state();
+    state.monster_overlap_ratio = ratio;
+    save_state(&state);
+    println!("[Config] Monster overlap ratio updated to: {}", ratio);
+    Ok(())
 }
 
-fn load_state() -> PersistentState {
-    let path = get_cache_path();
-    if let Ok(json) = std::fs::read_to_string(path) {
-        if let Ok(state) = serde_json::from_str::<PersistentState>(&json) {
-            return state;
-        }
-    }
-    PersistentState::default()
+#[tauri::command]
+fn get_min_crop_size() -> u32 {
+    load_state().min_crop_size
 }
 
-fn lookup_item(tid: &str, items_db: &ItemDb, skills_db: &SkillDb) -> Option<ItemData> {
-    if let Some(&index) = items_db.id_map.get(tid) {
-        return items_db.list.get(index).cloned();
-    }
-    if let Some(&index) = skills_db.id_map.get(tid) {
-        return skills_db.list.get(index).cloned();
+#[tauri::command]
+fn set_min_crop_size(size: u32) -> Result<(), String> {
+    if size == 0 {
+        return Err("Minimum crop size must be greater than 0".into());
     }
-    None
+    let mut state = load_state();
+    state.min_crop_size = size;
+    save_state(&state);
+    println!("[Config] Minimum crop size updated to: {}", size);
+    Ok(())
 }
 
-fn lookup_item_by_name(name_cn: &str, items_db: &ItemDb, skills_db: &SkillDb) -> Option<ItemData> {
-    // 先在物品库中查找完整名字
-    for item in &items_db.list {
-        if item.name_cn == name_cn {
-            return Some(item.clone());
-        }
-    }
-    // 再在技能库中查找完整名字
-    for skill in &skills_db.list {
-        if skill.name_cn == name_cn {
-            return Some(skill.clone());
-        }
-    }
-    
-    // 如果找不到，尝试去除空格及空格之前的前缀（如"毒性蔓延 獠牙" -> "獠牙"）
-    if let Some(space_pos) = name_cn.rfind(' ') {
-        let base_name = &name_cn[space_pos + 1..];
-        
-        // 用基础名字再查找一次
-        for item in &items_db.list {
-            if item.name_cn == base_name {
-                return Some(item.clone());
-            }
-        }
-        for skill in &skills_db.list {
-            if skill.name_cn == base_name {
-                return Some(skill.clone());
-            }
-        }
-    }
-    
-    None
+#[tauri::command]
+fn get_enhance_contrast() -> bool {
+    load_state().enhance_contrast
 }
 
-// --- Commands ---
-#[derive(Debug, serde::Deserialize)]
-pub struct SearchQuery {
-    pub keyword: Option<String>,
-    pub item_type: Option<String>, // "all", "item", "skill"
-    pub size: Option<String>,
-    pub start_tier: Option<String>,
-    pub hero: Option<String>,
-    pub tags: Option<String>,
-    pub hidden_tags: Option<String>,
+#[tauri::command]
+fn set_enhance_contrast(enabled: bool) {
+    // 不在运行时切换：已加载的模板是按旧设置生成的，需要重启后重新加载
+    let mut state = load_state();
+    state.enhance_contrast = enabled;
+    save_state(&state);
+    println!("[Config] Enhance contrast set to: {} (takes effect after restart)", enabled);
 }
 
 #[tauri::command]
-fn search_items(query: SearchQuery, state: State<'_, DbState>) -> Result<Vec<ItemData>, String> {
-    let mut results = Vec::new();
-    let keyword = query.keyword.as_deref().map(|s| s.to_lowercase());
-    let size_filter = query.size.as_deref().map(|s| s.to_lowercase());
-    let tier_filter = query.start_tier.as_deref().map(|s| s.to_lowercase());
-    let hero_filter = query.hero.as_deref().map(|s| s.to_lowercase());
-    let tags_filter = query.tags.as_deref().map(|s| s.to_lowercase());
-    let htags_filter = query.hidden_tags.as_deref().map(|s| s.to_lowercase());
-
-    let match_item = |item: &ItemData| -> bool {
-        if let Some(ref k) = keyword {
-            if !item.name_cn.to_lowercase().contains(k) && !item.name.to_lowercase().contains(k) {
-                return false;
-            }
-        }
-        if let Some(ref s) = size_filter {
-            if !item.size.as_ref().map(|v| v.to_lowercase()).unwrap_or_default().contains(s) {
-                return false;
-            }
-        }
-        if let Some(ref t) = tier_filter {
-            if !item.tier.to_lowercase().contains(t) {
-                return false;
-            }
-        }
-        if let Some(ref h) = hero_filter {
-            if !item.heroes.iter().any(|hero| hero.to_lowercase().contains(h)) {
-                return false;
-            }
-        }
-        if let Some(ref t) = tags_filter {
-             if !item.tags.to_lowercase().contains(t) {
-                 return false;
-             }
-        }
-        if let Some(ref h) = htags_filter {
-             if !item.hidden_tags.to_lowercase().contains(h) {
-                 return false;
-             }
-        }
-        true
-    };
+fn get_orb_params() -> monster_recognition::OrbParams {
+    load_state().orb_params
+}
 
-    let search_type = query.item_type.as_deref().unwrap_or("all");
+#[tauri::command]
+fn set_orb_params(params: monster_recognition::OrbParams) -> Result<(), String> {
+    // 与 enhance_contrast 相同，不在运行时切换；重启后缓存指纹不匹配会自动重建模板
+    params.validate()?;
+    let mut state = load_state();
+    state.orb_params = params;
+    save_state(&state);
+    println!("[Config] ORB params set to: {:?} (takes effect after restart)", params);
+    Ok(())
+}
 
-    if search_type == "all" || search_type == "item" {
-        if let Ok(db) = state.items.read() {
-            for item in &db.list {
-                if match_item(item) {
-                     results.push(item.clone());
-                }
-            }
-        }
-    }
+#[tauri::command]
+fn get_feature_algorithm() -> String {
+    load_state().feature_algorithm
+}
 
-    if search_type == "all" || search_type == "skill" {
-        if let Ok(db) = state.skills.read() {
-            for item in &db.list {
-                if match_item(item) {
-                     results.push(item.clone());
-                }
-            }
-        }
-    }
+#[tauri::command]
+fn set_feature_algorithm(algorithm: String) -> Result<(), String> {
+    // 与 enhance_contrast 相同，不在运行时切换；重启后按新算法加载（或重建）对应的缓存
+    let parsed = monster_recognition::FeatureAlgorithm::parse(&algorithm)?;
+    let mut state = load_state();
+    state.feature_algorithm = algorithm.trim().to_ascii_lowercase();
+    save_state(&state);
+    println!("[Config] Feature algorithm set to: {:?} (takes effect after restart)", parsed);
+    Ok(())
+}
 
-    // Sort by tier then name
-    results.sort_by(|a, b| {
-        // Simple tier sort logic (Bronze < Silver < Gold < Diamond < Legendary)
-        let tier_rank = |t: &str| match t.split('/').next().unwrap_or("").trim() {
-            "Bronze" | "Common" => 1,
-            "Silver" => 2,
-            "Gold" => 3,
-            "Diamond" => 4,
-            "Legendary" => 5,
-            _ => 10,
-        };
-        let ta = tier_rank(&a.tier);
-        let tb = tier_rank(&b.tier);
-        if ta != tb {
-            ta.cmp(&tb)
-        } else {
-            a.name_cn.cmp(&b.name_cn)
-        }
-    });
+#[tauri::command]
+fn list_yolo_models(app: tauri::AppHandle) -> Result<Vec<String>, String> {
+    let resources_path = app.path().resource_dir().map_err(|e| e.to_string())?;
+    Ok(monster_recognition::list_yolo_models(&resources_path))
+}
 
-    Ok(results)
+#[tauri::command]
+fn get_active_yolo_model() -> String {
+    load_state().active_yolo_model
 }
 
 #[tauri::command]
-fn get_all_monsters(state: State<'_, DbState>) -> Result<serde_json::Map<String, serde_json::Value>, String> {
-    log_to_file("get_all_monsters called");
-    let db = state.monsters.read().map_err(|_| "DB Busy")?;
-    let count = db.len();
-    log_to_file(&format!("Monsters DB contains {} entries", count));
-    
-    // 调试：输出前几个怪物名称
-    if count > 0 {
-        let sample_names: Vec<String> = db.keys().take(5).cloned().collect();
-        log_to_file(&format!("Sample monster names: {:?}", sample_names));
-    } else {
-        log_to_file("Warning: Monsters DB is empty!");
+fn set_active_yolo_model(app: tauri::AppHandle, model: String) -> Result<(), String> {
+    // 会话按模型路径缓存，切换后下次扫描直接加载（或复用）对应模型，无需重启
+    let resources_path = app.path().resource_dir().map_err(|e| e.to_string())?;
+    let model = model.trim().to_string();
+    if !monster_recognition::list_yolo_models(&resources_path).contains(&model) {
+        return Err(format!("模型不存在: {}", model));
     }
-    
-    Ok(db.clone())
+    let mut state = load_state();
+    state.active_yolo_model = model.clone();
+    save_state(&state);
+    println!("[Config] Active YOLO model updated to: {}", model);
+    Ok(())
 }
 
 #[tauri::command]
-fn debug_monsters_db(state: State<'_, DbState>) -> Result<String, String> {
-    let db = state.monsters.read().map_err(|_| "DB Busy")?;
-    let count = db.len();
-    let mut result = format!("Monsters DB Status:\n- Total entries: {}\n", count);
-    
-    if count > 0 {
-        let sample: Vec<String> = db.keys().take(10).cloned().collect();
-        result.push_str(&format!("- Sample entries: {:?}\n", sample));
-        
-        // 检查Day 1的怪物
-        let day1_monsters: Vec<String> = db.iter()
-            .filter(|(_, data)| {
-                data.get("available").and_then(|v| v.as_str()) == Some("Day 1")
-            })
-            .map(|(name, _)| name.clone())
-            .take(5)
-            .collect();
-        result.push_str(&format!("- Day 1 monsters: {:?}\n", day1_monsters));
-    } else {
-        result.push_str("- Database is empty!\n");
-    }
-    
-    log_to_file(&result);
-    Ok(result)
+fn measure_contrast_enhancement() -> Result<Vec<monster_recognition::ContrastMeasurement>, String> {
+    monster_recognition::measure_contrast_enhancement()
 }
 
 #[tauri::command]
-fn clear_yolo_cache() -> Result<String, String> {
-    // 清理YOLO扫描结果和图像缓存
-    {
-        let mut results = get_yolo_scan_results().write().unwrap();
-        results.clear();
+fn get_overlay_show_on_first_scan() -> bool {
+    load_state().overlay_show_on_first_scan
+}
+
+#[tauri::command]
+fn set_overlay_show_on_first_scan(enabled: bool) {
+    let mut state = load_state();
+    state.overlay_show_on_first_scan = enabled;
+    save_state(&state);
+    println!("[Config] Overlay show on first scan set to: {}", enabled);
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OverlayStyle {
+    pub box_color: String,
+    pub opacity: f32,
+}
+
+fn is_hex_color(s: &str) -> bool {
+    s.strip_prefix('#')
+        .map_or(false, |hex| (hex.len() == 6 || hex.len() == 8) && hex.chars().all(|ch| ch.is_ascii_hexdigit()))
+}
+
+#[tauri::command]
+fn get_overlay_style() -> OverlayStyle {
+    let state = load_state();
+    OverlayStyle { box_color: state.overlay_box_color, opacity: state.overlay_opacity }
+}
+
+/// 设置 overlay 详情框颜色（#RRGGBB / #RRGGBBAA）与不透明度 (0, 1]，并广播给 overlay 窗口
+#[tauri::command]
+fn set_overlay_style(app: tauri::AppHandle, box_color: String, opacity: f32) -> Result<(), String> {
+    if !is_hex_color(&box_color) {
+        return Err(format!("Invalid color: {} (expected #RRGGBB or #RRGGBBAA)", box_color));
     }
-    {
-        let mut saved_img = get_yolo_scan_image().write().unwrap();
-        *saved_img = None;
+    if !(opacity > 0.0 && opacity <= 1.0) {
+        return Err(format!("Invalid opacity: {} (expected 0 < opacity <= 1)", opacity));
     }
-    log_to_file("YOLO cache cleared to free memory");
-    Ok("YOLO缓存已清理".to_string())
+    let mut state = load_state();
+    state.overlay_box_color = box_color.clone();
+    state.overlay_opacity = opacity;
+    save_state(&state);
+    println!("[Config] Overlay style updated to: {} @ {}", box_color, opacity);
+    let _ = app.emit("overlay-style-update", OverlayStyle { box_color, opacity });
+    Ok(())
 }
 
 #[tauri::command]
-fn debug_resource_paths(app: tauri::AppHandle) -> Result<serde_json::Value, String> {
-    let resources_path = app.path().resource_dir().map_err(|e| e.to_string())?;
-    let mut report = serde_json::Map::new();
-    report.insert("resource_dir".to_string(), serde_json::Value::String(resources_path.to_string_lossy().to_string()));
+fn get_toast_dedup_ms() -> u64 {
+    load_state().toast_dedup_ms
+}
 
-    let files = [
-        "monsters_db.json",
-        "monsters_export.json",
-        "images_monster_map.json",
-        "items_db.json",
-        "skills_db.json",
-    ];
+#[tauri::command]
+fn set_toast_dedup_ms(ms: u64) {
+    let mut state = load_state();
+    state.toast_dedup_ms = ms;
+    save_state(&state);
+    println!("[Config] Toast dedup window updated to: {}ms", ms);
+}
 
-    let mut files_obj = serde_json::Map::new();
-    for f in &files {
-        let p1 = resources_path.join("resources").join(f);
-        let p2 = resources_path.join(f);
-        let mut info = serde_json::Map::new();
-        info.insert("path1".to_string(), serde_json::Value::String(p1.to_string_lossy().to_string()));
-        info.insert("exists1".to_string(), serde_json::Value::Bool(p1.exists()));
-        if p1.exists() {
-            if let Ok(md) = std::fs::metadata(&p1) {
-                info.insert("size1".to_string(), serde_json::Value::Number(serde_json::Number::from(md.len())));
-            }
-        }
-        info.insert("path2".to_string(), serde_json::Value::String(p2.to_string_lossy().to_string()));
-        info.insert("exists2".to_string(), serde_json::Value::Bool(p2.exists()));
-        if p2.exists() {
-            if let Ok(md) = std::fs::metadata(&p2) {
-                info.insert("size2".to_string(), serde_json::Value::Number(serde_json::Number::from(md.len())));
-            }
+#[tauri::command]
+fn get_nms_strategy() -> monster_recognition::NmsStrategy {
+    load_state().nms_strategy
+}
+
+#[tauri::command]
+fn set_nms_strategy(strategy: monster_recognition::NmsStrategy) {
+    let mut state = load_state();
+    state.nms_strategy = strategy;
+    save_state(&state);
+    println!("[Config] NMS strategy updated to: {:?}", strategy);
+}
+
+#[tauri::command]
+fn get_max_detections() -> usize {
+    load_state().max_detections
+}
+
+#[tauri::command]
+fn set_max_detections(max: usize) {
+    let mut state = load_state();
+    state.max_detections = max;
+    save_state(&state);
+    println!("[Config] Max YOLO detections updated to: {}", max);
+}
+
+#[tauri::command]
+fn get_yolo_thresholds() -> serde_json::Value {
+    let state = load_state();
+    serde_json::json!({
+        "conf": state.yolo_conf_threshold,
+        "iou": state.yolo_iou_threshold,
+    })
+}
+
+#[tauri::command]
+fn set_yolo_thresholds(conf: f32, iou: f32) -> Result<(), String> {
+    for (name, v) in [("conf", conf), ("iou", iou)] {
+        if !(v > 0.0 && v <= 1.0) {
+            return Err(format!("{} 阈值必须在 (0, 1] 范围内: {}", name, v));
         }
-        files_obj.insert(f.to_string(), serde_json::Value::Object(info));
     }
+    let mut state = load_state();
+    state.yolo_conf_threshold = conf;
+    state.yolo_iou_threshold = iou;
+    save_state(&state);
+    println!("[Config] YOLO thresholds updated to: conf={}, iou={}", conf, iou);
+    Ok(())
+}
 
-    report.insert("files".to_string(), serde_json::Value::Object(files_obj));
-    Ok(serde_json::Value::Object(report))
+#[tauri::command]
+fn get_event_match_thresholds() -> serde_json::Value {
+    let state = load_state();
+    serde_json::json!({
+        "min_matches": state.event_min_matches,
+        "min_confidence": state.event_min_confidence,
+    })
 }
 
 #[tauri::command]
-fn recognize_monsters_from_screenshot(day: Option<u32>) -> Result<Vec<monster_recognition::MonsterRecognitionResult>, String> {
-    let day_filter = day.map(|d| if d >= 10 { "Day 10+".to_string() } else { format!("Day {}", d) });
-    monster_recognition::recognize_monsters(day_filter)
+fn set_event_match_thresholds(min_matches: usize, min_confidence: f32) -> Result<(), String> {
+    if min_matches == 0 {
+        return Err("最少匹配点数必须大于 0".to_string());
+    }
+    if !(0.0..1.0).contains(&min_confidence) {
+        return Err(format!("置信度阈值必须在 [0, 1) 范围内: {}", min_confidence));
+    }
+    let mut state = load_state();
+    state.event_min_matches = min_matches;
+    state.event_min_confidence = min_confidence;
+    save_state(&state);
+    println!("[Config] Event match thresholds updated to: min_matches={}, min_confidence={}", min_matches, min_confidence);
+    Ok(())
 }
 
 #[tauri::command]
-fn get_template_loading_progress() -> monster_recognition::LoadingProgress {
-    monster_recognition::get_loading_progress()
+fn get_merge_scan_detections() -> bool {
+    load_state().merge_scan_detections
 }
 
 #[tauri::command]
-fn get_current_day(hours_per_day: Option<u32>, retro: Option<bool>) -> Result<u32, String> {
-    // Return cached value if available, log scan only as fallback
-    let cached = load_state();
-    if cached.day > 0 {
-        return Ok(cached.day);
+fn set_merge_scan_detections(enabled: bool) {
+    let mut state = load_state();
+    state.merge_scan_detections = enabled;
+    save_state(&state);
+    println!("[Config] Merge scan detections updated to: {}", enabled);
+}
+
+#[tauri::command]
+fn get_fullscreen_capture_fallback() -> bool {
+    load_state().fullscreen_capture_fallback
+}
+
+#[tauri::command]
+fn set_fullscreen_capture_fallback(enabled: bool) {
+    let mut state = load_state();
+    state.fullscreen_capture_fallback = enabled;
+    save_state(&state);
+    println!("[Config] Fullscreen capture fallback updated to: {}", enabled);
+}
+
+#[tauri::command]
+fn get_phash_prefilter() -> serde_json::Value {
+    let state = load_state();
+    serde_json::json!({ "enabled": state.phash_prefilter, "top_k": state.phash_top_k })
+}
+
+#[tauri::command]
+fn set_phash_prefilter(enabled: bool, top_k: usize) -> Result<(), String> {
+    if top_k == 0 {
+        return Err("pHash top K must be greater than 0".into());
     }
-    
-    let hours = hours_per_day.unwrap_or(6);
-    let retro = retro.unwrap_or(false);
-    let log_path = get_log_path();
-    
-    // Fallback to scan only if cache is 0 (first run)
-    if log_path.exists() {
-        // Use a more memory-efficient way to read large logs
-        let mut file = File::open(&log_path).map_err(|e| e.to_string())?;
-        let metadata = file.metadata().map_err(|e| e.to_string())?;
-        let file_size = metadata.len();
-        
-        // Read at most 5MB from the end
-        let read_size = file_size.min(5_000_000) as usize;
-        let mut buffer = vec![0u8; read_size];
-        file.seek(SeekFrom::End(-(read_size as i64))).map_err(|e| e.to_string())?;
-        file.read_exact(&mut buffer).map_err(|e| e.to_string())?;
-        
-        let content = String::from_utf8_lossy(&buffer);
-        if let Some(day) = calculate_day_from_log(&content, hours, retro) {
-            return Ok(day);
-        }
+    let mut state = load_state();
+    state.phash_prefilter = enabled;
+    state.phash_top_k = top_k;
+    save_state(&state);
+    println!("[Config] pHash prefilter updated to: {} (top {})", enabled, top_k);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_excluded_window_titles() -> Vec<String> {
+    load_state().excluded_window_titles
+}
+
+#[tauri::command]
+fn set_excluded_window_titles(titles: Vec<String>) {
+    let titles: Vec<String> = titles.into_iter().map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect();
+    let mut state = load_state();
+    state.excluded_window_titles = titles.clone();
+    save_state(&state);
+    println!("[Config] Excluded window titles updated to: {:?}", titles);
+}
+
+#[tauri::command]
+fn get_capture_inset() -> monster_recognition::CaptureInset {
+    load_state().capture_inset
+}
+
+#[tauri::command]
+fn set_capture_inset(inset: monster_recognition::CaptureInset) -> Result<(), String> {
+    inset.validate()?;
+    let mut state = load_state();
+    state.capture_inset = inset;
+    save_state(&state);
+    println!("[Config] Capture inset updated to: {:?}", inset);
+    Ok(())
+}
+
+// 识别相关配置的整体读写，设置页一次保存全部字段，避免逐项 set 时的部分更新
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RecognitionSettings {
+    pub monster_overlap_ratio: f32,
+    pub min_crop_size: u32,
+    pub enhance_contrast: bool,
+    pub nms_strategy: monster_recognition::NmsStrategy,
+    pub max_detections: usize,
+    pub merge_scan_detections: bool,
+    pub fullscreen_capture_fallback: bool,
+    pub toast_dedup_ms: u64,
+    pub phash_prefilter: bool,
+    pub phash_top_k: usize,
+    #[serde(default)]
+    pub capture_inset: monster_recognition::CaptureInset,
+    #[serde(default)]
+    pub orb_params: monster_recognition::OrbParams,
+}
+
+#[tauri::command]
+fn get_recognition_settings() -> RecognitionSettings {
+    let state = load_state();
+    RecognitionSettings {
+        monster_overlap_ratio: state.monster_overlap_ratio,
+        min_crop_size: state.min_crop_size,
+        enhance_contrast: state.enhance_contrast,
+        nms_strategy: state.nms_strategy,
+        max_detections: state.max_detections,
+        merge_scan_detections: state.merge_scan_detections,
+        fullscreen_capture_fallback: state.fullscreen_capture_fallback,
+        toast_dedup_ms: state.toast_dedup_ms,
+        phash_prefilter: state.phash_prefilter,
+        phash_top_k: state.phash_top_k,
+        capture_inset: state.capture_inset,
+        orb_params: state.orb_params,
+    }
+}
+
+// 先整体校验再一次性写入，任一字段非法则不做任何修改
+#[tauri::command]
+fn set_recognition_settings(settings: RecognitionSettings) -> Result<(), String> {
+    if !(settings.monster_overlap_ratio > 0.0 && settings.monster_overlap_ratio <= 1.0) {
+        return Err(format!("Overlap ratio must be in (0, 1], got {}", settings.monster_overlap_ratio));
+    }
+    if settings.min_crop_size == 0 {
+        return Err("Minimum crop size must be greater than 0".into());
     }
+    if settings.phash_top_k == 0 {
+        return Err("pHash top K must be greater than 0".into());
+    }
+    settings.capture_inset.validate()?;
+    settings.orb_params.validate()?;
+    let mut state = load_state();
+    state.monster_overlap_ratio = settings.monster_overlap_ratio;
+    state.min_crop_size = settings.min_crop_size;
+    state.enhance_contrast = settings.enhance_contrast; // 重启后生效
+    state.nms_strategy = settings.nms_strategy;
+    state.max_detections = settings.max_detections;
+    state.merge_scan_detections = settings.merge_scan_detections;
+    state.fullscreen_capture_fallback = settings.fullscreen_capture_fallback;
+    state.toast_dedup_ms = settings.toast_dedup_ms;
+    state.phash_prefilter = settings.phash_prefilter;
+    state.phash_top_k = settings.phash_top_k;
+    state.capture_inset = settings.capture_inset;
+    state.orb_params = settings.orb_params; // 重启后生效
+    save_state(&state);
+    println!("[Config] Recognition settings updated: {:?}", settings);
+    Ok(())
+}
 
-    Ok(1)
+#[tauri::command]
+fn get_minimize_to_tray() -> bool {
+    load_state().minimize_to_tray
 }
 
 #[tauri::command]
-fn update_day(day: u32) -> Result<(), String> {
+fn set_minimize_to_tray(enabled: bool) {
     let mut state = load_state();
-    state.day = day;
+    state.minimize_to_tray = enabled;
     save_state(&state);
-    println!("[State] Manually updated Day to: {}", day);
+    println!("[Config] Minimize to tray updated to: {}", enabled);
+}
+
+#[tauri::command]
+fn get_custom_log_path() -> Option<String> {
+    load_state().custom_log_path
+}
+
+// 传 None 或空字符串恢复默认路径；日志监控线程被唤醒后重新解析路径并改读新文件，无需重启
+#[tauri::command]
+fn set_custom_log_path(path: Option<String>) -> Result<(), String> {
+    let path = path.map(|p| p.trim().to_string()).filter(|p| !p.is_empty());
+    if let Some(p) = &path {
+        if !PathBuf::from(p).is_file() {
+            return Err(format!("日志文件不存在: {}", p));
+        }
+    }
+    let mut state = load_state();
+    state.custom_log_path = path.clone();
+    save_state(&state);
+    println!("[Config] Custom log path updated to: {:?}", path);
+    LOG_PATH_CHANGED.store(true, Ordering::SeqCst);
+    wake_log_monitor();
     Ok(())
 }
 
-#[tauri::command]
-fn get_detection_hotkey() -> Option<i32> {
-    load_state().detection_hotkey
+#[tauri::command]
+fn get_auto_yolo_on_state_change() -> bool {
+    load_state().auto_yolo_on_state_change
+}
+
+#[tauri::command]
+fn set_auto_yolo_on_state_change(enabled: bool) {
+    let mut state = load_state();
+    state.auto_yolo_on_state_change = enabled;
+    save_state(&state);
+    println!("[Config] Auto YOLO on state change updated to: {}", enabled);
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UpdateSettings {
+    pub auto_check_updates: bool,
+}
+
+#[tauri::command]
+fn get_update_settings() -> UpdateSettings {
+    UpdateSettings { auto_check_updates: load_state().auto_check_updates }
+}
+
+#[tauri::command]
+fn set_update_settings(settings: UpdateSettings) {
+    let mut state = load_state();
+    state.auto_check_updates = settings.auto_check_updates;
+    save_state(&state);
+    println!("[Config] Auto check updates set to: {}", settings.auto_check_updates);
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct UpdateCheckResult {
+    pub available: bool,
+    pub current_version: String,
+    pub version: Option<String>,
+    pub notes: Option<String>,
+}
+
+// 手动检查更新（不受 auto_check_updates 影响）
+#[tauri::command]
+async fn check_for_update_now(app: tauri::AppHandle) -> Result<UpdateCheckResult, String> {
+    use tauri_plugin_updater::UpdaterExt;
+
+    let current_version = app.package_info().version.to_string();
+    let update = app.updater().map_err(|e| e.to_string())?
+        .check().await
+        .map_err(|e| {
+            log_to_file(&format!("[Update] Check failed: {}", e));
+            e.to_string()
+        })?;
+
+    println!("[Update] Check finished, update available: {}", update.is_some());
+    Ok(UpdateCheckResult {
+        available: update.is_some(),
+        current_version,
+        version: update.as_ref().map(|u| u.version.clone()),
+        notes: update.and_then(|u| u.body),
+    })
+}
+
+fn get_prev_log_path() -> PathBuf {
+    let mut p = get_log_path();
+    p.set_file_name("Player-prev.log");
+    p
+}
+
+// 以共享读方式打开日志：游戏持有 Player.log 时普通打开可能因共享冲突失败
+// Windows 下显式指定 FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE
+fn open_log_shared(path: &PathBuf) -> std::io::Result<File> {
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::fs::OpenOptionsExt;
+        std::fs::OpenOptions::new().read(true).share_mode(0x1 | 0x2 | 0x4).open(path)
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        File::open(path)
+    }
+}
+
+// 权限不足或 Windows 共享冲突 (ERROR_SHARING_VIOLATION = 32) / 锁冲突 (ERROR_LOCK_VIOLATION = 33)
+fn is_log_lock_error(e: &std::io::Error) -> bool {
+    e.kind() == std::io::ErrorKind::PermissionDenied || matches!(e.raw_os_error(), Some(32) | Some(33))
+}
+
+fn save_state(state: &PersistentState) {
+    let path = get_cache_path();
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    if let Ok(json) = serde_json::to_string(state) {
+        let _ = std::fs::write(path, json);
+    }
+}
+
+fn load_state() -> PersistentState {
+    let path = get_cache_path();
+    if let Ok(json) = std::fs::read_to_string(path) {
+        if let Ok(state) = serde_json::from_str::<PersistentState>(&json) {
+            return state;
+        }
+    }
+    PersistentState::default()
+}
+
+// 导出/导入配置时排除的运行态字段：手牌/仓库、实例映射、当前天数与本局战绩、上次跳转记录，
+// 以及只对本机有效的自定义日志路径
+const RUNTIME_STATE_FIELDS: [&str; 8] = [
+    "day", "inst_to_temp", "current_hand", "current_stash", "last_jumped_day", "last_recognized_monster",
+    "custom_log_path",
+];
+
+fn validate_hotkey(name: &str, hotkey: &Hotkey) -> Result<(), String> {
+    // Windows 虚拟键码范围 1-254
+    let is_valid_code = |code: i32| (1..=254).contains(&code);
+    if !is_valid_code(hotkey.key) {
+        return Err(format!("热键 {} 的键码非法: {} (应为 1-254)", name, hotkey.key));
+    }
+    if let Some(m) = hotkey.modifiers.iter().find(|&&m| !is_valid_code(m) || m == hotkey.key) {
+        return Err(format!("热键 {} 的修饰键非法: {}", name, m));
+    }
+    Ok(())
+}
+
+// 导入配置的整体校验，规则与对应的 set_xxx 命令保持一致
+fn validate_settings(state: &PersistentState) -> Result<(), String> {
+    let hotkeys = [
+        ("detection", &state.detection_hotkey),
+        ("card_detection", &state.card_detection_hotkey),
+        ("toggle_collapse", &state.toggle_collapse_hotkey),
+        ("yolo", &state.yolo_hotkey),
+        ("detail_display", &state.detail_display_hotkey),
+    ];
+    for (name, hotkey) in hotkeys {
+        if let Some(hotkey) = hotkey {
+            validate_hotkey(name, hotkey)?;
+        }
+    }
+    if let Some(name) = state.disabled_hotkeys.iter().find(|n| !HOTKEY_NAMES.contains(&n.as_str())) {
+        return Err(format!("未知热键: {} (可选 {})", name, HOTKEY_NAMES.join("/")));
+    }
+    if !(state.monster_overlap_ratio > 0.0 && state.monster_overlap_ratio <= 1.0) {
+        return Err(format!("Overlap ratio must be in (0, 1], got {}", state.monster_overlap_ratio));
+    }
+    if state.min_crop_size == 0 {
+        return Err("Minimum crop size must be greater than 0".into());
+    }
+    if state.phash_top_k == 0 {
+        return Err("pHash top K must be greater than 0".into());
+    }
+    for (name, v) in [("conf", state.yolo_conf_threshold), ("iou", state.yolo_iou_threshold)] {
+        if !(v > 0.0 && v <= 1.0) {
+            return Err(format!("{} 阈值必须在 (0, 1] 范围内: {}", name, v));
+        }
+    }
+    if state.event_min_matches == 0 {
+        return Err("最少匹配点数必须大于 0".to_string());
+    }
+    if !(0.0..1.0).contains(&state.event_min_confidence) {
+        return Err(format!("置信度阈值必须在 [0, 1) 范围内: {}", state.event_min_confidence));
+    }
+    if !is_hex_color(&state.overlay_box_color) {
+        return Err(format!("Invalid color: {} (expected #RRGGBB or #RRGGBBAA)", state.overlay_box_color));
+    }
+    if !(state.overlay_opacity > 0.0 && state.overlay_opacity <= 1.0) {
+        return Err(format!("Invalid opacity: {} (expected 0 < opacity <= 1)", state.overlay_opacity));
+    }
+    if state.recognition_cache_ms > 10_000 {
+        return Err(format!("缓存有效期不能超过 10000ms: {}", state.recognition_cache_ms));
+    }
+    monster_recognition::FeatureAlgorithm::parse(&state.feature_algorithm)?;
+    state.capture_inset.validate()?;
+    state.orb_params.validate()?;
+    if let Some(region) = &state.monster_scan_region {
+        region.validate()?;
+    }
+    Ok(())
+}
+
+/// 导出除运行态（手牌/仓库等）之外的全部配置，换机器或重装后用 import_settings 恢复
+#[tauri::command]
+fn export_settings() -> String {
+    let mut value = serde_json::to_value(load_state()).unwrap_or_default();
+    if let Some(obj) = value.as_object_mut() {
+        for field in RUNTIME_STATE_FIELDS {
+            obj.remove(field);
+        }
+    }
+    serde_json::to_string_pretty(&value).unwrap_or_default()
+}
+
+/// 把 export_settings 导出的 JSON 合并进当前配置：缺失字段保持当前值，未知字段与运行态字段忽略，
+/// 任一字段非法则不做任何修改（热键、阈值等部分配置重启后才完全生效）
+#[tauri::command]
+fn import_settings(json: String) -> Result<(), String> {
+    let incoming: serde_json::Value = serde_json::from_str(&json).map_err(|e| format!("配置文件不是合法的 JSON: {}", e))?;
+    let incoming = incoming.as_object().ok_or("配置文件顶层必须是 JSON 对象")?;
+
+    let mut merged = serde_json::to_value(load_state()).map_err(|e| e.to_string())?;
+    let fields = merged.as_object_mut().ok_or("Failed to serialize current state")?;
+    let mut ignored = Vec::new();
+    for (key, value) in incoming {
+        if RUNTIME_STATE_FIELDS.contains(&key.as_str()) || !fields.contains_key(key) {
+            ignored.push(key.as_str());
+            continue;
+        }
+        fields.insert(key.clone(), value.clone());
+        // 逐字段试解析，出错时能指出具体是哪个字段
+        if let Err(e) = serde_json::from_value::<PersistentState>(serde_json::Value::Object(fields.clone())) {
+            return Err(format!("字段 {} 无效: {}", key, e));
+        }
+    }
+
+    let state: PersistentState = serde_json::from_value(merged).map_err(|e| e.to_string())?;
+    validate_settings(&state)?;
+    save_state(&state);
+    println!("[Config] Settings imported ({} fields, ignored: {:?})", incoming.len() - ignored.len(), ignored);
+    Ok(())
+}
+
+fn lookup_item(tid: &str, items_db: &ItemDb, skills_db: &SkillDb) -> Option<ItemData> {
+    if let Some(&index) = items_db.id_map.get(tid) {
+        return items_db.list.get(index).cloned();
+    }
+    if let Some(&index) = skills_db.id_map.get(tid) {
+        return skills_db.list.get(index).cloned();
+    }
+    None
+}
+
+fn lookup_item_by_name(name_cn: &str, items_db: &ItemDb, skills_db: &SkillDb) -> Option<ItemData> {
+    // 先在物品库中查找完整名字
+    for item in &items_db.list {
+        if item.name_cn == name_cn {
+            return Some(item.clone());
+        }
+    }
+    // 再在技能库中查找完整名字
+    for skill in &skills_db.list {
+        if skill.name_cn == name_cn {
+            return Some(skill.clone());
+        }
+    }
+    
+    // 如果找不到，尝试去除空格及空格之前的前缀（如"毒性蔓延 獠牙" -> "獠牙"）
+    if let Some(space_pos) = name_cn.rfind(' ') {
+        let base_name = &name_cn[space_pos + 1..];
+        
+        // 用基础名字再查找一次
+        for item in &items_db.list {
+            if item.name_cn == base_name {
+                return Some(item.clone());
+            }
+        }
+        for skill in &skills_db.list {
+            if skill.name_cn == base_name {
+                return Some(skill.clone());
+            }
+        }
+    }
+    
+    None
+}
+
+// --- Commands ---
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct SearchQuery {
+    pub keyword: Option<String>,
+    pub item_type: Option<String>, // "all", "item", "skill"
+    pub size: Option<String>,
+    pub start_tier: Option<String>,
+    pub hero: Option<String>,
+    pub tags: Option<String>,
+    pub hidden_tags: Option<String>,
+    // 数值范围过滤（闭区间），取物品当前品阶的数值
+    pub min_damage: Option<i32>,
+    pub max_damage: Option<i32>,
+    pub min_cooldown: Option<f32>,
+    pub max_cooldown: Option<f32>,
+    pub min_shield: Option<i32>,
+    pub max_shield: Option<i32>,
+    pub min_heal: Option<i32>,
+    pub max_heal: Option<i32>,
+    // 设置了范围但物品没有该数值时：默认不匹配，true 则跳过该条件
+    pub skip_missing_stats: Option<bool>,
+    // 关键词子串无结果时回退到模糊匹配（编辑距离相似度），fuzzy_threshold 为最低相似度 (0, 1]
+    pub fuzzy: Option<bool>,
+    pub fuzzy_threshold: Option<f32>,
+    // 结果排序字段："tier" / "damage" / "cooldown" / "name"，未设置时按相似度、品阶、中文名排序
+    pub sort_by: Option<String>,
+    pub sort_desc: Option<bool>,
+}
+
+const FUZZY_DEFAULT_THRESHOLD: f32 = 0.5;
+const FUZZY_MAX_RESULTS: usize = 20;
+
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        curr[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == cb { 0 } else { 1 };
+            curr[j + 1] = (prev[j + 1] + 1).min(curr[j] + 1).min(prev[j] + cost);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+    prev[b.len()]
+}
+
+// 关键词与名称的相似度 [0, 1]：整体编辑距离，或与名称中等长子串的最佳编辑距离（只输入了部分名称时）
+fn fuzzy_similarity(keyword: &str, text: &str) -> f32 {
+    let k: Vec<char> = keyword.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    if k.is_empty() || t.is_empty() {
+        return 0.0;
+    }
+    let whole = 1.0 - levenshtein(&k, &t) as f32 / k.len().max(t.len()) as f32;
+    let partial = if t.len() > k.len() {
+        t.windows(k.len())
+            .map(|w| 1.0 - levenshtein(&k, w) as f32 / k.len() as f32)
+            .fold(0.0, f32::max)
+    } else {
+        0.0
+    };
+    whole.max(partial)
+}
+
+// 中文名的拼音别名 (全拼, 首字母)，如 "短剑" -> ("duanjian", "dj")，非汉字字符原样保留（小写）。
+// 只依赖 name_cn，按名称缓存，数据库重新加载后也不会过期
+static PINYIN_ALIASES: OnceLock<std::sync::Mutex<HashMap<String, (String, String)>>> = OnceLock::new();
+
+fn pinyin_aliases(name_cn: &str) -> (String, String) {
+    let cache = PINYIN_ALIASES.get_or_init(|| std::sync::Mutex::new(HashMap::new()));
+    if let Some(hit) = cache.lock().ok().and_then(|c| c.get(name_cn).cloned()) {
+        return hit;
+    }
+    use pinyin::ToPinyin;
+    let mut full = String::new();
+    let mut initials = String::new();
+    for (ch, py) in name_cn.chars().zip(name_cn.to_pinyin()) {
+        match py {
+            Some(py) => {
+                full.push_str(py.plain());
+                initials.push_str(py.first_letter());
+            }
+            None if ch.is_alphanumeric() => {
+                full.extend(ch.to_lowercase());
+                initials.extend(ch.to_lowercase());
+            }
+            None => {}
+        }
+    }
+    if let Ok(mut c) = cache.lock() {
+        c.insert(name_cn.to_string(), (full.clone(), initials.clone()));
+    }
+    (full, initials)
+}
+
+// 拼音别名匹配只针对纯 ASCII 关键词：全拼包含关键词，或首字母以关键词开头（至少两个字母，避免单字母命中大半物品）
+fn matches_pinyin_alias(keyword: &str, name_cn: &str) -> bool {
+    if !keyword.is_ascii() {
+        return false;
+    }
+    let k: String = keyword.chars().filter(|c| !c.is_whitespace()).collect();
+    if k.is_empty() {
+        return false;
+    }
+    let (full, initials) = pinyin_aliases(name_cn);
+    full.contains(&k) || (k.len() >= 2 && initials.starts_with(&k))
+}
+
+// 数值范围判断：未设置上下限时总是通过
+fn stat_in_range<T: PartialOrd + Copy>(value: Option<T>, min: Option<T>, max: Option<T>, skip_missing: bool) -> bool {
+    if min.is_none() && max.is_none() {
+        return true;
+    }
+    match value {
+        None => skip_missing,
+        Some(v) => min.map_or(true, |m| v >= m) && max.map_or(true, |m| v <= m),
+    }
+}
+
+// 搜索结果 LRU 缓存：输入联想时同一前缀会反复查询。键为规范化后的查询，值只存结果 uuid（按排序后顺序），
+// 命中时再按 id_map 解析为 ItemData。物品/技能库重新加载时必须调用 invalidate_search_cache
+const SEARCH_CACHE_CAPACITY: usize = 64;
+static SEARCH_CACHE: OnceLock<std::sync::Mutex<std::collections::VecDeque<(String, Vec<String>)>>> = OnceLock::new();
+
+fn get_search_cache() -> &'static std::sync::Mutex<std::collections::VecDeque<(String, Vec<String>)>> {
+    SEARCH_CACHE.get_or_init(|| std::sync::Mutex::new(std::collections::VecDeque::new()))
+}
+
+fn invalidate_search_cache() -> usize {
+    get_search_cache().lock().map(|mut c| { let n = c.len(); c.clear(); n }).unwrap_or(0)
+}
+
+impl SearchQuery {
+    fn cache_key(&self) -> String {
+        let norm = |v: &Option<String>| v.as_deref().map(|s| s.trim().to_lowercase()).unwrap_or_default();
+        [
+            norm(&self.keyword),
+            self.item_type.as_deref().unwrap_or("all").to_lowercase(),
+            norm(&self.size),
+            norm(&self.start_tier),
+            norm(&self.hero),
+            norm(&self.tags),
+            norm(&self.hidden_tags),
+            format!("{:?}", (self.min_damage, self.max_damage, self.min_cooldown, self.max_cooldown)),
+            format!("{:?}", (self.min_shield, self.max_shield, self.min_heal, self.max_heal, self.skip_missing_stats.unwrap_or(false))),
+            format!("{:?}", (self.fuzzy.unwrap_or(false), self.fuzzy_threshold)),
+            format!("{}:{}", norm(&self.sort_by), self.sort_desc.unwrap_or(false)),
+        ].join("\u{1f}")
+    }
+}
+
+#[tauri::command]
+fn search_items(query: SearchQuery, state: State<'_, DbState>) -> Result<Vec<ItemData>, String> {
+    search_items_internal(query, &state)
+}
+
+/// 本地 HTTP 接口 (GET /item/{id}, GET /search?keyword=) 的端口，未启动时为 None
+#[tauri::command]
+fn get_api_port() -> Option<u16> {
+    local_api::api_port()
+}
+
+// Simple tier sort logic (Bronze < Silver < Gold < Diamond < Legendary)
+fn tier_rank(tier: &str) -> u8 {
+    match tier.split('/').next().unwrap_or("").trim() {
+        "Bronze" | "Common" => 1,
+        "Silver" => 2,
+        "Gold" => 3,
+        "Diamond" => 4,
+        "Legendary" => 5,
+        _ => 10,
+    }
+}
+
+// 按数值排序时缺失值（None）无论升降序都排在最后
+fn cmp_missing_last<T>(a: Option<T>, b: Option<T>, desc: bool, cmp: impl Fn(&T, &T) -> std::cmp::Ordering) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+    match (a, b) {
+        (Some(a), Some(b)) => if desc { cmp(&b, &a) } else { cmp(&a, &b) },
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+// search_items 的实际实现，本地 HTTP 接口也复用这里
+fn search_items_internal(query: SearchQuery, state: &DbState) -> Result<Vec<ItemData>, String> {
+    let sort_by = query.sort_by.as_deref().map(|s| s.trim().to_lowercase()).filter(|s| !s.is_empty());
+    if let Some(ref field) = sort_by {
+        if !["tier", "damage", "cooldown", "name"].contains(&field.as_str()) {
+            return Err(format!("不支持的排序字段: {} (可选 tier/damage/cooldown/name)", field));
+        }
+    }
+    let cache_key = query.cache_key();
+    let cached_ids = get_search_cache().lock().ok().and_then(|mut cache| {
+        let pos = cache.iter().position(|(k, _)| *k == cache_key)?;
+        let entry = cache.remove(pos)?;
+        let ids = entry.1.clone();
+        cache.push_front(entry);
+        Some(ids)
+    });
+    if let Some(ids) = cached_ids {
+        let items_db = state.items.read().map_err(|_| "DB Busy")?;
+        let skills_db = state.skills.read().map_err(|_| "DB Busy")?;
+        return Ok(ids.iter().filter_map(|id| lookup_item(id, &items_db, &skills_db)).collect());
+    }
+
+    let keyword = query.keyword.as_deref().map(|s| s.to_lowercase());
+    let size_filter = query.size.as_deref().map(|s| s.to_lowercase());
+    let tier_filter = query.start_tier.as_deref().map(|s| s.to_lowercase());
+    let hero_filter = query.hero.as_deref().map(|s| s.to_lowercase());
+    let tags_filter = query.tags.as_deref().map(|s| s.to_lowercase());
+    let htags_filter = query.hidden_tags.as_deref().map(|s| s.to_lowercase());
+    let skip_missing = query.skip_missing_stats.unwrap_or(false);
+
+    let match_keyword = |item: &ItemData| -> bool {
+        match keyword {
+            Some(ref k) => item.name_cn.to_lowercase().contains(k)
+                || item.name.to_lowercase().contains(k)
+                || matches_pinyin_alias(k, &item.name_cn),
+            None => true,
+        }
+    };
+
+    // 关键词以外的过滤条件
+    let match_filters = |item: &ItemData| -> bool {
+        if let Some(ref s) = size_filter {
+            if !item.size.as_ref().map(|v| v.to_lowercase()).unwrap_or_default().contains(s) {
+                return false;
+            }
+        }
+        if let Some(ref t) = tier_filter {
+            if !item.tier.to_lowercase().contains(t) {
+                return false;
+            }
+        }
+        if let Some(ref h) = hero_filter {
+            if !item.heroes.iter().any(|hero| hero.to_lowercase().contains(h)) {
+                return false;
+            }
+        }
+        if let Some(ref t) = tags_filter {
+             if !item.tags.to_lowercase().contains(t) {
+                 return false;
+             }
+        }
+        if let Some(ref h) = htags_filter {
+             if !item.hidden_tags.to_lowercase().contains(h) {
+                 return false;
+             }
+        }
+        // 非武器的伤害、被动物品的冷却等按 0 存储，先转成 None 再判断，才会受 skip_missing 控制
+        stat_in_range(present_stat(item.damage, &item.damage_tiers), query.min_damage, query.max_damage, skip_missing)
+            && stat_in_range(present_stat(item.cooldown, &item.cooldown_tiers), query.min_cooldown, query.max_cooldown, skip_missing)
+            && stat_in_range(present_stat(item.shield, &item.shield_tiers), query.min_shield, query.max_shield, skip_missing)
+            && stat_in_range(present_stat(item.heal, &item.heal_tiers), query.min_heal, query.max_heal, skip_missing)
+    };
+
+    let search_type = query.item_type.as_deref().unwrap_or("all");
+
+    // 候选集合：满足非关键词过滤条件的物品与技能；过滤、打分、排序都只借用，最后才克隆返回的结果
+    let items_db = state.items.read().map_err(|_| "DB Busy")?;
+    let skills_db = state.skills.read().map_err(|_| "DB Busy")?;
+    let mut candidates: Vec<&ItemData> = Vec::new();
+    if search_type == "all" || search_type == "item" {
+        candidates.extend(items_db.list.iter().filter(|item| match_filters(item)));
+    }
+    if search_type == "all" || search_type == "skill" {
+        candidates.extend(skills_db.list.iter().filter(|item| match_filters(item)));
+    }
+
+    // (相似度, 物品)：精确子串匹配相似度为 1
+    let mut scored: Vec<(f32, &ItemData)> = candidates.iter()
+        .filter(|item| match_keyword(item))
+        .map(|item| (1.0, *item))
+        .collect();
+
+    if scored.is_empty() && query.fuzzy.unwrap_or(false) {
+        if let Some(ref k) = keyword {
+            let threshold = query.fuzzy_threshold.filter(|t| *t > 0.0 && *t <= 1.0).unwrap_or(FUZZY_DEFAULT_THRESHOLD);
+            scored = candidates.into_iter()
+                .map(|item| {
+                    let mut sim = fuzzy_similarity(k, &item.name_cn.to_lowercase()).max(fuzzy_similarity(k, &item.name.to_lowercase()));
+                    // 拼音输入打错时按全拼别名比较
+                    if k.is_ascii() {
+                        sim = sim.max(fuzzy_similarity(k, &pinyin_aliases(&item.name_cn).0));
+                    }
+                    (sim, item)
+                })
+                .filter(|(sim, _)| *sim >= threshold)
+                .collect();
+            scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+            scored.truncate(FUZZY_MAX_RESULTS);
+            println!("[Search] No exact match for '{}', fuzzy fallback returned {} items", k, scored.len());
+        }
+    }
+
+    // Sort by similarity, then tier, then name
+    scored.sort_by(|(sa, a), (sb, b)| {
+        if sa != sb {
+            return sb.total_cmp(sa);
+        }
+        let ta = tier_rank(&a.tier);
+        let tb = tier_rank(&b.tier);
+        if ta != tb {
+            ta.cmp(&tb)
+        } else {
+            a.name_cn.cmp(&b.name_cn)
+        }
+    });
+    let mut results: Vec<&ItemData> = scored.into_iter().map(|(_, item)| item).collect();
+
+    // 指定排序字段时在默认顺序上做稳定排序，字段相同的物品保持默认顺序
+    if let Some(field) = sort_by {
+        let desc = query.sort_desc.unwrap_or(false);
+        // 按 0 存储的缺失属性（非武器的伤害、被动物品的冷却）视为 None，排在最后
+        let damage = |item: &ItemData| present_stat(item.damage, &item.damage_tiers);
+        let cooldown = |item: &ItemData| present_stat(item.cooldown, &item.cooldown_tiers);
+        results.sort_by(|&a, &b| match field.as_str() {
+            "tier" => cmp_missing_last(Some(tier_rank(&a.tier)), Some(tier_rank(&b.tier)), desc, |x, y| x.cmp(y)),
+            "damage" => cmp_missing_last(damage(a), damage(b), desc, |x, y| x.cmp(y)),
+            "cooldown" => cmp_missing_last(cooldown(a), cooldown(b), desc, |x, y| x.total_cmp(y)),
+            _ => cmp_missing_last(Some(&a.name_cn), Some(&b.name_cn), desc, |x, y| x.cmp(y)),
+        });
+    }
+
+    if let Ok(mut cache) = get_search_cache().lock() {
+        cache.push_front((cache_key, results.iter().map(|item| item.uuid.clone()).collect()));
+        cache.truncate(SEARCH_CACHE_CAPACITY);
+    }
+
+    Ok(results.into_iter().cloned().collect())
+}
+
+#[tauri::command]
+fn get_data_version() -> Result<serde_json::Value, String> {
+    let versions = get_data_versions().read().map_err(|_| "DB Busy")?;
+    Ok(serde_json::json!({
+        "app_version": env!("CARGO_PKG_VERSION"),
+        "items": versions.get("items"),
+        "skills": versions.get("skills"),
+        "monsters": versions.get("monsters"),
+        "monsters_export": versions.get("monsters_export"),
+    }))
+}
+
+#[tauri::command]
+fn reload_events(app: tauri::AppHandle, state: State<'_, DbState>) -> Result<usize, String> {
+    let events = load_events_db(&app)?;
+    let count = events.len();
+    *state.events.write().map_err(|_| "DB Busy")? = events;
+    println!("[Events] Reloaded {} events from disk", count);
+    Ok(count)
+}
+
+// 按 Id 获取完整事件数据（启动时已加载到内存），未找到返回 None
+#[tauri::command]
+fn get_event_by_id(state: State<'_, DbState>, id: String) -> Result<Option<serde_json::Value>, String> {
+    let events = state.events.read().map_err(|_| "DB Busy")?;
+    Ok(events.get(&id).cloned())
+}
+
+#[tauri::command]
+fn get_heroes(state: State<'_, DbState>) -> Result<Vec<String>, String> {
+    let db = state.items.read().map_err(|_| "DB Busy")?;
+    Ok(db.unique_heroes.clone())
+}
+
+#[tauri::command]
+fn get_all_monsters(state: State<'_, DbState>) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+    log_to_file("get_all_monsters called");
+    let db = state.monsters.read().map_err(|_| "DB Busy")?;
+    let count = db.len();
+    log_to_file(&format!("Monsters DB contains {} entries", count));
+    
+    // 调试：输出前几个怪物名称
+    if count > 0 {
+        let sample_names: Vec<String> = db.keys().take(5).cloned().collect();
+        log_to_file(&format!("Sample monster names: {:?}", sample_names));
+    } else {
+        log_to_file("Warning: Monsters DB is empty!");
+    }
+    
+    Ok(db.clone())
+}
+
+// 技能浏览用的精简字段
+#[derive(Debug, Serialize, Clone)]
+pub struct SkillSummary {
+    pub uuid: String,
+    pub name: String,
+    pub name_cn: String,
+    pub tier: String,
+    pub heroes: Vec<String>,
+    pub image: Option<String>,
+}
+
+// 分页返回技能库：offset 默认 0，limit 缺省返回全部；summary 为 true 时只返回 SkillSummary 字段
+#[tauri::command]
+fn get_all_skills(state: State<'_, DbState>, offset: Option<usize>, limit: Option<usize>, summary: Option<bool>) -> Result<serde_json::Value, String> {
+    let db = state.skills.read().map_err(|_| "DB Busy")?;
+    let total = db.list.len();
+    let offset = offset.unwrap_or(0).min(total);
+    let page = db.list.iter().skip(offset).take(limit.unwrap_or(total));
+    let skills = if summary.unwrap_or(false) {
+        serde_json::to_value(page.map(|s| SkillSummary {
+            uuid: s.uuid.clone(),
+            name: s.name.clone(),
+            name_cn: s.name_cn.clone(),
+            tier: s.tier.clone(),
+            heroes: s.heroes.clone(),
+            image: s.image.clone(),
+        }).collect::<Vec<_>>())
+    } else {
+        serde_json::to_value(page.collect::<Vec<_>>())
+    }.map_err(|e| e.to_string())?;
+    Ok(serde_json::json!({ "total": total, "offset": offset, "skills": skills }))
+}
+
+// 怪物难度评分：装备总伤害 + 生命值 / 10 + 每件装备 10 分
+// 启动时在怪物数据合并完成后计算一次，写入 threat_score / loadout_damage / item_count 字段
+fn compute_monster_threat(monster: &serde_json::Value, items_db: &ItemDb, skills_db: &SkillDb) -> (f64, i64, usize) {
+    let mut total_damage: i64 = 0;
+    let mut item_count = 0;
+    if let Some(items) = monster.get("items").and_then(|v| v.as_array()) {
+        for sub in items {
+            item_count += 1;
+            let id = sub.get("id").and_then(|v| v.as_str()).unwrap_or("");
+            let damage = sub.get("damage").and_then(|v| v.as_i64())
+                .or_else(|| lookup_item(id, items_db, skills_db).and_then(|it| it.damage).map(|d| d as i64))
+                .unwrap_or(0);
+            total_damage += damage.max(0);
+        }
+    }
+    let health = monster.get("health").and_then(|v| v.as_f64()).unwrap_or(0.0);
+    let score = total_damage as f64 + health / 10.0 + item_count as f64 * 10.0;
+    (score, total_damage, item_count)
+}
+
+// 指定天数的怪物按难度从低到高排序
+#[tauri::command]
+fn get_monsters_sorted_by_threat(state: State<'_, DbState>, day: u32) -> Result<Vec<serde_json::Value>, String> {
+    let monsters = state.monsters.read().map_err(|_| "DB Busy")?;
+    let label = day_bucket_label(day);
+    let mut list: Vec<serde_json::Value> = monsters.values()
+        .filter(|m| m.get("available").and_then(|v| v.as_str()).map(canonical_day_label).as_deref() == Some(label.as_str()))
+        .cloned()
+        .collect();
+    let score = |m: &serde_json::Value| m.get("threat_score").and_then(|v| v.as_f64()).unwrap_or(0.0);
+    list.sort_by(|a, b| score(a).partial_cmp(&score(b)).unwrap_or(std::cmp::Ordering::Equal));
+    Ok(list)
+}
+
+// 克制关系：(怪物具备的机制, 可应对该机制的物品机制, 权重)
+const COUNTER_RULES: &[(&str, &str, f32)] = &[
+    ("Damage", "Shield", 1.0),
+    ("Damage", "Heal", 0.6),
+    ("Burn", "Heal", 0.8),
+    ("Burn", "Shield", 0.5),
+    ("Poison", "Heal", 0.8),
+    ("Poison", "Regen", 0.6),
+    ("Freeze", "Haste", 0.7),
+    ("Slow", "Haste", 0.8),
+    ("Shield", "Poison", 0.9),
+    ("Shield", "Burn", 0.5),
+    ("Heal", "Burn", 0.6),
+    ("Regen", "Damage", 0.5),
+    ("Health", "Damage", 0.6),
+    ("Health", "Poison", 0.4),
+];
+
+// 物品具备的机制：隐藏标签的英文部分（忽略 xxxRef 引用标签）+ 数值字段非零
+fn item_mechanics(item: &ItemData) -> HashSet<String> {
+    let mut mechanics: HashSet<String> = item.hidden_tags.split('|')
+        .map(|t| t.split(" / ").next().unwrap_or(t).trim().to_string())
+        .filter(|t| !t.is_empty() && !t.ends_with("Ref") && !t.ends_with("Reference"))
+        .collect();
+    for (name, v) in [("Damage", item.damage), ("Shield", item.shield), ("Heal", item.heal),
+                      ("Burn", item.burn), ("Poison", item.poison), ("Regen", item.regen)] {
+        if v.unwrap_or(0) > 0 {
+            mechanics.insert(name.to_string());
+        }
+    }
+    mechanics
+}
+
+// 根据怪物装备/技能的机制给出克制物品建议（启发式）：
+// 统计怪物各机制的出现次数（生命值按每 500 计 1 次）并归一化，候选物品得分 = Σ 威胁权重 × 克制权重
+#[tauri::command]
+fn suggest_counters(state: State<'_, DbState>, monster_name: String, hero: Option<String>, limit: Option<usize>) -> Result<serde_json::Value, String> {
+    let monsters = state.monsters.read().map_err(|_| "DB Busy")?;
+    let items_db = state.items.read().map_err(|_| "DB Busy")?;
+    let skills_db = state.skills.read().map_err(|_| "DB Busy")?;
+
+    let monster = monsters.get(&monster_name)
+        .or_else(|| monsters.values().find(|m| {
+            m.get("name_zh").and_then(|v| v.as_str()) == Some(monster_name.as_str())
+                || m.get("name").and_then(|v| v.as_str()).map(|n| n.eq_ignore_ascii_case(&monster_name)).unwrap_or(false)
+        }))
+        .ok_or_else(|| format!("Monster not found: {}", monster_name))?;
+
+    let mut threats: HashMap<String, f32> = HashMap::new();
+    for key in ["items", "skills"] {
+        if let Some(list) = monster.get(key).and_then(|v| v.as_array()) {
+            for sub in list {
+                let id = sub.get("id").and_then(|v| v.as_str()).unwrap_or("");
+                if let Some(item) = lookup_item(id, &items_db, &skills_db) {
+                    for m in item_mechanics(&item) {
+                        *threats.entry(m).or_insert(0.0) += 1.0;
+                    }
+                }
+            }
+        }
+    }
+    let health = monster.get("health").and_then(|v| v.as_f64()).unwrap_or(0.0) as f32;
+    if health > 0.0 {
+        *threats.entry("Health".to_string()).or_insert(0.0) += health / 500.0;
+    }
+    let max_threat = threats.values().cloned().fold(0.0f32, f32::max);
+    if max_threat <= 0.0 {
+        return Ok(serde_json::json!({ "monster": monster_name, "threats": threats, "suggestions": [] }));
+    }
+    for v in threats.values_mut() {
+        *v /= max_threat;
+    }
+
+    let mut scored: Vec<(f32, Vec<String>, &ItemData)> = items_db.list.iter()
+        .filter(|item| match &hero {
+            Some(h) => item.heroes.iter().any(|ih| ih.contains(h.as_str()) || ih.starts_with("Common")),
+            None => true,
+        })
+        .filter_map(|item| {
+            let mechanics = item_mechanics(item);
+            let mut score = 0.0;
+            let mut reasons = Vec::new();
+            for (threat, counter, weight) in COUNTER_RULES {
+                let t = threats.get(*threat).cloned().unwrap_or(0.0);
+                if t > 0.0 && mechanics.contains(*counter) {
+                    score += t * weight;
+                    reasons.push(format!("{} vs {}", counter, threat));
+                }
+            }
+            if score > 0.0 { Some((score, reasons, item)) } else { None }
+        })
+        .collect();
+    scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal).then_with(|| a.2.name.cmp(&b.2.name)));
+    scored.truncate(limit.unwrap_or(20));
+
+    let suggestions: Vec<serde_json::Value> = scored.into_iter()
+        .map(|(score, reasons, item)| serde_json::json!({ "item": item, "score": score, "reasons": reasons }))
+        .collect();
+    Ok(serde_json::json!({ "monster": monster_name, "threats": threats, "suggestions": suggestions }))
+}
+
+#[tauri::command]
+fn debug_monsters_db(state: State<'_, DbState>) -> Result<String, String> {
+    let db = state.monsters.read().map_err(|_| "DB Busy")?;
+    let count = db.len();
+    let mut result = format!("Monsters DB Status:\n- Total entries: {}\n", count);
+    
+    if count > 0 {
+        let sample: Vec<String> = db.keys().take(10).cloned().collect();
+        result.push_str(&format!("- Sample entries: {:?}\n", sample));
+        
+        // 检查Day 1的怪物
+        let day1_monsters: Vec<String> = db.iter()
+            .filter(|(_, data)| {
+                data.get("available").and_then(|v| v.as_str()) == Some("Day 1")
+            })
+            .map(|(name, _)| name.clone())
+            .take(5)
+            .collect();
+        result.push_str(&format!("- Day 1 monsters: {:?}\n", day1_monsters));
+    } else {
+        result.push_str("- Database is empty!\n");
+    }
+    
+    log_to_file(&result);
+    Ok(result)
+}
+
+#[tauri::command]
+fn clear_yolo_cache() -> Result<String, String> {
+    // 清理YOLO扫描结果和图像缓存
+    {
+        let mut results = get_yolo_scan_results().write().unwrap();
+        results.clear();
+    }
+    {
+        let mut saved_img = get_yolo_scan_image().write().unwrap();
+        *saved_img = None;
+        *YOLO_SCAN_IMAGE_SCALE.write().unwrap() = 1.0;
+    }
+    log_to_file("YOLO cache cleared to free memory");
+    Ok("YOLO缓存已清理".to_string())
+}
+
+// 对已加载的物品和技能重新执行 process_tags（标签规则变化或数据补丁后使用），并重建 unique_tags
+#[tauri::command]
+fn recompute_processed_tags(state: State<'_, DbState>) -> Result<usize, String> {
+    let mut count = 0;
+    {
+        let mut items_db = state.items.write().map_err(|_| "DB Busy")?;
+        let mut tag_set = HashSet::new();
+        for item in items_db.list.iter_mut() {
+            item.processed_tags = process_tags(&item.tags);
+            tag_set.extend(item.processed_tags.iter().cloned());
+            count += 1;
+        }
+        let mut unique_tags: Vec<String> = tag_set.into_iter().collect();
+        unique_tags.sort();
+        items_db.unique_tags = unique_tags;
+    }
+    {
+        let mut skills_db = state.skills.write().map_err(|_| "DB Busy")?;
+        for skill in skills_db.list.iter_mut() {
+            skill.processed_tags = process_tags(&skill.tags);
+            count += 1;
+        }
+    }
+    invalidate_search_cache();
+    println!("[DB] Recomputed processed tags for {} entries", count);
+    Ok(count)
+}
+
+#[tauri::command]
+fn get_recognition_cache_ms() -> u64 {
+    load_state().recognition_cache_ms
+}
+
+#[tauri::command]
+fn set_recognition_cache_ms(ms: u64) -> Result<(), String> {
+    if ms > 10_000 {
+        return Err(format!("缓存有效期不能超过 10000ms: {}", ms));
+    }
+    let mut state = load_state();
+    state.recognition_cache_ms = ms;
+    save_state(&state);
+    if ms == 0 {
+        monster_recognition::clear_recognition_caches();
+    }
+    println!("[Config] Recognition cache ttl updated to: {}ms", ms);
+    Ok(())
+}
+
+#[tauri::command]
+fn clear_recognition_caches() -> Result<usize, String> {
+    // 只清理识别结果缓存和搜索结果缓存，模板与 YOLO 扫描结果保持不变（后者用 clear_yolo_cache）
+    let cleared = monster_recognition::clear_recognition_caches() + invalidate_search_cache();
+    log_to_file(&format!("Recognition caches cleared: {} entries", cleared));
+    Ok(cleared)
+}
+
+#[tauri::command]
+fn debug_resource_paths(app: tauri::AppHandle) -> Result<serde_json::Value, String> {
+    let resources_path = app.path().resource_dir().map_err(|e| e.to_string())?;
+    let mut report = serde_json::Map::new();
+    report.insert("resource_dir".to_string(), serde_json::Value::String(resources_path.to_string_lossy().to_string()));
+
+    let files = [
+        "monsters_db.json",
+        "monsters_export.json",
+        "images_monster_map.json",
+        "items_db.json",
+        "skills_db.json",
+    ];
+
+    let mut files_obj = serde_json::Map::new();
+    for f in &files {
+        let p1 = resources_path.join("resources").join(f);
+        let p2 = resources_path.join(f);
+        let mut info = serde_json::Map::new();
+        info.insert("path1".to_string(), serde_json::Value::String(p1.to_string_lossy().to_string()));
+        info.insert("exists1".to_string(), serde_json::Value::Bool(p1.exists()));
+        if p1.exists() {
+            if let Ok(md) = std::fs::metadata(&p1) {
+                info.insert("size1".to_string(), serde_json::Value::Number(serde_json::Number::from(md.len())));
+            }
+        }
+        info.insert("path2".to_string(), serde_json::Value::String(p2.to_string_lossy().to_string()));
+        info.insert("exists2".to_string(), serde_json::Value::Bool(p2.exists()));
+        if p2.exists() {
+            if let Ok(md) = std::fs::metadata(&p2) {
+                info.insert("size2".to_string(), serde_json::Value::Number(serde_json::Number::from(md.len())));
+            }
+        }
+        files_obj.insert(f.to_string(), serde_json::Value::Object(info));
+    }
+
+    report.insert("files".to_string(), serde_json::Value::Object(files_obj));
+    Ok(serde_json::Value::Object(report))
+}
+
+/// 第 10 天及以后的怪物共用同一个 "Day 10+" 池
+const DAY_BUCKET_MAX: u32 = 10;
+
+/// 天数 -> 所属分组编号 (0..=DAY_BUCKET_MAX)
+pub fn day_bucket(day: u32) -> u32 {
+    day.min(DAY_BUCKET_MAX)
+}
+
+/// 天数 -> 分组标签，识别 / 怪物筛选 / 天数浏览统一使用
+pub fn day_bucket_label(day: u32) -> String {
+    if day >= DAY_BUCKET_MAX {
+        format!("Day {}+", DAY_BUCKET_MAX)
+    } else {
+        format!("Day {}", day)
+    }
+}
+
+/// 解析 "Day N" / "Day N+" 形式的标签，返回天数
+pub fn parse_day_label(label: &str) -> Option<u32> {
+    label.trim().strip_prefix("Day ")?.trim().trim_end_matches('+').parse::<u32>().ok()
+}
+
+/// 把数据里的原始标签（如 "Day 10"、"Day 15"）规范化为分组标签；无法解析的原样返回
+pub fn canonical_day_label(label: &str) -> String {
+    match parse_day_label(label) {
+        Some(d) => day_bucket_label(d),
+        None => label.to_string(),
+    }
+}
+
+#[tauri::command]
+fn recognize_monsters_from_screenshot(day: Option<u32>) -> Result<Vec<monster_recognition::MonsterRecognitionResult>, String> {
+    let day_filter = day.map(day_bucket_label);
+    monster_recognition::recognize_monsters(day_filter)
+}
+
+fn monster_candidates_json(candidates: &[(String, usize, f32)]) -> Vec<serde_json::Value> {
+    candidates.iter()
+        .map(|(name, matches, confidence)| serde_json::json!({
+            "name": name,
+            "matches": matches,
+            "confidence": confidence,
+        }))
+        .collect()
+}
+
+// 鼠标指向怪物的前 5 个候选（名称、匹配点数、置信度），供前端在识别不确定时让用户选择
+#[tauri::command]
+fn scan_and_identify_monster_candidates_at_mouse() -> Result<Vec<serde_json::Value>, String> {
+    scan_and_identify_monster_candidates().map(|c| monster_candidates_json(&c))
+}
+
+// 鼠标指向怪物的识别状态：未达标时也返回最接近的候选与差距
+#[tauri::command]
+fn scan_and_identify_monster_at_mouse() -> Result<monster_recognition::MonsterMatchStatus, String> {
+    monster_recognition::scan_and_identify_monster_at_mouse()
+}
+
+/// 最近一次鼠标识别的耗时与统计（截图 / 特征提取 / 模板比对耗时、候选数、最佳匹配），尚未识别过时为 null
+#[tauri::command]
+fn get_recognition_diagnostics() -> serde_json::Value {
+    serde_json::json!(monster_recognition::recognition_diagnostics())
+}
+
+#[tauri::command]
+fn get_monster_scan_region() -> Option<monster_recognition::MonsterScanRegion> {
+    load_state().monster_scan_region
+}
+
+// 传 None 恢复默认区域
+#[tauri::command]
+fn set_monster_scan_region(region: Option<monster_recognition::MonsterScanRegion>) -> Result<(), String> {
+    if let Some(r) = region {
+        r.validate()?;
+    }
+    let mut state = load_state();
+    state.monster_scan_region = region;
+    save_state(&state);
+    println!("[Config] Monster scan region updated to: {:?}", region);
+    Ok(())
+}
+
+#[tauri::command]
+fn preview_monster_scan_region() -> Result<monster_recognition::MonsterScanPreview, String> {
+    monster_recognition::preview_monster_scan_region()
+}
+
+#[tauri::command]
+fn preview_monster_slots() -> Result<Vec<monster_recognition::MonsterSlotPreview>, String> {
+    monster_recognition::preview_monster_slots()
+}
+
+#[tauri::command]
+fn export_template_cache(path: String) -> Result<serde_json::Value, String> {
+    let (monsters, cards) = monster_recognition::export_template_cache(&PathBuf::from(path))?;
+    Ok(serde_json::json!({ "monsters": monsters, "cards": cards }))
+}
+
+#[tauri::command]
+fn import_template_cache(path: String) -> Result<serde_json::Value, String> {
+    let (monsters, cards) = monster_recognition::import_template_cache(&PathBuf::from(path))?;
+    Ok(serde_json::json!({ "monsters": monsters, "cards": cards }))
+}
+
+#[tauri::command]
+fn get_template_stats() -> monster_recognition::TemplateStats {
+    monster_recognition::get_template_stats()
+}
+
+#[tauri::command]
+fn get_template_loading_progress() -> monster_recognition::LoadingProgress {
+    monster_recognition::get_loading_progress()
+}
+
+/// 天数的来源：缓存 / 日志扫描 / 默认值
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DaySource {
+    Cache,
+    Log,
+    Default,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DayInfo {
+    pub day: u32,
+    pub source: DaySource,
+}
+
+fn detect_current_day(hours_per_day: Option<u32>, retro: Option<bool>) -> Result<DayInfo, String> {
+    // Return cached value if available, log scan only as fallback
+    // 缓存文件不存在时 load_state 返回的是默认值，不能算作缓存命中
+    if get_cache_path().exists() {
+        let cached = load_state();
+        if cached.day > 0 {
+            return Ok(DayInfo { day: cached.day, source: DaySource::Cache });
+        }
+    }
+
+    let hours = hours_per_day.unwrap_or(6);
+    let retro = retro.unwrap_or(false);
+
+    // Fallback to scan only if cache is 0 (first run)
+    if let Some(day) = detect_day_from_log_files(&get_log_path(), &get_prev_log_path(), hours, retro)? {
+        return Ok(DayInfo { day, source: DaySource::Log });
+    }
+
+    Ok(DayInfo { day: 1, source: DaySource::Default })
+}
+
+/// detect_current_day 的日志扫描部分，只依赖文件路径（log_day_counter 也用它对照）
+pub fn detect_day_from_log_files(log_path: &PathBuf, prev_path: &PathBuf, hours: u32, retro: bool) -> Result<Option<u32>, String> {
+    if !log_path.exists() {
+        return Ok(None);
+    }
+    let mut content = read_log_tail(log_path, 5_000_000)?;
+
+    // 本局开始于上一次游戏会话时，开局标记只存在于 Player-prev.log 中，
+    // 需要把 prev 日志拼接在前面，否则会从第 1 天重新计数
+    if !content.contains("NetMessageRunInitialized") && prev_path.exists() {
+        if let Ok(prev_content) = read_log_tail(prev_path, 5_000_000) {
+            content = format!("{}\n{}", prev_content, content);
+        }
+    }
+
+    Ok(calculate_day_from_log(&content, hours, retro))
+}
+
+#[tauri::command]
+fn get_current_day(hours_per_day: Option<u32>, retro: Option<bool>) -> Result<u32, String> {
+    detect_current_day(hours_per_day, retro).map(|info| info.day)
+}
+
+/// 与 get_current_day 相同，但附带天数来源，方便前端提示“从日志检测” / “无法检测，默认第 1 天”
+#[tauri::command]
+fn get_current_day_with_source(hours_per_day: Option<u32>, retro: Option<bool>) -> Result<DayInfo, String> {
+    detect_current_day(hours_per_day, retro)
+}
+
+#[tauri::command]
+fn update_day(day: u32) -> Result<(), String> {
+    let mut state = load_state();
+    state.day = day;
+    save_state(&state);
+    println!("[State] Manually updated Day to: {}", day);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_detection_hotkey() -> Option<Hotkey> {
+    load_state().detection_hotkey
+}
+
+#[tauri::command]
+fn get_card_detection_hotkey() -> Option<Hotkey> {
+    load_state().card_detection_hotkey
+}
+
+#[tauri::command]
+fn get_toggle_collapse_hotkey() -> Option<Hotkey> {
+    load_state().toggle_collapse_hotkey
+}
+
+#[tauri::command]
+fn set_detection_hotkey(hotkey: Hotkey) {
+    let mut state = load_state();
+    state.detection_hotkey = Some(hotkey.clone());
+    save_state(&state);
+    println!("[Config] Detection hotkey updated to: {}", hotkey);
+}
+
+#[tauri::command]
+fn set_card_detection_hotkey(hotkey: Hotkey) {
+    let mut state = load_state();
+    state.card_detection_hotkey = Some(hotkey.clone());
+    save_state(&state);
+    println!("[Config] Card detection hotkey updated to: {}", hotkey);
+}
+
+#[tauri::command]
+fn set_toggle_collapse_hotkey(hotkey: Hotkey) {
+    let mut state = load_state();
+    state.toggle_collapse_hotkey = Some(hotkey.clone());
+    save_state(&state);
+    println!("[Config] Toggle collapse hotkey updated to: {}", hotkey);
+}
+
+#[tauri::command]
+fn set_yolo_hotkey(hotkey: Hotkey) {
+    let mut state = load_state();
+    state.yolo_hotkey = Some(hotkey.clone());
+    save_state(&state);
+    println!("[Config] YOLO hotkey updated to: {}", hotkey);
+}
+
+#[tauri::command]
+fn get_detail_display_hotkey() -> Option<Hotkey> {
+    load_state().detail_display_hotkey
+}
+
+#[tauri::command]
+fn set_detail_display_hotkey(hotkey: Hotkey) {
+    let mut state = load_state();
+    state.detail_display_hotkey = Some(hotkey.clone());
+    save_state(&state);
+    println!("[Config] Detail display hotkey updated to: {}", hotkey);
+}
+
+#[tauri::command]
+fn get_restore_last_jump() -> bool {
+    load_state().restore_last_jump
+}
+
+#[tauri::command]
+fn set_restore_last_jump(enabled: bool) {
+    let mut state = load_state();
+    state.restore_last_jump = enabled;
+    save_state(&state);
+    println!("[Config] Restore last jump updated to: {}", enabled);
+}
+
+// 同一中文名怪物出现的所有天数（升序去重）
+fn monster_variant_days(monsters: &serde_json::Map<String, serde_json::Value>, name_zh: &str) -> Vec<u32> {
+    let mut days: Vec<u32> = monsters.values()
+        .filter(|v| v.get("name_zh").and_then(|val| val.as_str()) == Some(name_zh))
+        .filter_map(|v| v.get("available").and_then(|val| val.as_str()).and_then(parse_day_label))
+        .collect();
+    days.sort();
+    days.dedup();
+    days
+}
+
+/// 该怪物出现的所有天数，前端据此显示「还出现在 Day X/Y」的切换按钮
+#[tauri::command]
+fn get_monster_variants(name_zh: String, state: State<'_, DbState>) -> Vec<u32> {
+    match state.monsters.read() {
+        Ok(monsters) => monster_variant_days(&monsters, &name_zh),
+        Err(_) => Vec::new(),
+    }
+}
+
+// 前端注册 auto-jump-to-monster 监听后调用：开启 restore_last_jump 且有记录时重新推送上次的跳转
+#[tauri::command]
+fn restore_last_jump(app: tauri::AppHandle) -> bool {
+    let state = load_state();
+    if !state.restore_last_jump {
+        return false;
+    }
+    match (state.last_jumped_day, state.last_recognized_monster) {
+        (Some(day), Some(monster_name)) => {
+            println!("[State] Restoring last jump: Day {} ({})", day, monster_name);
+            let _ = app.emit("auto-jump-to-monster", serde_json::json!({
+                "day": day,
+                "monster_name": monster_name
+            }));
+            true
+        }
+        _ => false,
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct HotkeyBinding {
+    pub key: i32,
+    pub modifiers: Vec<i32>, // 修饰键虚拟键码，单键为空
+    pub is_default: bool,
+    pub enabled: bool,
+}
+
+const HOTKEY_NAMES: [&str; 5] = ["detection", "card_detection", "toggle_collapse", "yolo", "detail_display"];
+
+/// 启用/禁用单个热键而不清空键码，name 同 get_all_hotkeys 的 key
+#[tauri::command]
+fn set_hotkey_enabled(name: String, enabled: bool) -> Result<(), String> {
+    if !HOTKEY_NAMES.contains(&name.as_str()) {
+        return Err(format!("未知热键: {} (可选 {})", name, HOTKEY_NAMES.join("/")));
+    }
+    let mut state = load_state();
+    if enabled {
+        state.disabled_hotkeys.remove(&name);
+    } else {
+        state.disabled_hotkeys.insert(name.clone());
+    }
+    save_state(&state);
+    println!("[Config] Hotkey {} enabled: {}", name, enabled);
+    Ok(())
+}
+
+// 所有热键绑定（动作名 -> 按键），未设置的显示实际生效的默认值；新增热键需同步加到这里
+#[tauri::command]
+fn get_all_hotkeys() -> HashMap<String, HotkeyBinding> {
+    let state = load_state();
+    let binding = |name: &str, configured: Option<Hotkey>, default: i32| {
+        let is_default = configured.is_none();
+        let hotkey = configured.unwrap_or_else(|| Hotkey::single(default));
+        let enabled = !state.disabled_hotkeys.contains(name);
+        (name.to_string(), HotkeyBinding { key: hotkey.key, modifiers: hotkey.modifiers, is_default, enabled })
+    };
+
+    HashMap::from([
+        binding("detection", state.detection_hotkey.clone(), default_monster_hotkey()),
+        binding("card_detection", state.card_detection_hotkey.clone(), default_card_hotkey()),
+        binding("toggle_collapse", state.toggle_collapse_hotkey.clone(), 192),
+        binding("yolo", state.yolo_hotkey.clone(), 81),
+        binding("detail_display", state.detail_display_hotkey.clone(), VK_RBUTTON_CODE),
+    ])
+}
+
+// Use a more memory-efficient way to read large logs: read at most `max_bytes` from the end
+fn read_log_tail(path: &PathBuf, max_bytes: u64) -> Result<String, String> {
+    let mut file = open_log_shared(path).map_err(|e| e.to_string())?;
+    let file_size = file.metadata().map_err(|e| e.to_string())?.len();
+
+    let read_size = file_size.min(max_bytes) as usize;
+    let mut buffer = vec![0u8; read_size];
+    file.seek(SeekFrom::End(-(read_size as i64))).map_err(|e| e.to_string())?;
+    file.read_exact(&mut buffer).map_err(|e| e.to_string())?;
+
+    Ok(String::from_utf8_lossy(&buffer).into_owned())
+}
+
+fn calculate_day_from_log(content: &str, _hours: u32, retro: bool) -> Option<u32> {
+    let start_pos = if retro { content.rfind("NetMessageRunInitialized").unwrap_or(0) } else { 0 };
+    let slice = &content[start_pos..];
+    let mut current_day: u32 = 1; // Default to 1
+    let mut in_pvp = false;
+    let mut hour_count: u32 = 0;
+
+    for line in slice.lines() {
+        let l = line.trim();
+        if l.contains("NetMessageRunInitialized") {
+            current_day = 1; in_pvp = false; hour_count = 0; continue;
+        }
+        
+        if l.contains("to [PVPCombatState]") { in_pvp = true; continue; }
+
+        if in_pvp && l.contains("State changed") && (l.contains("to [ChoiceState]") || l.contains("to [LevelUpState]")) {
+            current_day = current_day.saturating_add(1);
+            in_pvp = false; hour_count = 0; continue;
+        }
+
+        if l.starts_with("[") && l.contains("State changed from [ChoiceState] to [") {
+             if !l.contains("to [ChoiceState]") && !l.contains("to [PVPCombatState]") {
+                hour_count = hour_count.saturating_add(1);
+                if hour_count >= 10 { // Fallback for modes without PVP or unexpected logs
+                    current_day = current_day.saturating_add(1);
+                    hour_count = 0;
+                }
+             }
+        }
+    }
+    
+    Some(current_day)
+}
+
+// --- Log Replay ---
+// 日志监控线程与手动重新同步共用的正则
+struct LogPatterns {
+    re_purchase: Regex,
+    re_id: Regex,
+    re_owner: Regex,
+    re_section: Regex,
+    re_item_id: Regex,
+    re_sold: Regex,
+    re_removed: Regex,
+    re_moved_to: Regex,
+}
+
+impl LogPatterns {
+    fn new() -> Self {
+        Self {
+            re_purchase: Regex::new(r"Card Purchased: InstanceId:\s*(?P<iid>[^ ]+)\s*-\s*TemplateId\s*(?P<tid>[^ ]+)(?:.*Target:(?P<tgt>[^ ]+))?(?:.*Section(?P<sec>[^ ]+))?").unwrap(),
+            re_id: Regex::new(r"ID: \[(?P<id>[^\]]+)\]").unwrap(),
+            re_owner: Regex::new(r"- Owner: \[(?P<val>[^\]]+)\]").unwrap(),
+            re_section: Regex::new(r"- Section: \[(?P<val>[^\]]+)\]").unwrap(),
+            re_item_id: Regex::new(r"itm_[A-Za-z0-9_-]+").unwrap(),
+            re_sold: Regex::new(r"Sold Card\s+(?P<iid>itm_[^ ]+)").unwrap(),
+            re_removed: Regex::new(r"Successfully removed item\s+(?P<iid>itm_[^ ]+)").unwrap(),
+            re_moved_to: Regex::new(r"Successfully moved card\s+(?P<iid>itm_[^ ]+)\s+to\s+(?P<tgt>[^ ]+)").unwrap(),
+        }
+    }
+}
+
+// 回放 prev + current 日志后得到的追踪状态
+struct ReplayState {
+    day: u32,
+    in_pvp: bool,
+    inst_to_temp: HashMap<String, String>,
+    current_hand: HashSet<String>,
+    current_stash: HashSet<String>,
+    // Player.log 已回放到的字节位置，监控循环从这里继续读
+    log_offset: u64,
+}
+
+// 初始回放时 log-sync-progress 事件的推送间隔（字节）
+const LOG_SYNC_PROGRESS_STEP: u64 = 256 * 1024;
+
+// 手动重新同步的结果，由日志监控线程在下一轮循环中接管，避免它用旧状态覆盖
+static PENDING_RESYNC: OnceLock<std::sync::Mutex<Option<ReplayState>>> = OnceLock::new();
+
+fn get_pending_resync() -> &'static std::sync::Mutex<Option<ReplayState>> {
+    PENDING_RESYNC.get_or_init(|| std::sync::Mutex::new(None))
+}
+
+// 没有收到文件事件时的兜底轮询间隔：防止事件丢失，或文件被截断/重建时没有触发事件。
+// Windows 上游戏持有 Player.log 写句柄时目录变更通知常被推迟到 flush/关闭，兜底轮询就是实际延迟，不能太长
+const LOG_FALLBACK_POLL_MS: u64 = 1000;
+// sync-items / day-update 的合并窗口：窗口内的多批变更只推送一次最终状态，追赶日志期间推迟到追赶结束
+const SYNC_ITEMS_DEBOUNCE_MS: u64 = 50;
+
+type LogEvent = notify::Result<notify::Event>;
+
+// 日志监控线程的唤醒通道（不带路径的事件视为唤醒），用于让 resync_inventory 的结果被立即接管
+static LOG_WAKE: OnceLock<std::sync::mpsc::Sender<LogEvent>> = OnceLock::new();
+
+// set_custom_log_path 修改路径后置位，日志监控线程下一轮重新解析 get_log_path 并切换监听目录
+static LOG_PATH_CHANGED: AtomicBool = AtomicBool::new(false);
+
+fn wake_log_monitor() {
+    if let Some(tx) = LOG_WAKE.get() {
+        let _ = tx.send(Ok(notify::Event::new(notify::EventKind::Any)));
+    }
+}
+
+// 用 notify 监听日志所在目录（而非文件本身），文件尚不存在或被游戏重建时也能收到事件。
+// 目录不存在（游戏从未启动）时 watcher 创建失败，退回低频轮询，每次等待前重试创建
+struct LogWatcher {
+    watcher: Option<notify::RecommendedWatcher>,
+    tx: std::sync::mpsc::Sender<LogEvent>,
+    rx: std::sync::mpsc::Receiver<LogEvent>,
+    dir: Option<PathBuf>,
+    file_names: Vec<std::ffi::OsString>,
+}
+
+impl LogWatcher {
+    fn new(log_path: &PathBuf, prev_log_path: &PathBuf) -> Self {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let _ = LOG_WAKE.set(tx.clone());
+        let file_names = [log_path, prev_log_path].iter().filter_map(|p| p.file_name().map(|n| n.to_os_string())).collect();
+        let mut watcher = Self { watcher: None, tx, rx, dir: log_path.parent().map(|d| d.to_path_buf()), file_names };
+        watcher.ensure_watching();
+        watcher
+    }
+
+    // 日志路径变化时改为监听新目录；保留原通道，LOG_WAKE 仍然有效
+    fn retarget(&mut self, log_path: &PathBuf, prev_log_path: &PathBuf) {
+        self.file_names = [log_path, prev_log_path].iter().filter_map(|p| p.file_name().map(|n| n.to_os_string())).collect();
+        self.dir = log_path.parent().map(|d| d.to_path_buf());
+        self.watcher = None;
+        self.ensure_watching();
+    }
+
+    fn ensure_watching(&mut self) {
+        use notify::Watcher;
+        if self.watcher.is_some() {
+            return;
+        }
+        let Some(dir) = self.dir.clone().filter(|d| d.is_dir()) else { return };
+        let result = notify::recommended_watcher(self.tx.clone()).and_then(|mut w| {
+            w.watch(&dir, notify::RecursiveMode::NonRecursive)?;
+            Ok(w)
+        });
+        match result {
+            Ok(w) => {
+                log_to_file(&format!("[LogMonitor] Watching {:?} for log changes", dir));
+                self.watcher = Some(w);
+            }
+            Err(e) => log_to_file(&format!("[LogMonitor] Failed to watch {:?}: {}, using {}ms polling", dir, e, LOG_FALLBACK_POLL_MS)),
+        }
+    }
+
+    fn is_relevant(&self, event: &notify::Event) -> bool {
+        event.paths.is_empty()
+            || event.paths.iter().any(|p| p.file_name().map_or(false, |n| self.file_names.iter().any(|f| f == n)))
+    }
+
+    // 阻塞直到日志文件有变化（或唤醒），最长等待 LOG_FALLBACK_POLL_MS
+    fn wait(&mut self, timeout: time::Duration) {
+        self.ensure_watching();
+        let deadline = time::Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(time::Instant::now());
+            match self.rx.recv_timeout(remaining) {
+                Ok(Ok(event)) if self.is_relevant(&event) => break,
+                Ok(Ok(_)) => continue,
+                Ok(Err(e)) => {
+                    // watcher 出错（如目录被删除）时丢弃，下一轮重新创建
+                    log_to_file(&format!("[LogMonitor] Watch error: {}, recreating watcher", e));
+                    self.watcher = None;
+                    break;
+                }
+                Err(_) => break,
+            }
+        }
+        // 一次写入通常触发多个事件，读取增量前合并掉
+        while self.rx.try_recv().is_ok() {}
+    }
+}
+
+// 从头回放 Player-prev.log 与 Player.log，重建天数和手牌/仓库，并写回缓存
+// 回放过程中通过 log-sync-progress 事件推送 { processed, total, done }（字节数），大日志时前端可显示进度
+fn resync_from_logs(app: &tauri::AppHandle, patterns: &LogPatterns) -> ReplayState {
+    let log_path = get_log_path();
+    let prev_path = get_prev_log_path();
+    let cached = load_state();
+
+    // 先记下当前大小，只回放到这里；之后追加的内容交给监控循环，避免重复处理
+    let log_offset = std::fs::metadata(&log_path).map(|m| m.len()).unwrap_or(0);
+
+    let total_bytes = std::fs::metadata(&prev_path).map(|m| m.len()).unwrap_or(0) + log_offset;
+    let mut last_reported: u64 = 0;
+    let emit_progress = |processed: u64, done: bool| {
+        let _ = app.emit("log-sync-progress", serde_json::json!({
+            "processed": processed.min(total_bytes),
+            "total": total_bytes,
+            "done": done,
+        }));
+    };
+    emit_progress(0, false);
+
+    // 缓存作为兜底，日志中的购买记录会覆盖
+    let files = [(prev_path, u64::MAX), (log_path, log_offset)];
+    let replay = replay_log_files(&files, patterns, cached.inst_to_temp, cached.day, |processed| {
+        // 每处理约 256KB 推送一次进度，避免事件过多
+        if processed - last_reported >= LOG_SYNC_PROGRESS_STEP {
+            last_reported = processed;
+            emit_progress(processed, false);
+        }
+    });
+
+    emit_progress(total_bytes, true);
+
+    save_state(&PersistentState {
+        day: replay.day,
+        inst_to_temp: replay.inst_to_temp.clone(),
+        current_hand: replay.current_hand.clone(),
+        current_stash: replay.current_stash.clone(),
+        ..load_state()
+    });
+
+    ReplayState { log_offset, ..replay }
+}
+
+// 按顺序回放若干日志文件（每个文件最多读 limit 字节），只依赖文件路径，不涉及 AppHandle 和缓存写入
+fn replay_log_files(files: &[(PathBuf, u64)], patterns: &LogPatterns, mut inst_to_temp: HashMap<String, String>, cached_day: u32, mut on_progress: impl FnMut(u64)) -> ReplayState {
+    let mut processed_bytes: u64 = 0;
+    let mut current_hand: HashSet<String> = HashSet::new();
+    let mut current_stash: HashSet<String> = HashSet::new();
+    let mut last_iid = String::new();
+    let mut cur_owner = String::new();
+    let mut in_pvp = false;
+
+    // 回放从第 1 天开始计数：缓存里的天数本身就是由这些日志算出来的，
+    // 若在缓存天数上继续累加，跨 prev/current 两个文件的对局会被重复计数
+    let mut current_day = 1;
+    let mut saw_run_init = false;
+
+    // prev -> current 视为一条连续的日志流：天数、PVP 状态和物品映射跨文件延续
+    for (path, limit) in files {
+        if !path.exists() {
+            println!("[LogMonitor] Skipping non-existent file: {:?}", path);
+            continue;
+        }
+        println!("[LogMonitor] Processing log file: {:?}", path);
+        // 同步块只在单个文件内有效，prev 末尾被截断的同步块不能延续到 current 的开头
+        let mut is_sync = false;
+        last_iid.clear();
+        cur_owner.clear();
+        if let Ok(file) = open_log_shared(path) {
+            let reader = BufReader::new(file.take(*limit));
+            for line in reader.lines() {
+                if let Ok(l) = line {
+                    processed_bytes += l.len() as u64 + 1;
+                    on_progress(processed_bytes);
+                    let trimmed = l.trim();
+
+                    // Reset everything if we see a new run start
+                    if trimmed.contains("NetMessageRunInitialized") {
+                        saw_run_init = true;
+                        current_day = 1; in_pvp = false;
+                        inst_to_temp.clear();
+                        current_hand.clear();
+                        current_stash.clear();
+                        is_sync = false;
+                    }
+
+                    if trimmed.contains("to [PVPCombatState]") { in_pvp = true; }
+                    if in_pvp && trimmed.contains("State changed") && (trimmed.contains("to [ChoiceState]") || trimmed.contains("to [LevelUpState]")) {
+                        current_day = current_day.saturating_add(1); in_pvp = false;
+                    }
+
+                    if let Some(cap) = patterns.re_purchase.captures(trimmed) {
+                        let iid = cap["iid"].to_string();
+                        inst_to_temp.insert(iid.clone(), cap["tid"].to_string());
+                        let mut section = cap.name("sec").map(|s| s.as_str().to_string());
+                        if section.as_deref().unwrap_or("") == "" {
+                            if let Some(tgt) = cap.name("tgt").map(|t| t.as_str()) {
+                                if tgt.contains("PlayerStorageSocket") { section = Some("Stash".to_string()); }
+                                else if tgt.contains("PlayerSocket") { section = Some("Player".to_string()); }
+                            }
+                        }
+                        if let Some(s) = section {
+                            if s == "Player" || s == "Hand" { current_hand.insert(iid); }
+                            else if s == "Stash" || s == "Storage" || s == "PlayerStorage" { current_stash.insert(iid); }
+                        }
+                    }
+                    if let Some(cap) = patterns.re_moved_to.captures(trimmed) {
+                        let iid = cap["iid"].to_string();
+                        if cap["tgt"].contains("StorageSocket") {
+                            current_stash.insert(iid.clone()); current_hand.remove(&iid);
+                        } else if cap["tgt"].contains("Socket") {
+                            current_hand.insert(iid.clone()); current_stash.remove(&iid);
+                        }
+                    }
+                    if let Some(cap) = patterns.re_sold.captures(trimmed) {
+                        let iid = cap["iid"].to_string(); 
+                        current_hand.remove(&iid); current_stash.remove(&iid);
+                    }
+                    if let Some(cap) = patterns.re_removed.captures(trimmed) {
+                        let iid = cap["iid"].to_string(); 
+                        current_hand.remove(&iid); current_stash.remove(&iid);
+                    }
+                    if trimmed.contains("Cards Disposed:") {
+                        for mat in patterns.re_item_id.find_iter(trimmed) {
+                            let iid = mat.as_str().to_string(); 
+                            current_hand.remove(&iid); current_stash.remove(&iid);
+                        }
+                    }
+                    if trimmed.contains("Cards Spawned:") || trimmed.contains("Cards Dealt:") || trimmed.contains("NetMessageGameStateSync") { 
+                        is_sync = true; 
+                    }
+                    if is_sync {
+                        if let Some(cap) = patterns.re_id.captures(trimmed) { last_iid = cap["id"].to_string(); }
+                        else if let Some(cap) = patterns.re_owner.captures(trimmed) { cur_owner = cap["val"].to_string(); }
+                        else if let Some(cap) = patterns.re_section.captures(trimmed) {
+                            if !last_iid.is_empty() && &cur_owner == "Player" && last_iid.starts_with("itm_") {
+                                let sec_val = &cap["val"];
+                                if sec_val == "Hand" || sec_val == "Player" { 
+                                    current_hand.insert(last_iid.clone()); 
+                                    current_stash.remove(&last_iid);
+                                }
+                                else if sec_val == "Stash" || sec_val == "Storage" || sec_val == "PlayerStorage" { 
+                                    current_stash.insert(last_iid.clone()); 
+                                    current_hand.remove(&last_iid);
+                                }
+                                else {
+                                    current_hand.remove(&last_iid); 
+                                    current_stash.remove(&last_iid);
+                                }
+                            }
+                            last_iid.clear(); cur_owner.clear();
+                        }
+                        else if trimmed.contains("Finished processing") { is_sync = false; }
+                    }
+                }
+            }
+        }
+    }
+
+    if !saw_run_init {
+        // 两个文件里都没有开局标记：本局开始于更早的日志，回放结果只是下限
+        current_day = current_day.max(cached_day);
+    }
+    println!("[LogMonitor] Replay finished at Day {} (run start found: {}, cached day: {})", current_day, saw_run_init, cached_day);
+
+    // log_offset 由调用方按实际回放截止位置填写
+    ReplayState { day: current_day, in_pvp, inst_to_temp, current_hand, current_stash, log_offset: 0 }
+}
+
+/// 与监控线程启动时相同的 prev -> current 回放，返回得到的天数（不读写缓存，供 log_day_counter 对照）
+pub fn replay_day_from_log_files(prev_path: Option<&PathBuf>, log_path: &PathBuf) -> u32 {
+    let mut files: Vec<(PathBuf, u64)> = prev_path.map(|p| (p.clone(), u64::MAX)).into_iter().collect();
+    files.push((log_path.clone(), u64::MAX));
+    replay_log_files(&files, &LogPatterns::new(), HashMap::new(), 0, |_| {}).day
+}
+
+// 把实例 ID 集合映射成前端需要的物品列表
+fn build_sync_payload(hand: &HashSet<String>, stash: &HashSet<String>, inst_to_temp: &HashMap<String, String>, items_db: &ItemDb, skills_db: &SkillDb) -> SyncPayload {
+    let mut unresolved: BTreeSet<String> = BTreeSet::new();
+    let mut map_items = |ids: &HashSet<String>| -> Vec<ItemData> {
+        ids.iter()
+           .filter_map(|iid| {
+               let tid = inst_to_temp.get(iid)?;
+               match lookup_item(tid, items_db, skills_db) {
+                   Some(mut item) => {
+                       item.instance_id = Some(iid.clone());
+                       Some(item)
+                   }
+                   None => {
+                       unresolved.insert(tid.clone());
+                       None
+                   }
+               }
+           })
+           .collect()
+    };
+
+    let hand_items = map_items(hand);
+    let stash_items = map_items(stash);
+    if !unresolved.is_empty() {
+        log_to_file(&format!("[Sync] {} tracked item(s) couldn't be matched to the database: {:?}", unresolved.len(), unresolved));
+    }
+
+    SyncPayload {
+        hand_items,
+        stash_items,
+        all_tags: items_db.unique_tags.clone(),
+        unresolved_template_ids: unresolved.into_iter().collect(),
+    }
+}
+
+#[derive(Serialize)]
+pub struct ResyncSummary {
+    pub day: u32,
+    pub hand_count: usize,
+    pub stash_count: usize,
 }
 
+// 手动重新回放日志（例如中途启动助手、手牌显示错乱时）
 #[tauri::command]
-fn get_card_detection_hotkey() -> Option<i32> {
-    load_state().card_detection_hotkey
-}
+fn resync_inventory(app: tauri::AppHandle, state: State<'_, DbState>) -> Result<ResyncSummary, String> {
+    println!("[LogMonitor] Manual resync requested");
+    let replay = resync_from_logs(&app, &LogPatterns::new());
+
+    let payload = {
+        let items_db = state.items.read().map_err(|_| "DB Busy")?;
+        let skills_db = state.skills.read().map_err(|_| "DB Busy")?;
+        build_sync_payload(&replay.current_hand, &replay.current_stash, &replay.inst_to_temp, &items_db, &skills_db)
+    };
+    let summary = ResyncSummary {
+        day: replay.day,
+        hand_count: payload.hand_items.len(),
+        stash_count: payload.stash_items.len(),
+    };
 
-#[tauri::command]
-fn get_toggle_collapse_hotkey() -> Option<i32> {
-    load_state().toggle_collapse_hotkey
-}
+    let _ = app.emit("day-update", replay.day);
+    let _ = app.emit("sync-items", payload);
 
-#[tauri::command]
-fn set_detection_hotkey(hotkey: i32) {
-    let mut state = load_state();
-    state.detection_hotkey = Some(hotkey);
-    save_state(&state);
-    println!("[Config] Detection hotkey updated to: {}", hotkey);
+    *get_pending_resync().lock().map_err(|e| e.to_string())? = Some(replay);
+    wake_log_monitor();
+    Ok(summary)
 }
 
+// 把当前天数与手牌/仓库导出为格式化 JSON，前端写入剪贴板便于分享 build
 #[tauri::command]
-fn set_card_detection_hotkey(hotkey: i32) {
-    let mut state = load_state();
-    state.card_detection_hotkey = Some(hotkey);
-    save_state(&state);
-    println!("[Config] Card detection hotkey updated to: {}", hotkey);
-}
+fn export_current_build(state: State<'_, DbState>) -> Result<String, String> {
+    let saved = load_state();
+    let payload = {
+        let items_db = state.items.read().map_err(|_| "DB Busy")?;
+        let skills_db = state.skills.read().map_err(|_| "DB Busy")?;
+        build_sync_payload(&saved.current_hand, &saved.current_stash, &saved.inst_to_temp, &items_db, &skills_db)
+    };
 
-#[tauri::command]
-fn set_toggle_collapse_hotkey(hotkey: i32) {
-    let mut state = load_state();
-    state.toggle_collapse_hotkey = Some(hotkey);
-    save_state(&state);
-    println!("[Config] Toggle collapse hotkey updated to: {}", hotkey);
-}
+    let describe = |items: &[ItemData]| -> Vec<serde_json::Value> {
+        let mut list: Vec<serde_json::Value> = items.iter()
+            .map(|i| serde_json::json!({
+                "name": i.name,
+                "name_cn": i.name_cn,
+                "tier": i.tier,
+                "size": i.size,
+            }))
+            .collect();
+        list.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+        list
+    };
 
-#[tauri::command]
-fn set_yolo_hotkey(hotkey: i32) {
-    let mut state = load_state();
-    state.yolo_hotkey = Some(hotkey);
-    save_state(&state);
-    println!("[Config] YOLO hotkey updated to: {}", hotkey);
+    let build = serde_json::json!({
+        "day": saved.day,
+        "hand": describe(&payload.hand_items),
+        "stash": describe(&payload.stash_items),
+        "unresolved_template_ids": payload.unresolved_template_ids,
+    });
+    serde_json::to_string_pretty(&build).map_err(|e| e.to_string())
 }
 
-#[tauri::command]
-fn get_detail_display_hotkey() -> Option<i32> {
-    load_state().detail_display_hotkey
-}
+// 自动 YOLO 只在进入这些状态时触发，其余状态切换忽略。Player.log 的状态切换行（与 calculate_day_from_log 匹配的格式相同）：
+//   [...] State changed from [PVPCombatState] to [ChoiceState]
+// ChoiceState 为商店 / 事件的选项界面，LevelUpState 为升级奖励；EncounterState 是 PvE 战斗，不触发
+const AUTO_YOLO_STATES: &[&str] = &["to [ChoiceState]", "to [LevelUpState]"];
+// 两次自动 YOLO 请求的最小间隔，避免连续状态切换时重复扫描
+const AUTO_YOLO_DEBOUNCE_MS: u64 = 1500;
 
-#[tauri::command]
-fn set_detail_display_hotkey(hotkey: i32) {
-    let mut state = load_state();
-    state.detail_display_hotkey = Some(hotkey);
-    save_state(&state);
-    println!("[Config] Detail display hotkey updated to: {}", hotkey);
+fn is_auto_yolo_transition(line: &str) -> bool {
+    line.contains("State changed from [") && AUTO_YOLO_STATES.iter().any(|s| line.contains(s))
 }
 
-fn calculate_day_from_log(content: &str, _hours: u32, retro: bool) -> Option<u32> {
-    let start_pos = if retro { content.rfind("NetMessageRunInitialized").unwrap_or(0) } else { 0 };
-    let slice = &content[start_pos..];
-    let mut current_day: u32 = 1; // Default to 1
-    let mut in_pvp = false;
-    let mut hour_count: u32 = 0;
-
-    for line in slice.lines() {
-        let l = line.trim();
-        if l.contains("NetMessageRunInitialized") {
-            current_day = 1; in_pvp = false; hour_count = 0; continue;
-        }
-        
-        if l.contains("to [PVPCombatState]") { in_pvp = true; continue; }
+// 鼠标线程推送事件的去重：窗口期内与上一次相同的内容不再推送，避免连按热键刷屏
+struct EmitDeduper {
+    last: Option<(String, time::Instant)>,
+}
 
-        if in_pvp && l.contains("State changed") && (l.contains("to [ChoiceState]") || l.contains("to [LevelUpState]")) {
-            current_day = current_day.saturating_add(1);
-            in_pvp = false; hour_count = 0; continue;
-        }
+impl EmitDeduper {
+    fn new() -> Self {
+        Self { last: None }
+    }
 
-        if l.starts_with("[") && l.contains("State changed from [ChoiceState] to [") {
-             if !l.contains("to [ChoiceState]") && !l.contains("to [PVPCombatState]") {
-                hour_count = hour_count.saturating_add(1);
-                if hour_count >= 10 { // Fallback for modes without PVP or unexpected logs
-                    current_day = current_day.saturating_add(1);
-                    hour_count = 0;
-                }
-             }
+    fn should_emit(&mut self, key: &str, window_ms: u64) -> bool {
+        if let Some((last_key, at)) = &self.last {
+            if last_key == key && at.elapsed() < time::Duration::from_millis(window_ms) {
+                return false;
+            }
         }
+        self.last = Some((key.to_string(), time::Instant::now()));
+        true
     }
-    
-    Some(current_day)
 }
 
 // --- App Run ---
@@ -1817,15 +4615,16 @@ fn calculate_day_from_log(content: &str, _hours: u32, retro: bool) -> Option<u32
 fn get_yolo_stats() -> serde_json::Value {
     let detections = get_yolo_scan_results().read().unwrap();
     let total = detections.len();
-    let items = detections.iter().filter(|d| d.class_id == 2).count(); // item
-    let events = detections.iter().filter(|d| d.class_id == 1).count(); // event
-    let skills = detections.iter().filter(|d| d.class_id == 6).count(); // skill
-    let monster_icons = detections.iter().filter(|d| d.class_id == 3).count(); // monstericon
+    let items = detections.iter().filter(|d| d.is_class(CLASS_ITEM)).count();
+    let events = detections.iter().filter(|d| d.is_class(CLASS_EVENT)).count();
+    let skills = detections.iter().filter(|d| d.is_class(CLASS_SKILL)).count();
+    let monster_icons = detections.iter().filter(|d| d.is_class(CLASS_MONSTER_ICON)).count();
     
-    // 计算怪物数量（event和monstericon重叠的）
-    let events_list: Vec<_> = detections.iter().filter(|d| d.class_id == 1).collect();
+    // 计算怪物数量（event和monstericon重叠的），阈值与右键识别保持一致
+    let overlap_ratio = load_state().monster_overlap_ratio;
+    let events_list: Vec<_> = detections.iter().filter(|d| d.is_class(CLASS_EVENT)).collect();
     let monsters_count = events_list.iter().map(|event| {
-        detections.iter().filter(|d| d.class_id == 3).any(|icon| {
+        detections.iter().filter(|d| d.is_class(CLASS_MONSTER_ICON)).any(|icon| {
             // 检查交集
             let ix1 = event.x1.max(icon.x1);
             let iy1 = event.y1.max(icon.y1);
@@ -1833,12 +4632,22 @@ fn get_yolo_stats() -> serde_json::Value {
             let iy2 = event.y2.min(icon.y2);
             let i_area = (ix2 - ix1).max(0) * (iy2 - iy1).max(0);
             let icon_area = (icon.x2 - icon.x1) * (icon.y2 - icon.y1);
-            icon_area > 0 && (i_area as f32 / icon_area as f32) > 0.5
+            icon_area > 0 && (i_area as f32 / icon_area as f32) > overlap_ratio
         })
     }).filter(|&has_monster| has_monster).count();
 
+    // 按类别名统计，附带 class_id 方便对照模型输出
+    let mut by_class = serde_json::Map::new();
+    for d in detections.iter() {
+        let entry = by_class.entry(monster_recognition::yolo_class_name(d.class_id))
+            .or_insert_with(|| serde_json::json!({ "class_id": d.class_id, "count": 0 }));
+        entry["count"] = serde_json::json!(entry["count"].as_u64().unwrap_or(0) + 1);
+    }
+
     serde_json::json!({
         "total": total,
+        "by_class": by_class,
+        "dropped": monster_recognition::last_dropped_detections(),
         "items": items,
         "events": events,
         "monsters": monsters_count,
@@ -1847,16 +4656,156 @@ fn get_yolo_stats() -> serde_json::Value {
     })
 }
 
+/// 全流程基准：对指定截图做 YOLO 检测 + 每个检测框的 ORB 识别，返回各阶段耗时（毫秒）
+/// 未指定截图时使用安装包自带的 resources/benchmark/sample_screenshot.png
+#[tauri::command]
+async fn benchmark_full_pipeline(app: tauri::AppHandle, image_path: Option<String>, use_gpu: Option<bool>) -> Result<serde_json::Value, String> {
+    use image::GenericImageView;
+
+    let resources_path = app.path().resource_dir().map_err(|e| e.to_string())?;
+    let model_path = monster_recognition::active_yolo_model_path(&resources_path);
+    let sample_path = image_path
+        .map(PathBuf::from)
+        .unwrap_or_else(|| resources_path.join("benchmark").join("sample_screenshot.png"));
+    if !sample_path.exists() {
+        return Err(format!("Benchmark image not found: {:?}", sample_path));
+    }
+
+    let total_start = time::Instant::now();
+    let img = image::open(&sample_path).map_err(|e| format!("Failed to open benchmark image: {}", e))?;
+    let (img_w, img_h) = img.dimensions();
+
+    let yolo_start = time::Instant::now();
+    let state = load_state();
+    let detections = monster_recognition::run_yolo_inference(&img, &model_path, use_gpu.unwrap_or(false), state.nms_strategy, state.yolo_conf_threshold, state.yolo_iou_threshold)?;
+    let yolo_ms = yolo_start.elapsed().as_secs_f64() * 1000.0;
+
+    let overlap_ratio = load_state().monster_overlap_ratio;
+    // class key -> (count, resolved, total_ms)
+    let mut per_class: HashMap<&'static str, (usize, usize, f64)> = HashMap::new();
+    let orb_start = time::Instant::now();
+
+    for det in detections.iter() {
+        let class_key = if det.is_class(CLASS_ITEM) {
+            "item"
+        } else if det.is_class(CLASS_SKILL) {
+            "skill"
+        } else if det.is_class(CLASS_MONSTER_ICON) {
+            "monster"
+        } else if det.is_class(CLASS_EVENT) {
+            let is_monster = detections.iter().filter(|d| d.is_class(CLASS_MONSTER_ICON)).any(|icon| {
+                let ix1 = det.x1.max(icon.x1);
+                let iy1 = det.y1.max(icon.y1);
+                let ix2 = det.x2.min(icon.x2);
+                let iy2 = det.y2.min(icon.y2);
+                let i_area = (ix2 - ix1).max(0) * (iy2 - iy1).max(0);
+                let icon_area = (icon.x2 - icon.x1) * (icon.y2 - icon.y1);
+                icon_area > 0 && (i_area as f32 / icon_area as f32) > overlap_ratio
+            });
+            if is_monster { "monster" } else { "event" }
+        } else {
+            continue;
+        };
+
+        let det_start = time::Instant::now();
+        let crop_x = (det.x1.max(0) as u32).min(img_w.saturating_sub(1));
+        let crop_y = (det.y1.max(0) as u32).min(img_h.saturating_sub(1));
+        let crop_w = ((det.x2 - det.x1).max(1) as u32).min(img_w - crop_x);
+        let crop_h = ((det.y2 - det.y1).max(1) as u32).min(img_h - crop_y);
+        let cropped = img.crop_imm(crop_x, crop_y, crop_w, crop_h);
+
+        let resolved = match monster_recognition::extract_features_from_dynamic_image(&cropped, 1000) {
+            Ok(desc) if !desc.empty() => match class_key {
+                "item" | "skill" => monster_recognition::match_card_descriptors(&desc)?
+                    .and_then(|v| v.as_array().map(|a| !a.is_empty()))
+                    .unwrap_or(false),
+                "monster" => monster_recognition::match_monster_descriptors_from_mat(&desc)?.is_some(),
+                _ => monster_recognition::match_event_descriptors_from_mat(&desc)?.is_some(),
+            },
+            _ => false,
+        };
+
+        let entry = per_class.entry(class_key).or_insert((0, 0, 0.0));
+        entry.0 += 1;
+        if resolved { entry.1 += 1; }
+        entry.2 += det_start.elapsed().as_secs_f64() * 1000.0;
+    }
+
+    let orb_ms = orb_start.elapsed().as_secs_f64() * 1000.0;
+    let total_ms = total_start.elapsed().as_secs_f64() * 1000.0;
+
+    let orb_per_class: serde_json::Map<String, serde_json::Value> = per_class.into_iter().map(|(k, (count, resolved, ms))| {
+        (k.to_string(), serde_json::json!({
+            "count": count,
+            "resolved": resolved,
+            "total_ms": ms,
+            "avg_ms": if count > 0 { ms / count as f64 } else { 0.0 }
+        }))
+    }).collect();
+
+    println!("[Benchmark] {:?}: YOLO {:.1}ms ({} detections), ORB {:.1}ms, total {:.1}ms",
+             sample_path, yolo_ms, detections.len(), orb_ms, total_ms);
+
+    Ok(serde_json::json!({
+        "image": sample_path.to_string_lossy(),
+        "image_width": img_w,
+        "image_height": img_h,
+        "yolo_ms": yolo_ms,
+        "detections": detections.len(),
+        "orb_ms": orb_ms,
+        "orb_per_class": orb_per_class,
+        "total_ms": total_ms
+    }))
+}
+
 #[tauri::command]
 async fn invoke_yolo_scan(app: tauri::AppHandle) -> Result<Vec<String>, String> {
     monster_recognition::recognize_monsters_yolo(&app)
 }
 
+// --- Overlay -> Main 消息 ---
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MonsterMatchedPayload {
+    pub name: String,
+    pub name_zh: String,
+}
+
+// overlay 允许发给主窗口的消息，未列出的事件名一律拒绝
+#[derive(Debug, Clone)]
+pub enum OverlayMessage {
+    MonsterMatched(MonsterMatchedPayload),
+}
+
+impl OverlayMessage {
+    fn parse(event: &str, payload: serde_json::Value) -> Result<Self, String> {
+        let invalid = |e: serde_json::Error| format!("Invalid payload for '{}': {}", event, e);
+        match event {
+            "monster-matched" => serde_json::from_value(payload).map(OverlayMessage::MonsterMatched).map_err(invalid),
+            _ => Err(format!("Unknown overlay message: '{}'", event)),
+        }
+    }
+
+    fn emit(&self, app: &tauri::AppHandle) -> Result<(), String> {
+        let res = match self {
+            OverlayMessage::MonsterMatched(p) => app.emit("monster-matched", p),
+        };
+        res.map_err(|e| format!("Failed to emit event: {}", e))
+    }
+}
+
+#[tauri::command]
+async fn send_overlay_message(app: tauri::AppHandle, event: String, payload: serde_json::Value) -> Result<(), String> {
+    let message = OverlayMessage::parse(&event, payload).map_err(|e| {
+        log_to_file(&format!("[Overlay] Rejected message: {}", e));
+        e
+    })?;
+    message.emit(&app)
+}
+
+// 兼容旧前端：同样走 OverlayMessage 校验
 #[tauri::command]
 async fn emit_to_main(app: tauri::AppHandle, event: String, payload: serde_json::Value) -> Result<(), String> {
-    app.emit(&event, payload)
-        .map_err(|e| format!("Failed to emit event: {}", e))?;
-    Ok(())
+    send_overlay_message(app, event, payload).await
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -1897,17 +4846,46 @@ pub fn run() {
                 list: Vec::new(),
                 id_map: HashMap::new(),
                 unique_tags: Vec::new(),
+                unique_heroes: Vec::new(),
             })),
             skills: Arc::new(RwLock::new(SkillDb {
                 list: Vec::new(),
                 id_map: HashMap::new(),
             })),
             monsters: Arc::new(RwLock::new(serde_json::Map::new())),
+            events: Arc::new(RwLock::new(HashMap::new())),
+        })
+        .on_window_event(|window, event| {
+            // Windows: 开启 minimize_to_tray 时关闭主窗口只隐藏到托盘，通过托盘菜单退出
+            if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+                if cfg!(target_os = "windows") && window.label() == "main" && load_state().minimize_to_tray {
+                    api.prevent_close();
+                    let _ = window.hide();
+                    println!("[Tray] Main window hidden to tray");
+                }
+            }
         })
         .setup(move |app| {
             let handle = app.handle().clone();
+            init_log_dir(&handle);
             log_system_info(&handle);
 
+            // 本地 HTTP 查询接口（仅 127.0.0.1）
+            tauri::async_runtime::spawn(local_api::serve(handle.clone()));
+
+            // 模板加载前确定 ORB 预处理方式
+            monster_recognition::set_enhance_contrast(load_state().enhance_contrast);
+            monster_recognition::set_orb_params(load_state().orb_params);
+            match monster_recognition::FeatureAlgorithm::parse(&load_state().feature_algorithm) {
+                Ok(algorithm) => monster_recognition::set_feature_algorithm(algorithm),
+                Err(e) => log_to_file(&format!("[Config] {}, falling back to ORB", e)),
+            }
+
+            // YOLO 类别名映射（自定义模型可替换 yolo_classes.json）
+            if let Ok(res_dir) = handle.path().resource_dir() {
+                monster_recognition::load_yolo_classes(&res_dir.join("resources").join("models").join("yolo_classes.json"));
+            }
+
             // macOS: 设置为 Accessory 模式（隐藏 dock 图标）
             // 这对于让窗口显示在全屏应用上方是必要的
             #[cfg(target_os = "macos")]
@@ -1951,7 +4929,7 @@ pub fn run() {
 
                     // 跨平台检测右键点击（使用 device_query）
                     let right_click = mouse.button_pressed[2]; // 右键是索引 2
-                    if right_click && !last_right_click {
+                    if right_click && !last_right_click && !load_state().disabled_hotkeys.contains("detail_display") {
                         let _ = handle_monitor.emit("global-right-click", serde_json::json!({ "x": mx, "y": my }));
                     }
                     last_right_click = right_click;
@@ -2007,15 +4985,59 @@ pub fn run() {
                     .build(app)?;
             }
 
+            // ============== Windows 托盘图标（显示 / 隐藏 / 退出） ==============
+            #[cfg(target_os = "windows")]
+            {
+                let tray_handle = app.handle().clone();
+                let show_item = MenuItem::with_id(app, "show", "显示", true, None::<&str>)?;
+                let hide_item = MenuItem::with_id(app, "hide", "隐藏", true, None::<&str>)?;
+                let quit_item = MenuItem::with_id(app, "quit", "退出", true, None::<&str>)?;
+                let menu = Menu::with_items(app, &[&show_item, &hide_item, &quit_item])?;
+
+                let _tray = TrayIconBuilder::new()
+                    .icon(app.default_window_icon().unwrap().clone())
+                    .tooltip("BazaarHelper")
+                    .menu(&menu)
+                    .show_menu_on_left_click(false)
+                    .on_menu_event(move |app: &tauri::AppHandle, event| {
+                        match event.id.as_ref() {
+                            "show" => show_main_from_tray(app),
+                            "hide" => {
+                                if let Some(main_win) = app.get_webview_window("main") {
+                                    let _ = main_win.hide();
+                                }
+                            }
+                            "quit" => app.exit(0),
+                            _ => {}
+                        }
+                    })
+                    .on_tray_icon_event(move |_tray, event| {
+                        if let TrayIconEvent::Click { button: MouseButton::Left, button_state: MouseButtonState::Up, .. } = event {
+                            show_main_from_tray(&tray_handle);
+                        }
+                    })
+                    .build(app)?;
+            }
+
             // ============== 跨平台 Overlay 初始化 ==============
             if let Some(overlay) = app.get_webview_window("overlay") {
-                let _ = overlay.set_ignore_cursor_events(true);
+                let click_through = load_state().overlay_click_through;
+                OVERLAY_CLICK_THROUGH.store(click_through, Ordering::SeqCst);
+                OVERLAY_IGNORE_CURSOR.store(click_through, Ordering::SeqCst);
+                let _ = overlay.set_ignore_cursor_events(click_through);
 
                 // macOS: 设置窗口可覆盖全屏应用
                 #[cfg(target_os = "macos")]
                 setup_macos_fullscreen_overlay(&overlay);
 
-                if let Ok(Some(monitor)) = overlay.primary_monitor() {
+                // 游戏已在运行时直接铺到游戏所在显示器，否则先用主屏，之后由窗口同步线程跟随游戏窗口
+                let mut placed = None;
+                if let Some(game_win) = monster_recognition::find_game_window(None).ok().flatten() {
+                    move_overlay_to_game_monitor(&overlay, &game_win, &mut placed);
+                }
+                if placed.is_some() {
+                    println!("[Overlay Init] Overlay placed on the game's monitor");
+                } else if let Ok(Some(monitor)) = overlay.primary_monitor() {
                     let size = monitor.size();
                     let position = monitor.position();
                     println!("[Overlay Init] Setting overlay: x={}, y={}, w={}, h={}",
@@ -2027,7 +5049,18 @@ pub fn run() {
                     let _ = overlay.set_size(tauri::PhysicalSize::new(3840, 2160));
                     let _ = overlay.set_position(tauri::PhysicalPosition::new(0, 0));
                 }
-                let _ = overlay.show();
+                if load_state().overlay_show_on_first_scan {
+                    println!("[Overlay Init] Overlay stays hidden until the first scan result");
+                    OVERLAY_REVEALED.store(false, Ordering::SeqCst);
+                } else {
+                    let _ = overlay.show();
+                }
+
+                // overlay 加载完成并显示结果时会发出同步请求，直接用保存的布局应答，不依赖固定延时
+                let layout_handle = app.handle().clone();
+                app.listen_any("request-sync-overlay-settings", move |_| {
+                    restore_overlay_detail_layout(&layout_handle);
+                });
             }
 
             // macOS: 主窗口也设置全屏覆盖
@@ -2052,21 +5085,22 @@ pub fn run() {
             let sync_handle = app.handle().clone();
             std::thread::spawn(move || {
                 let mut was_game_running = true;
+                // overlay 当前所在显示器的原点，游戏窗口移到其他显示器时跟随
+                let mut overlay_monitor: Option<(i32, i32)> = None;
 
                 loop {
                     std::thread::sleep(std::time::Duration::from_millis(500));
 
                     // 使用 xcap 跨平台查找游戏窗口
-                    let game_window = xcap::Window::all()
-                        .ok()
-                        .and_then(|windows| {
-                            windows.into_iter().find(|w| w.title().contains("The Bazaar"))
-                        });
+                    let game_window = monster_recognition::find_game_window(None).ok().flatten();
 
                     let main_win = sync_handle.get_webview_window("main");
                     let overlay_win = sync_handle.get_webview_window("overlay");
 
-                    if let Some(_game_win) = game_window {
+                    if let Some(game_win) = game_window {
+                        if let Some(ref w) = overlay_win {
+                            move_overlay_to_game_monitor(w, &game_win, &mut overlay_monitor);
+                        }
                         // 游戏正在运行
                         if !was_game_running {
                             // 游戏刚启动，显示窗口
@@ -2075,7 +5109,9 @@ pub fn run() {
                                 let _ = w.set_always_on_top(true);
                             }
                             if let Some(ref w) = overlay_win {
-                                let _ = w.show();
+                                if OVERLAY_REVEALED.load(Ordering::SeqCst) {
+                                    let _ = w.show();
+                                }
                                 let _ = w.set_always_on_top(true);
                             }
                         }
@@ -2103,22 +5139,32 @@ pub fn run() {
                 if path.exists() {
                      match std::fs::read_to_string(path) {
                         Ok(json) => {
+                            record_data_version("items", path, &json);
                             match serde_json::from_str::<Vec<RawItem>>(&json) {
                                 Ok(raw_list) => {
                                     let items_list: Vec<ItemData> = raw_list.into_iter().map(ItemData::from).collect();
                                     let mut id_map = HashMap::new();
                                     let mut tag_set = std::collections::HashSet::new();
+                                    let mut hero_set = std::collections::HashSet::new();
                                     for (index, item) in items_list.iter().enumerate() {
                                         id_map.insert(item.uuid.clone(), index);
                                         for tag in &item.processed_tags { tag_set.insert(tag.clone()); }
+                                        for hero in &item.heroes {
+                                            if !hero.is_empty() { hero_set.insert(hero.clone()); }
+                                        }
                                     }
                                     let mut unique_tags: Vec<String> = tag_set.into_iter().collect();
                                     unique_tags.sort();
+                                    // 英雄按字母排序，"Common"（通用物品）固定放在最后
+                                    let mut unique_heroes: Vec<String> = hero_set.into_iter().collect();
+                                    unique_heroes.sort_by_key(|h| (h == "Common", h.clone()));
                                     let count = items_list.len();
                                     let mut db = db_state.items.write().unwrap();
                                     db.list = items_list;
                                     db.id_map = id_map;
                                     db.unique_tags = unique_tags;
+                                    db.unique_heroes = unique_heroes;
+                                    invalidate_search_cache();
                                     log_to_file(&format!("[Init] Successfully loaded {} items from {:?}", count, path));
                                     break;
                                 },
@@ -2143,6 +5189,7 @@ pub fn run() {
                 if path.exists() {
                     match std::fs::read_to_string(path) {
                         Ok(json) => {
+                            record_data_version("skills", path, &json);
                             match serde_json::from_str::<Vec<RawItem>>(&json) {
                                 Ok(raw_list) => {
                                     let skills_list: Vec<ItemData> = raw_list.into_iter().map(ItemData::from).collect();
@@ -2152,6 +5199,7 @@ pub fn run() {
                                     let mut db = db_state.skills.write().unwrap();
                                     db.list = skills_list;
                                     db.id_map = id_map;
+                                    invalidate_search_cache();
                                     log_to_file(&format!("[Init] Successfully loaded {} skills from {:?}", count, path));
                                     break;
                                 },
@@ -2165,6 +5213,15 @@ pub fn run() {
                 }
             }
 
+            // 2.5 Load Events DB
+            match load_events_db(&handle) {
+                Ok(events) => {
+                    log_to_file(&format!("[Init] Successfully loaded {} events", events.len()));
+                    *db_state.events.write().unwrap() = events;
+                },
+                Err(e) => log_to_file(&format!("Error loading events: {}", e)),
+            }
+
             // 3. Load Monster Image Map
             let mut monster_img_map_path = resources_path.join("resources").join("images_monster_map.json");
             if !monster_img_map_path.exists() {
@@ -2211,11 +5268,12 @@ pub fn run() {
 
             if monsters_export_path.exists() {
                 if let Ok(json) = std::fs::read_to_string(&monsters_export_path) {
+                    record_data_version("monsters_export", &monsters_export_path, &json);
                     if let Ok(serde_json::Value::Array(exports)) = serde_json::from_str::<serde_json::Value>(&json) {
                         for m_val in exports {
                             if let Some(m_obj) = m_val.as_object() {
                                 let level = m_obj.get("level").and_then(|v| v.as_u64()).unwrap_or(0);
-                                let day_label = if level >= 10 { "Day 10+".to_string() } else { format!("Day {}", level) };
+                                let day_label = day_bucket_label(level as u32);
                                 let name_zh = m_obj.get("name_cn").and_then(|v| v.as_str()).unwrap_or("未知");
                                 let name_en = m_obj.get("name_en").and_then(|v| v.as_str()).unwrap_or("Unknown");
                                 
@@ -2277,24 +5335,28 @@ pub fn run() {
             let mut db_by_day: HashMap<String, Vec<(String, serde_json::Value)>> = HashMap::new();
             if monsters_db_path.exists() {
                 if let Ok(json) = std::fs::read_to_string(&monsters_db_path) {
+                    record_data_version("monsters", &monsters_db_path, &json);
                     if let Ok(serde_json::Value::Object(monsters)) = serde_json::from_str::<serde_json::Value>(&json) {
                         for (name, data) in monsters {
                             let day = data.get("available").and_then(|v| v.as_str()).unwrap_or("").to_string();
-                            if !day.is_empty() { db_by_day.entry(day).or_default().push((name, data)); }
+                            if !day.is_empty() { db_by_day.entry(canonical_day_label(&day)).or_default().push((name, data)); }
                         }
                     }
                 }
             }
 
             // Consolidate: Prioritize monsters_db, then supplement with monsters_export
-            for i in 0..21 {
-                let day_label = if i >= 10 { "Day 10+".to_string() } else { format!("Day {}", i) };
+            for i in 0..=DAY_BUCKET_MAX {
+                let day_label = day_bucket_label(i);
                 
                 // First check if Day exists in monsters_db
                 if let Some(db_monsters) = db_by_day.get(&day_label) {
                     for (name, m) in db_monsters {
                         let mut enriched_m = m.clone();
                         if let Some(m_obj) = enriched_m.as_object_mut() {
+                            // 统一为分组标签，前端按 available 筛选时与 Day Tab 一致
+                            m_obj.insert("available".to_string(), serde_json::Value::String(day_label.clone()));
+
                             // 强制设置图片路径（使用角色图），增加陷阱类前缀回退逻辑
                             let mut img_name = name.clone();
                             let img_path = resources_path.join("resources").join(format!("images_monster_char/{}.webp", img_name));
@@ -2412,6 +5474,16 @@ pub fn run() {
                     }
                 }
             }
+            // 难度评分随怪物数据一起缓存，排序时无需重复计算
+            for m in final_monsters.values_mut() {
+                let (score, total_damage, item_count) = compute_monster_threat(m, &items_db, &skills_db);
+                if let Some(m_obj) = m.as_object_mut() {
+                    m_obj.insert("threat_score".to_string(), serde_json::json!(score));
+                    m_obj.insert("loadout_damage".to_string(), serde_json::json!(total_damage));
+                    m_obj.insert("item_count".to_string(), serde_json::json!(item_count));
+                }
+            }
+
             let monster_count = final_monsters.len();
             *db_state.monsters.write().unwrap() = final_monsters.clone();
             log_to_file(&format!("Monsters DB populated with {} entries", monster_count));
@@ -2438,150 +5510,34 @@ pub fn run() {
             
             thread::spawn(move || {
                 let handle = log_handle;
-                let log_path = get_log_path();
-                let prev_path = get_prev_log_path();
-                
-                let re_purchase = Regex::new(r"Card Purchased: InstanceId:\s*(?P<iid>[^ ]+)\s*-\s*TemplateId\s*(?P<tid>[^ ]+)(?:.*Target:(?P<tgt>[^ ]+))?(?:.*Section(?P<sec>[^ ]+))?").unwrap();
-                let re_id = Regex::new(r"ID: \[(?P<id>[^\]]+)\]").unwrap();
-                let re_owner = Regex::new(r"- Owner: \[(?P<val>[^\]]+)\]").unwrap();
-                let re_section = Regex::new(r"- Section: \[(?P<val>[^\]]+)\]").unwrap();
-
-                let re_item_id = Regex::new(r"itm_[A-Za-z0-9_-]+").unwrap();
-                let re_sold = Regex::new(r"Sold Card\s+(?P<iid>itm_[^ ]+)").unwrap();
-                let re_removed = Regex::new(r"Successfully removed item\s+(?P<iid>itm_[^ ]+)").unwrap();
-                let re_moved_to = Regex::new(r"Successfully moved card\s+(?P<iid>itm_[^ ]+)\s+to\s+(?P<tgt>[^ ]+)").unwrap();
-                
-                // Initialize state from cache
-                let _cache_path = get_cache_path();
-                let _has_cache = _cache_path.exists();
-                let state_init = load_state();
-                
-                let mut inst_to_temp = state_init.inst_to_temp;
-                let mut current_hand = state_init.current_hand;
-                let mut current_stash = state_init.current_stash;
-                let mut current_day = state_init.day;
-                
-                let mut last_file_size = if log_path.exists() {
-                    std::fs::metadata(&log_path).map(|m| m.len()).unwrap_or(0)
-                } else {
-                    0
-                };
+                let mut log_path = get_log_path();
                 
-                let mut last_iid = String::new();
-                let mut cur_owner = String::new();
-                let mut in_pvp = false;
-                let mut is_sync = false;
+                let patterns = LogPatterns::new();
+                let LogPatterns { re_purchase, re_id, re_owner, re_section, re_item_id, re_sold, re_removed, re_moved_to } = &patterns;
 
                 // --- Initial Sync: Replay Logs to catch up with current state ---
                 println!("[LogMonitor] Initializing state from logs...");
-                
-                // Clear state for fresh scan (we'll recover inst_to_temp from logs too)
-                current_hand.clear();
-                current_stash.clear();
-                // inst_to_temp.clear(); // We keep cache as fallback, but logs will overwrite
-
-                let files_to_process = vec![prev_path, log_path.clone()];
-                for path in files_to_process {
-                    if !path.exists() { 
-                        println!("[LogMonitor] Skipping non-existent file: {:?}", path);
-                        continue; 
-                    }
-                    println!("[LogMonitor] Processing log file: {:?}", path);
-                    if let Ok(file) = File::open(&path) {
-                        let reader = BufReader::new(file);
-                        for line in reader.lines() {
-                            if let Ok(l) = line {
-                                let trimmed = l.trim();
-                                
-                                // Reset everything if we see a new run start
-                                if trimmed.contains("NetMessageRunInitialized") {
-                                    current_day = 1; in_pvp = false;
-                                    inst_to_temp.clear();
-                                    current_hand.clear();
-                                    current_stash.clear();
-                                    is_sync = false;
-                                }
+                let replay = resync_from_logs(&handle, &patterns);
 
-                                if trimmed.contains("to [PVPCombatState]") { in_pvp = true; }
-                                if in_pvp && trimmed.contains("State changed") && (trimmed.contains("to [ChoiceState]") || trimmed.contains("to [LevelUpState]")) {
-                                    current_day = current_day.saturating_add(1); in_pvp = false;
-                                }
+                let mut inst_to_temp = replay.inst_to_temp;
+                let mut current_hand = replay.current_hand;
+                let mut current_stash = replay.current_stash;
+                let mut current_day = replay.day;
+                let mut in_pvp = replay.in_pvp;
+                let mut last_file_size = replay.log_offset;
 
-                                if let Some(cap) = re_purchase.captures(trimmed) {
-                                    let iid = cap["iid"].to_string();
-                                    inst_to_temp.insert(iid.clone(), cap["tid"].to_string());
-                                    let mut section = cap.name("sec").map(|s| s.as_str().to_string());
-                                    if section.as_deref().unwrap_or("") == "" {
-                                        if let Some(tgt) = cap.name("tgt").map(|t| t.as_str()) {
-                                            if tgt.contains("PlayerStorageSocket") { section = Some("Stash".to_string()); }
-                                            else if tgt.contains("PlayerSocket") { section = Some("Player".to_string()); }
-                                        }
-                                    }
-                                    if let Some(s) = section {
-                                        if s == "Player" || s == "Hand" { current_hand.insert(iid); }
-                                        else if s == "Stash" || s == "Storage" || s == "PlayerStorage" { current_stash.insert(iid); }
-                                    }
-                                }
-                                if let Some(cap) = re_moved_to.captures(trimmed) {
-                                    let iid = cap["iid"].to_string();
-                                    if cap["tgt"].contains("StorageSocket") {
-                                        current_stash.insert(iid.clone()); current_hand.remove(&iid);
-                                    } else if cap["tgt"].contains("Socket") {
-                                        current_hand.insert(iid.clone()); current_stash.remove(&iid);
-                                    }
-                                }
-                                if let Some(cap) = re_sold.captures(trimmed) {
-                                    let iid = cap["iid"].to_string(); 
-                                    current_hand.remove(&iid); current_stash.remove(&iid);
-                                }
-                                if let Some(cap) = re_removed.captures(trimmed) {
-                                    let iid = cap["iid"].to_string(); 
-                                    current_hand.remove(&iid); current_stash.remove(&iid);
-                                }
-                                if trimmed.contains("Cards Disposed:") {
-                                    for mat in re_item_id.find_iter(trimmed) {
-                                        let iid = mat.as_str().to_string(); 
-                                        current_hand.remove(&iid); current_stash.remove(&iid);
-                                    }
-                                }
-                                if trimmed.contains("Cards Spawned:") || trimmed.contains("Cards Dealt:") || trimmed.contains("NetMessageGameStateSync") { 
-                                    is_sync = true; 
-                                }
-                                if is_sync {
-                                    if let Some(cap) = re_id.captures(trimmed) { last_iid = cap["id"].to_string(); }
-                                    else if let Some(cap) = re_owner.captures(trimmed) { cur_owner = cap["val"].to_string(); }
-                                    else if let Some(cap) = re_section.captures(trimmed) {
-                                        if !last_iid.is_empty() && &cur_owner == "Player" && last_iid.starts_with("itm_") {
-                                            let sec_val = &cap["val"];
-                                            if sec_val == "Hand" || sec_val == "Player" { 
-                                                current_hand.insert(last_iid.clone()); 
-                                                current_stash.remove(&last_iid);
-                                            }
-                                            else if sec_val == "Stash" || sec_val == "Storage" || sec_val == "PlayerStorage" { 
-                                                current_stash.insert(last_iid.clone()); 
-                                                current_hand.remove(&last_iid);
-                                            }
-                                            else {
-                                                current_hand.remove(&last_iid); 
-                                                current_stash.remove(&last_iid);
-                                            }
-                                        }
-                                        last_iid.clear(); cur_owner.clear();
-                                    }
-                                    else if trimmed.contains("Finished processing") { is_sync = false; }
-                                }
-                            }
-                        }
-                    }
-                }
+                let mut last_iid = String::new();
+                let mut cur_owner = String::new();
+                let mut is_sync = false;
 
-                save_state(&PersistentState {
-                    day: current_day,
-                    inst_to_temp: inst_to_temp.clone(),
-                    current_hand: current_hand.clone(),
-                    current_stash: current_stash.clone(),
-                    ..load_state()
-                });
+                let mut prev_log_path = get_prev_log_path();
+                // Player.log 被锁时的重试退避（毫秒），0 表示当前未处于锁定状态
+                let mut lock_backoff_ms: u64 = 0;
+                let mut last_auto_yolo: Option<time::Instant> = None;
+                let mut log_watcher = LogWatcher::new(&log_path, &prev_log_path);
+                // 待推送的 sync-items：Some(截止时间)，pending_day_update 记录窗口内是否换过天
+                let mut sync_due: Option<time::Instant> = None;
+                let mut pending_day_update = false;
 
                 // Initial UI Sync after loading/backfilling
                 let init_handle = handle.clone();
@@ -2597,16 +5553,8 @@ pub fn run() {
                     let _ = init_handle.emit("day-update", init_day);
                     let items_db = init_items_db.read().unwrap();
                     let skills_db = init_skills_db.read().unwrap();
-                    let hand_items = init_hand.iter()
-                        .filter_map(|iid| init_map.get(iid))
-                        .filter_map(|tid| lookup_item(tid, &items_db, &skills_db))
-                        .collect();
-                    let stash_items = init_stash.iter()
-                        .filter_map(|iid| init_map.get(iid))
-                        .filter_map(|tid| lookup_item(tid, &items_db, &skills_db))
-                        .collect();
-                    let all_tags = items_db.unique_tags.clone();
-                    let _ = init_handle.emit("sync-items", SyncPayload { hand_items, stash_items, all_tags });
+                    let payload = build_sync_payload(&init_hand, &init_stash, &init_map, &items_db, &skills_db);
+                    let _ = init_handle.emit("sync-items", payload);
                 });
 
                 println!("[LogMonitor] Initialization complete. Starting main monitoring loop...");
@@ -2616,6 +5564,37 @@ pub fn run() {
                 log_to_file(&format!("[LogMonitor] Starting monitor loop, initial size: {}", last_file_size));
                 
                 loop {
+                    // 设置里改了日志路径：切换监听并从新日志重新回放，回放结果按手动 resync 的方式接管
+                    if LOG_PATH_CHANGED.swap(false, Ordering::SeqCst) {
+                        let new_log_path = get_log_path();
+                        if new_log_path != log_path {
+                            log_to_file(&format!("[LogMonitor] Log path changed: {:?} -> {:?}", log_path, new_log_path));
+                            log_path = new_log_path;
+                            prev_log_path = get_prev_log_path();
+                            log_watcher.retarget(&log_path, &prev_log_path);
+                            lock_backoff_ms = 0;
+                            let replay = resync_from_logs(&handle, &patterns);
+                            if let Ok(mut pending) = get_pending_resync().lock() {
+                                *pending = Some(replay);
+                            }
+                            pending_day_update = true;
+                            sync_due = Some(time::Instant::now());
+                        }
+                    }
+
+                    // 接管手动 resync_inventory 的回放结果
+                    if let Some(replay) = get_pending_resync().lock().ok().and_then(|mut p| p.take()) {
+                        inst_to_temp = replay.inst_to_temp;
+                        current_hand = replay.current_hand;
+                        current_stash = replay.current_stash;
+                        current_day = replay.day;
+                        in_pvp = replay.in_pvp;
+                        last_file_size = replay.log_offset;
+                        last_iid.clear();
+                        cur_owner.clear();
+                        is_sync = false;
+                    }
+
                     if !log_path.exists() { 
                         log_to_file(&format!("[LogMonitor] Log file not found: {:?}", log_path));
                         thread::sleep(time::Duration::from_secs(2)); 
@@ -2652,15 +5631,41 @@ pub fn run() {
                         });
                     }
                     
+                    // 日志不再增长，追赶结束
+                    if current_file_size == last_file_size {
+                        set_log_catching_up(&handle, false);
+                    }
+
                     if current_file_size > last_file_size {
                         // Prevent spamming triggers if we are catching up on a large log chunk (>5000 bytes)
+                        // 追赶状态跨多个轮询周期保持，直到日志停止增长
                         let is_bulk_read = (current_file_size - last_file_size) > 5000;
                         if is_bulk_read {
-                            log_to_file(&format!("[LogMonitor] Bulk read detected: {} bytes, will skip YOLO triggers for this batch", current_file_size - last_file_size));
+                            log_to_file(&format!("[LogMonitor] Bulk read detected: {} bytes, will skip YOLO triggers until the log stabilizes", current_file_size - last_file_size));
+                            set_log_catching_up(&handle, true);
                         }
+                        let catching_up = is_log_catching_up();
+                        // 每个读取块只读一次配置，追赶历史日志时不触发
+                        let auto_yolo = !catching_up && load_state().auto_yolo_on_state_change;
                         
-                        let mut f = match File::open(&log_path) {
-                            Ok(file) => file,
+                        let mut f = match open_log_shared(&log_path) {
+                            Ok(file) => {
+                                if lock_backoff_ms > 0 {
+                                    log_to_file("[LogMonitor] Player.log is readable again, resuming");
+                                    lock_backoff_ms = 0;
+                                }
+                                file
+                            }
+                            Err(e) if is_log_lock_error(&e) => {
+                                // 游戏独占了 Player.log：last_file_size 保持不变，退避重试，锁释放后从原偏移补读
+                                // 每次锁定只记一条日志，避免每秒刷屏
+                                if lock_backoff_ms == 0 {
+                                    log_to_file(&format!("[LogMonitor] Player.log is locked ({}), retrying with backoff", e));
+                                }
+                                lock_backoff_ms = (lock_backoff_ms * 2).clamp(250, 5000);
+                                thread::sleep(time::Duration::from_millis(lock_backoff_ms));
+                                continue;
+                            }
                             Err(e) => {
                                 log_to_file(&format!("Failed to open log file for reading: {}", e));
                                 thread::sleep(time::Duration::from_secs(1));
@@ -2668,16 +5673,30 @@ pub fn run() {
                             }
                         };
                         let _ = f.seek(SeekFrom::Start(last_file_size));
-                        let reader = BufReader::new(f);
+                        let mut chunk = Vec::new();
+                        if let Err(e) = f.take(current_file_size - last_file_size).read_to_end(&mut chunk) {
+                            log_to_file(&format!("Failed to read log file: {}", e));
+                            thread::sleep(time::Duration::from_secs(1));
+                            continue;
+                        }
+                        // 只处理到最后一个换行：游戏正在写的半行留到下次连同剩余部分一起读，避免被拆成两行
+                        let complete_len = chunk.iter().rposition(|&b| b == b'\n').map_or(0, |pos| pos + 1);
+                        if complete_len == 0 {
+                            // 只有未写完的一行，没有积压的日志
+                            set_log_catching_up(&handle, false);
+                        }
+                        let reader = BufReader::new(&chunk[..complete_len]);
                         
                         let mut changed = false;
                         let mut day_changed = false;
+                        let mut run_started = false;
                         for line in reader.lines() {
                             let l = if let Ok(l) = line { l } else { continue };
                             let trimmed = l.trim();
 
                             // Day Detection Logic
                             if trimmed.contains("NetMessageRunInitialized") {
+                                run_started = true;
                                 current_day = 1; in_pvp = false; day_changed = true;
                                 inst_to_temp.clear();
                                 current_hand.clear();
@@ -2689,7 +5708,7 @@ pub fn run() {
                             if trimmed.contains("to [PVPCombatState]") { 
                                 in_pvp = true; 
                             }
-                            
+
                             // Day increment: The most reliable trigger is the transition back to Map (ChoiceState) after a PVP fight.
                             if in_pvp && trimmed.contains("State changed") && (trimmed.contains("to [ChoiceState]") || trimmed.contains("to [LevelUpState]")) {
                                 current_day = current_day.saturating_add(1);
@@ -2698,33 +5717,16 @@ pub fn run() {
                                 println!("[DayMonitor] Day increased to {} after PVP completion", current_day);
                             }
 
-                            /* 
-                            // YOLO Trigger on ANY State changed (controlled by enable-yolo-auto setting)
-                            // Debug: Log every state change line and check conditions
-                            if trimmed.contains("State changed") {
-                                println!("[Debug] Found 'State changed' line: {}", trimmed);
-                                println!("[Debug] is_bulk_read: {}", is_bulk_read);
-                                println!("[Debug] contains 'State changed from [': {}", trimmed.contains("State changed from ["));
-                                println!("[Debug] contains '] to [': {}", trimmed.contains("] to ["));
-                            }
-                            
-                            if !is_bulk_read && trimmed.contains("State changed from [") && trimmed.contains("] to [") {
-                                println!("[State Change Detected] Emitting YOLO trigger for: {}", trimmed);
-                                log_to_file(&format!("[State Change Detected] {}", trimmed));
-                                log_to_file("[Backend] Emitting trigger_yolo_scan event to frontend");
-                                // Emit event to frontend, which will check enable-yolo-auto setting
-                                match handle.emit("trigger_yolo_scan", ()) {
-                                    Ok(_) => {
-                                        println!("[Backend] trigger_yolo_scan event emitted successfully");
-                                        log_to_file("[Backend] trigger_yolo_scan event emitted successfully");
-                                    },
-                                    Err(e) => {
-                                        println!("[Backend] Failed to emit trigger_yolo_scan: {}", e);
-                                        log_to_file(&format!("[Backend] Failed to emit trigger_yolo_scan: {}", e));
-                                    },
+                            if auto_yolo && is_auto_yolo_transition(trimmed) {
+                                let due = last_auto_yolo.map_or(true, |at| at.elapsed() >= time::Duration::from_millis(AUTO_YOLO_DEBOUNCE_MS));
+                                if due {
+                                    last_auto_yolo = Some(time::Instant::now());
+                                    log_to_file(&format!("[AutoYolo] State transition detected, requesting scan: {}", trimmed));
+                                    if let Err(e) = handle.emit("trigger_yolo_scan", ()) {
+                                        log_to_file(&format!("[AutoYolo] Failed to emit trigger_yolo_scan: {}", e));
+                                    }
                                 }
                             }
-                            */
 
                             if let Some(cap) = re_purchase.captures(trimmed) {
                                 let iid = cap["iid"].to_string();
@@ -2827,30 +5829,22 @@ pub fn run() {
                             }
                         }
 
+                        if run_started {
+                            // 新对局：先通知前端清空显示，再推送本批次的天数与物品
+                            println!("[LogMonitor] New run detected");
+                            let _ = handle.emit("run-started", ());
+                            // 上一局的跳转上下文不再有效
+                            let mut state = load_state();
+                            state.last_jumped_day = None;
+                            state.last_recognized_monster = None;
+                            save_state(&state);
+                        }
+
                         if changed || day_changed {
-                            if day_changed {
-                                let _ = handle.emit("day-update", current_day);
-                            }
-                            let items_db = thread_items_db.read().unwrap();
-                            let skills_db = thread_skills_db.read().unwrap();
-                            
-                            let map_items = |ids: &HashSet<String>| -> Vec<ItemData> {
-                                ids.iter()
-                                   .filter_map(|iid| {
-                                       let tid = inst_to_temp.get(iid)?;
-                                       let mut item = lookup_item(tid, &items_db, &skills_db)?;
-                                       item.instance_id = Some(iid.clone());
-                                       Some(item)
-                                   })
-                                   .collect()
-                            };
-
-                            let hand_items = map_items(&current_hand);
-                            let stash_items = map_items(&current_stash);
-                            
-                            let all_tags = items_db.unique_tags.clone();
-                            let _ = handle.emit("sync-items", SyncPayload { hand_items, stash_items, all_tags });
-                            
+                            // 状态立即落盘，前端推送交给合并窗口
+                            pending_day_update |= day_changed;
+                            sync_due.get_or_insert_with(|| time::Instant::now() + time::Duration::from_millis(SYNC_ITEMS_DEBOUNCE_MS));
+
                             save_state(&PersistentState {
                                 day: current_day,
                                 inst_to_temp: inst_to_temp.clone(),
@@ -2859,9 +5853,27 @@ pub fn run() {
                                 ..load_state()
                             });
                         }
-                        last_file_size = current_file_size;
+                        last_file_size += complete_len as u64;
+                    }
+                    // 合并窗口到期且不在追赶历史日志时，推送一次最终状态
+                    if sync_due.is_some_and(|due| time::Instant::now() >= due) && !is_log_catching_up() {
+                        sync_due = None;
+                        if std::mem::take(&mut pending_day_update) {
+                            let _ = handle.emit("day-update", current_day);
+                        }
+                        let items_db = thread_items_db.read().unwrap();
+                        let skills_db = thread_skills_db.read().unwrap();
+                        let payload = build_sync_payload(&current_hand, &current_stash, &inst_to_temp, &items_db, &skills_db);
+                        let _ = handle.emit("sync-items", payload);
                     }
-                    thread::sleep(time::Duration::from_millis(500));
+
+                    // 等待文件写入事件，兜底每 LOG_FALLBACK_POLL_MS 检查一次；有待推送状态时最多等到窗口结束
+                    let timeout = match sync_due {
+                        Some(_) if is_log_catching_up() => time::Duration::from_millis(SYNC_ITEMS_DEBOUNCE_MS),
+                        Some(due) => due.saturating_duration_since(time::Instant::now()),
+                        None => time::Duration::from_millis(LOG_FALLBACK_POLL_MS),
+                    };
+                    log_watcher.wait(timeout);
                 }
             });
 
@@ -2873,30 +5885,38 @@ pub fn run() {
                 let mut last_card_trigger = time::Instant::now();
                 let mut last_toggle_trigger = time::Instant::now();
                 let mut last_yolo_trigger = time::Instant::now();
+                let mut deduper = EmitDeduper::new();
                 loop {
                     let mouse_state = device_state.get_mouse();
 
                     // 读取配置的按键
-                    let (monster_hotkey, card_hotkey, toggle_hotkey, yolo_hotkey) = {
+                    let (monster_hotkey, card_hotkey, toggle_hotkey, yolo_hotkey, dedup_ms, disabled) = {
                         let state = load_state();
                         (
-                            state.detection_hotkey.unwrap_or(default_monster_hotkey()),
-                            state.card_detection_hotkey.unwrap_or(default_card_hotkey()),
-                            state.toggle_collapse_hotkey.unwrap_or(192),
-                            state.yolo_hotkey.unwrap_or(81)
+                            state.detection_hotkey.unwrap_or_else(|| Hotkey::single(default_monster_hotkey())),
+                            state.card_detection_hotkey.unwrap_or_else(|| Hotkey::single(default_card_hotkey())),
+                            state.toggle_collapse_hotkey.unwrap_or_else(|| Hotkey::single(192)),
+                            state.yolo_hotkey.unwrap_or_else(|| Hotkey::single(81)),
+                            state.toast_dedup_ms,
+                            state.disabled_hotkeys
                         )
                     };
+                    // 被禁用的热键跳过检测
+                    let enabled = |name: &str| !disabled.contains(name);
 
                     // 1. 检测怪物识别按键
-                    if is_key_pressed(monster_hotkey, &device_state, &mouse_state) {
+                    if enabled("detection") && is_key_pressed(&monster_hotkey, &device_state, &mouse_state) {
                             if last_trigger.elapsed() > time::Duration::from_millis(500) {
                                 last_trigger = time::Instant::now();
                                 log_to_file("Monster Hotkey pressed, starting scan...");
                                 
-                                // 尝试识别怪物
-                                match scan_and_identify_monster_at_mouse() {
-                                    Ok(Some(monster_name)) => {
+                                // 尝试识别怪物：可信则自动跳转，否则把候选列表交给前端让用户点选
+                                let scan = scan_and_identify_monster_candidates()
+                                    .map(|candidates| (pick_confident_monster(&candidates), candidates));
+                                match scan {
+                                    Ok((Some(monster_name), _)) => {
                                         log_to_file(&format!("Success! Valid monster found: {}", monster_name));
+                                        reveal_overlay_on_first_scan(&handle_mouse);
                                         
                                         // 关键修复：处理陷阱类并列名称
                                         let lookup_name = if monster_name.contains('|') {
@@ -2917,45 +5937,35 @@ pub fn run() {
 
                                                 if let Some(entry) = entry_opt {
                                                     let target_name_zh = entry.get("name_zh").and_then(|v| v.as_str()).unwrap_or(&monster_name);
-                                                    let mut candidate_days: Vec<u32> = Vec::new();
-                                                    
                                                     // 寻找所有具有相同中文名的怪物条目（解决同名不同天数问题）
-                                                    for (_, v) in monsters.iter() {
-                                                        if let Some(n_zh) = v.get("name_zh").and_then(|val| val.as_str()) {
-                                                            if n_zh == target_name_zh {
-                                                                if let Some(d_str) = v.get("available").and_then(|val| val.as_str()) {
-                                                                    if d_str.starts_with("Day ") {
-                                                                        let num_part = d_str[4..].trim_end_matches('+');
-                                                                        if let Ok(d_num) = num_part.parse::<u32>() {
-                                                                            candidate_days.push(d_num);
-                                                                        }
-                                                                    }
-                                                                }
-                                                            }
-                                                        }
-                                                    }
+                                                    let candidate_days = monster_variant_days(&monsters, target_name_zh);
                                                     
                                                     if !candidate_days.is_empty() {
-                                                        candidate_days.sort();
-                                                        candidate_days.dedup();
-
                                                         let current_day = load_state().day;
-                                                        let target_day = if candidate_days.contains(&current_day) {
+                                                        // 按分组比较：第 15 天识别到 "Day 10+" 的怪物时保持第 15 天，而不是跳回第 10 天
+                                                        let target_day = if candidate_days.iter().any(|&d| day_bucket(d) == day_bucket(current_day)) {
                                                             current_day
                                                         } else {
                                                             *candidate_days.iter().min_by_key(|&&d| (d as i32 - current_day as i32).abs()).unwrap()
                                                         };
 
-                                                        match handle_mouse.emit("auto-jump-to-monster", serde_json::json!({
-                                                            "day": target_day,
-                                                            "monster_name": monster_name // 使用包含 | 的原始名称
-                                                        })) {
-                                                            Ok(_) => {},
-                                                            Err(e) => println!("Failed to emit auto-jump-to-monster: {}", e),
+                                                        if deduper.should_emit(&format!("jump:{}:{}", target_day, monster_name), dedup_ms) {
+                                                            match handle_mouse.emit("auto-jump-to-monster", serde_json::json!({
+                                                                "day": target_day,
+                                                                "monster_name": monster_name, // 使用包含 | 的原始名称
+                                                                "candidate_days": candidate_days
+                                                            })) {
+                                                                Ok(_) => {},
+                                                                Err(e) => println!("Failed to emit auto-jump-to-monster: {}", e),
+                                                            }
+                                                        } else {
+                                                            println!("[Dedup] Skipped repeated auto-jump for {}", monster_name);
                                                         }
                                                         
                                                         let mut state = load_state();
                                                         state.day = target_day;
+                                                        state.last_jumped_day = Some(target_day);
+                                                        state.last_recognized_monster = Some(monster_name.clone());
                                                         save_state(&state);
                                                         
                                                         println!("自动跳转到 Day {} (识别: {}, 候选天数: {:?})", target_day, lookup_name, candidate_days);
@@ -2963,33 +5973,47 @@ pub fn run() {
                                                 }
                                             }
                                         }
+                                        restore_game_focus_if_enabled();
                                     }
-                                    Ok(None) => {
+                                    Ok((None, candidates)) => {
                                         // Scan successful but no monster found
-                                        log_to_file("Scan complete, no monster matched.");
+                                        log_to_file(&format!("Scan complete, no confident monster match. Candidates: {:?}", candidates));
+                                        let key = candidates.iter().map(|c| c.0.as_str()).collect::<Vec<_>>().join("|");
+                                        if deduper.should_emit(&format!("candidates:{}", key), dedup_ms) {
+                                            // 检测热键默认是右键，几乎每次右键都会走到这里，没有真实信号时既不提示也不弹候选；
+                                            // 有信号时先推送状态（最接近的候选、差几点达标），再推送候选列表
+                                            if monster_recognition::has_candidate_signal(&candidates) {
+                                                let _ = handle_mouse.emit("monster-match-status", monster_recognition::monster_match_status(&candidates));
+                                                let _ = handle_mouse.emit("monster-candidates", monster_candidates_json(&candidates));
+                                            }
+                                        }
+                                        restore_game_focus_if_enabled();
                                     }
                                     Err(e) => {
                                         let err_msg = format!("Monster Scan Failed: {}", e);
                                         println!("[Error] {}", err_msg);
                                         log_to_file(&format!("Error: {}", err_msg));
                                         // Emit error to frontend for toast
-                                        let _ = handle_mouse.emit("scan-error", e);
+                                        if deduper.should_emit(&format!("error:{}", e), dedup_ms) {
+                                            let _ = handle_mouse.emit("scan-error", e);
+                                        }
                                     }
                                 }
                             }
                         }
 
                     // 2. 检测卡牌识别按键
-                    if is_key_pressed(card_hotkey, &device_state, &mouse_state) {
+                    if enabled("card_detection") && is_key_pressed(&card_hotkey, &device_state, &mouse_state) {
                         if last_card_trigger.elapsed() > time::Duration::from_millis(500) {
                             last_card_trigger = time::Instant::now();
                             log_to_file("Card Hotkey pressed, triggering recognition...");
+                            // 卡牌识别由前端执行，结果展示后前端调用 restore_game_focus_after_hotkey 交还焦点
                             let _ = handle_mouse.emit("hotkey-detect-card", ());
                         }
                     }
 
                     // 3. 检测折叠/展开按键
-                    if is_key_pressed(toggle_hotkey, &device_state, &mouse_state) {
+                    if enabled("toggle_collapse") && is_key_pressed(&toggle_hotkey, &device_state, &mouse_state) {
                         if last_toggle_trigger.elapsed() > time::Duration::from_millis(500) {
                             last_toggle_trigger = time::Instant::now();
                             log_to_file("Toggle Hotkey pressed");
@@ -2997,8 +6021,8 @@ pub fn run() {
                         }
                     }
 
-                    // 4. 检测YOLO手动触发按键（排除左右键）
-                    if yolo_hotkey != 1 && yolo_hotkey != 2 && is_key_pressed(yolo_hotkey, &device_state, &mouse_state) {
+                    // 4. 检测YOLO手动触发按键（排除不带修饰键的左右键）
+                    if enabled("yolo") && !yolo_hotkey.is_plain_mouse_button() && is_key_pressed(&yolo_hotkey, &device_state, &mouse_state) {
                         if last_yolo_trigger.elapsed() > time::Duration::from_millis(500) {
                             last_yolo_trigger = time::Instant::now();
                             log_to_file("YOLO Hotkey pressed");
@@ -3015,12 +6039,31 @@ pub fn run() {
         })
         .invoke_handler(tauri::generate_handler![
             get_all_monsters,
+            get_all_skills,
+            suggest_counters,
+            get_monsters_sorted_by_threat,
             debug_monsters_db,
             debug_resource_paths,
             clear_yolo_cache,
+            clear_recognition_caches,
+            get_recognition_cache_ms,
+            set_recognition_cache_ms,
+            recompute_processed_tags,
             recognize_monsters_from_screenshot,
+            preview_monster_slots,
+            export_current_build,
+            scan_and_identify_monster_candidates_at_mouse,
+            scan_and_identify_monster_at_mouse,
+            get_monster_scan_region,
+            set_monster_scan_region,
+            preview_monster_scan_region,
+            export_template_cache,
+            import_template_cache,
             get_template_loading_progress,
+            get_template_stats,
             get_current_day,
+            get_current_day_with_source,
+            resync_inventory,
             update_day,
             get_detection_hotkey,
             set_detection_hotkey,
@@ -3030,27 +6073,106 @@ pub fn run() {
             set_toggle_collapse_hotkey,
             set_yolo_hotkey,
             get_detail_display_hotkey,
+            set_hotkey_enabled,
+            export_settings,
+            import_settings,
+            get_recognition_diagnostics,
             set_detail_display_hotkey,
+            get_all_hotkeys,
+            get_restore_last_jump,
+            set_restore_last_jump,
+            restore_last_jump,
             start_template_loading,
             get_item_info,
+            get_items_by_ids,
+            get_item_by_instance,
             search_items,
+            get_heroes,
+            get_data_version,
+            reload_events,
+            get_event_by_id,
             crate::monster_recognition::check_opencv_load, 
             crate::monster_recognition::recognize_card_at_mouse,
             crate::monster_recognition::load_event_templates,
             crate::monster_recognition::recognize_event_at_mouse,
             trigger_yolo_scan,
             abort_yolo_scan,
+            is_log_catching_up,
+            get_yolo_detections,
+            validate_yolo_model,
             invoke_yolo_scan,
             handle_overlay_right_click,
+            identify_at_cursor,
             update_overlay_bounds,
             emit_to_main,
+            send_overlay_message,
             get_yolo_stats,
+            benchmark_full_pipeline,
             get_show_yolo_monitor,
-            // clear_monster_cache,
+            get_monster_overlap_ratio,
+            set_monster_overlap_ratio,
+            get_min_crop_size,
+            set_min_crop_size,
+            get_enhance_contrast,
+            set_enhance_contrast,
+            get_orb_params,
+            set_orb_params,
+            get_feature_algorithm,
+            set_feature_algorithm,
+            compare_items,
+            list_yolo_models,
+            get_active_yolo_model,
+            set_active_yolo_model,
+            measure_contrast_enhancement,
+            get_overlay_show_on_first_scan,
+            get_overlay_style,
+            set_overlay_style,
+            set_overlay_show_on_first_scan,
+            get_toast_dedup_ms,
+            set_toast_dedup_ms,
+            get_update_settings,
+            set_update_settings,
+            check_for_update_now,
+            get_nms_strategy,
+            set_nms_strategy,
+            get_max_detections,
+            set_max_detections,
+            get_yolo_thresholds,
+            set_yolo_thresholds,
+            get_event_match_thresholds,
+            set_event_match_thresholds,
+            get_merge_scan_detections,
+            set_merge_scan_detections,
+            get_fullscreen_capture_fallback,
+            set_fullscreen_capture_fallback,
+            get_phash_prefilter,
+            set_phash_prefilter,
+            get_minimize_to_tray,
+            set_minimize_to_tray,
+            get_auto_yolo_on_state_change,
+            set_auto_yolo_on_state_change,
+            get_custom_log_path,
+            set_custom_log_path,
+            get_excluded_window_titles,
+            set_excluded_window_titles,
+            get_capture_inset,
+            set_capture_inset,
+            get_recognition_settings,
+            set_recognition_settings,
+            rebuild_monster_cache,
+            get_api_port,
+            get_monster_variants,
             set_overlay_ignore_cursor,
+            get_overlay_ignore_cursor,
+            get_overlay_click_through,
+            set_overlay_click_through,
             set_show_yolo_monitor,
             update_overlay_detail_position,
-            restore_game_focus
+            get_overlay_detail_layout,
+            restore_game_focus,
+            restore_game_focus_after_hotkey,
+            get_auto_restore_focus,
+            set_auto_restore_focus
         ])
         .run(tauri::generate_context!())
         .map_err(|e| {
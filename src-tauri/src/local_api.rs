@@ -0,0 +1,142 @@
+// 本地只读 HTTP 接口，供 Stream Deck / 网页叠加等外部工具查询物品数据
+// 只绑定 127.0.0.1 的随机端口，端口号通过 get_api_port 命令暴露给前端；
+// 不返回 CORS 头，浏览器里的任意网页不能跨域读取
+use std::sync::atomic::{AtomicU16, Ordering};
+use tauri::Manager;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::{get_item_info_internal, log_to_file, search_items_internal, DbState, SearchQuery};
+
+// 0 表示服务未启动
+static API_PORT: AtomicU16 = AtomicU16::new(0);
+
+const MAX_REQUEST_BYTES: usize = 8 * 1024;
+// 读取请求头的超时：连接后迟迟不发完请求的客户端不能一直占着任务
+const REQUEST_READ_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+pub fn api_port() -> Option<u16> {
+    match API_PORT.load(Ordering::Relaxed) {
+        0 => None,
+        port => Some(port),
+    }
+}
+
+pub async fn serve(app: tauri::AppHandle) {
+    let listener = match TcpListener::bind("127.0.0.1:0").await {
+        Ok(l) => l,
+        Err(e) => {
+            log_to_file(&format!("[LocalApi] Failed to bind: {}", e));
+            return;
+        }
+    };
+    if let Ok(addr) = listener.local_addr() {
+        API_PORT.store(addr.port(), Ordering::Relaxed);
+        println!("[LocalApi] Listening on http://{}", addr);
+        log_to_file(&format!("[LocalApi] Listening on http://{}", addr));
+    }
+
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = handle_connection(&app, stream).await {
+                        println!("[LocalApi] Connection error: {}", e);
+                    }
+                });
+            }
+            Err(e) => log_to_file(&format!("[LocalApi] Accept failed: {}", e)),
+        }
+    }
+}
+
+async fn handle_connection(app: &tauri::AppHandle, mut stream: TcpStream) -> std::io::Result<()> {
+    // 只需要请求行，读到头部结束或达到上限即可
+    let mut buf = Vec::with_capacity(1024);
+    let mut chunk = [0u8; 1024];
+    let read_request = async {
+        while buf.len() < MAX_REQUEST_BYTES && !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+            let n = stream.read(&mut chunk).await?;
+            if n == 0 {
+                break;
+            }
+            buf.extend_from_slice(&chunk[..n]);
+        }
+        Ok::<(), std::io::Error>(())
+    };
+    tokio::time::timeout(REQUEST_READ_TIMEOUT, read_request)
+        .await
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "timed out reading request"))??;
+
+    let request = String::from_utf8_lossy(&buf);
+    let mut parts = request.lines().next().unwrap_or("").split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+
+    let (status, body) = if method != "GET" {
+        ("405 Method Not Allowed", serde_json::json!({ "error": "only GET is supported" }))
+    } else {
+        route(app, target).await
+    };
+
+    let body = body.to_string();
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, body.len(), body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+async fn route(app: &tauri::AppHandle, target: &str) -> (&'static str, serde_json::Value) {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let state = app.state::<DbState>();
+
+    if let Some(id) = path.strip_prefix("/item/") {
+        let id = url_decode(id);
+        return match get_item_info_internal(&state, id.clone()).await {
+            Some(item) => ("200 OK", serde_json::json!(item)),
+            None => ("404 Not Found", serde_json::json!({ "error": format!("item {} not found", id) })),
+        };
+    }
+
+    if path == "/search" {
+        let keyword = query.split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(k, _)| *k == "keyword")
+            .map(|(_, v)| url_decode(v))
+            .filter(|k| !k.is_empty());
+        let search = SearchQuery { keyword, ..Default::default() };
+        return match search_items_internal(search, &state) {
+            Ok(items) => ("200 OK", serde_json::json!(items)),
+            Err(e) => ("500 Internal Server Error", serde_json::json!({ "error": e })),
+        };
+    }
+
+    ("404 Not Found", serde_json::json!({ "error": "unknown endpoint, try /item/{id} or /search?keyword=" }))
+}
+
+// 查询参数的百分号解码（'+' 视为空格），非法序列原样保留
+fn url_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok().and_then(|h| u8::from_str_radix(h, 16).ok());
+                match hex {
+                    Some(b) => {
+                        out.push(b);
+                        i += 2;
+                    }
+                    None => out.push(b'%'),
+                }
+            }
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
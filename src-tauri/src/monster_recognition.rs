@@ -2,18 +2,24 @@ use image::{DynamicImage, GenericImageView, imageops::FilterType};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex, OnceLock};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use rayon::prelude::*;
 use ndarray::Array;
 use ort::{
     session::{builder::GraphOptimizationLevel, Session},
     value::Value
 };
+use ort::execution_providers::ExecutionProviderDispatch;
 #[cfg(target_os = "windows")]
 use ort::execution_providers::DirectMLExecutionProvider;
+#[cfg(target_os = "macos")]
+use ort::execution_providers::CoreMLExecutionProvider;
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+use ort::execution_providers::CUDAExecutionProvider;
 use opencv::{
-    core::{Mat, Vector, KeyPoint, DMatch, NORM_HAMMING},
-    features2d::{ORB, BFMatcher},
+    core::{Mat, Vector, KeyPoint, DMatch, NORM_HAMMING, NORM_HAMMING2, NORM_L2},
+    features2d::{ORB, AKAZE, BFMatcher},
     imgcodecs::{imdecode, IMREAD_GRAYSCALE},
     prelude::*,
 };
@@ -33,62 +39,337 @@ pub struct YoloDetection {
     pub class_id: usize,
 }
 
-static YOLO_SESSION: OnceLock<Mutex<Session>> = OnceLock::new();
+// YOLO 类别名 -> id 映射，启动时从 resources/models/yolo_classes.json 加载（{"day": 0, "event": 1, ...}）
+// 自定义模型类别顺序不同时只需替换该文件；文件缺失或解析失败时使用内置默认顺序
+pub const CLASS_DAY: &str = "day";
+pub const CLASS_EVENT: &str = "event";
+pub const CLASS_ITEM: &str = "item";
+pub const CLASS_MONSTER_ICON: &str = "monstericon";
+pub const CLASS_RANDOM_ICON: &str = "randomicon";
+pub const CLASS_SHOP_ICON: &str = "shopicon";
+pub const CLASS_SKILL: &str = "skill";
 
-pub fn get_yolo_session(model_path: &PathBuf, #[allow(unused_variables)] use_gpu: bool) -> Result<impl std::ops::DerefMut<Target = Session> + '_, String> {
-    if let Some(mutex) = YOLO_SESSION.get() {
-        return mutex.lock().map_err(|e| e.to_string());
+// 内置默认顺序，与训练时的 ['day','event','item','monstericon','randomicon','shopicon','skill'] 一致
+const KNOWN_CLASSES: [&str; 7] = [CLASS_DAY, CLASS_EVENT, CLASS_ITEM, CLASS_MONSTER_ICON, CLASS_RANDOM_ICON, CLASS_SHOP_ICON, CLASS_SKILL];
+
+static YOLO_CLASSES: OnceLock<RwLock<HashMap<String, usize>>> = OnceLock::new();
+
+fn default_yolo_classes() -> HashMap<String, usize> {
+    KNOWN_CLASSES
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (name.to_string(), i))
+        .collect()
+}
+
+fn yolo_classes() -> &'static RwLock<HashMap<String, usize>> {
+    YOLO_CLASSES.get_or_init(|| RwLock::new(default_yolo_classes()))
+}
+
+pub fn load_yolo_classes(path: &PathBuf) {
+    let loaded = std::fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|s| serde_json::from_str::<HashMap<String, usize>>(&s).map_err(|e| e.to_string()));
+    match loaded {
+        Ok(map) if !map.is_empty() => {
+            println!("[YOLO] Loaded {} class labels from {:?}", map.len(), path);
+            *yolo_classes().write().unwrap() = map;
+        }
+        Ok(_) => println!("[YOLO] {:?} is empty, using default class labels", path),
+        Err(e) => println!("[YOLO] Failed to load class labels from {:?}: {}, using defaults", path, e),
     }
+}
 
-    // Windows: 支持 DirectML GPU 加速
-    // macOS/Linux: 仅 CPU 推理
-    #[cfg(target_os = "windows")]
-    let actually_use_gpu = use_gpu;
-    #[cfg(not(target_os = "windows"))]
-    let actually_use_gpu = false;
+pub fn yolo_class_id(name: &str) -> Option<usize> {
+    yolo_classes().read().unwrap().get(name).copied()
+}
 
-    if actually_use_gpu {
-        log_to_file("[YOLO] Initializing session with DirectML execution provider...");
-    } else {
-        log_to_file("[YOLO] Initializing session with CPU execution provider...");
+// 日志 / 统计用的类别名：按当前映射（含自定义 yolo_classes.json）反查，映射里没有的 id 返回 "unknown"
+pub fn yolo_class_name(class_id: usize) -> String {
+    yolo_classes().read().unwrap().iter()
+        .find(|(_, &id)| id == class_id)
+        .map(|(name, _)| name.clone())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+impl YoloDetection {
+    pub fn is_class(&self, name: &str) -> bool {
+        yolo_class_id(name) == Some(self.class_id)
     }
+}
 
-    let builder = Session::builder()
-        .map_err(|e| format!("创建Session Builder失败: {}", e))?;
+// NMS 策略：PerClass（默认）先按类别分组再组内抑制，event 与 monstericon / item 等跨类别重叠框都会保留；
+// ClassAgnostic 不区分类别互相抑制（旧行为）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NmsStrategy {
+    ClassAgnostic,
+    #[default]
+    PerClass,
+}
 
+// 截图四周按百分比（0-50）裁掉的边距，用于剔除常驻 HUD（顶栏/底栏）造成的误检；全 0 表示不裁剪
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct CaptureInset {
+    #[serde(default)]
+    pub top: f32,
+    #[serde(default)]
+    pub bottom: f32,
+    #[serde(default)]
+    pub left: f32,
+    #[serde(default)]
+    pub right: f32,
+}
+
+impl CaptureInset {
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, v) in [("top", self.top), ("bottom", self.bottom), ("left", self.left), ("right", self.right)] {
+            if !(0.0..50.0).contains(&v) {
+                return Err(format!("Capture inset {} must be in [0, 50), got {}", name, v));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.top <= 0.0 && self.bottom <= 0.0 && self.left <= 0.0 && self.right <= 0.0
+    }
+
+    // 保留区域在原图中的 (x, y, w, h)
+    pub fn region(&self, width: u32, height: u32) -> (u32, u32, u32, u32) {
+        let px = |total: u32, pct: f32| (total as f32 * pct.clamp(0.0, 50.0) / 100.0).round() as u32;
+        let x = px(width, self.left).min(width.saturating_sub(1));
+        let y = px(height, self.top).min(height.saturating_sub(1));
+        let w = width.saturating_sub(x + px(width, self.right)).max(1);
+        let h = height.saturating_sub(y + px(height, self.bottom)).max(1);
+        (x, y, w, h)
+    }
+}
+
+// 会话按 (模型路径, 是否 GPU) 分别缓存，切换模型或 useGpu 时不重建已加载过的会话；
+// 会话生命周期与进程相同，模型数量很少，直接 leak 换取 'static 的锁
+static YOLO_SESSIONS: OnceLock<Mutex<HashMap<(PathBuf, bool), &'static Mutex<Session>>>> = OnceLock::new();
+// GPU 提供器注册失败后不再重试，之后的 GPU 请求直接使用 CPU 会话
+static YOLO_GPU_UNAVAILABLE: AtomicBool = AtomicBool::new(false);
+static LAST_DROPPED_DETECTIONS: AtomicUsize = AtomicUsize::new(0);
+
+// 裁剪区域过小（鼠标贴近屏幕边缘）时的错误前缀，前端据此给出具体提示而不是通用失败
+pub const CROP_TOO_SMALL_ERROR: &str = "CropTooSmall";
+
+// 统一检查裁剪尺寸，最小边长来自配置 min_crop_size
+fn check_crop_size(crop_w: u32, crop_h: u32) -> Result<(), String> {
+    let min_size = crate::load_state().min_crop_size;
+    if crop_w < min_size || crop_h < min_size {
+        return Err(format!("{}: 裁剪区域太小 ({}x{}, 最小 {}px)，鼠标可能靠近屏幕边缘或已移出窗口范围", CROP_TOO_SMALL_ERROR, crop_w, crop_h, min_size));
+    }
+    Ok(())
+}
+
+// 各平台的 GPU 执行提供器：Windows DirectML / macOS CoreML / 其他 CUDA；
+// error_on_failure 让注册失败直接报错，由调用方回退 CPU
+fn gpu_execution_provider() -> (&'static str, ExecutionProviderDispatch) {
     #[cfg(target_os = "windows")]
-    let builder = if actually_use_gpu {
-        builder
-            .with_execution_providers([DirectMLExecutionProvider::default().build()])
-            .map_err(|e| format!("DirectML执行提供者加载失败: {}. 请确保已安装GPU驱动和DirectML.dll在程序目录中。", e))?
-    } else {
-        builder
-    };
+    { ("DirectML", DirectMLExecutionProvider::default().build().error_on_failure()) }
+    #[cfg(target_os = "macos")]
+    { ("CoreML", CoreMLExecutionProvider::default().build().error_on_failure()) }
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    { ("CUDA", CUDAExecutionProvider::default().build().error_on_failure()) }
+}
+
+fn build_yolo_session(model_path: &PathBuf, use_gpu: bool) -> Result<Session, String> {
+    let mut builder = Session::builder()
+        .map_err(|e| format!("创建Session Builder失败: {}", e))?;
+
+    if use_gpu {
+        let (name, provider) = gpu_execution_provider();
+        builder = builder
+            .with_execution_providers([provider])
+            .map_err(|e| format!("{}执行提供者加载失败: {}. 请确保已安装GPU驱动及对应运行库。", name, e))?;
+    }
 
-    let session = builder
+    builder
         .with_optimization_level(GraphOptimizationLevel::Level3)
         .map_err(|e| format!("设置优化级别失败: {}", e))?
         .with_intra_threads(4)
         .map_err(|e| format!("设置线程数失败: {}", e))?
         .commit_from_file(model_path)
-        .map_err(|e| format!("加载ONNX模型失败: {}. 模型路径: {:?}", e, model_path))?;
+        .map_err(|e| format!("加载ONNX模型失败: {}. 模型路径: {:?}", e, model_path))
+}
 
-    if actually_use_gpu {
-        log_to_file("[YOLO] Session initialized successfully with DirectML");
-    } else {
-        log_to_file("[YOLO] Session initialized successfully with CPU");
+fn cached_yolo_session(model_path: &PathBuf, gpu: bool) -> Option<&'static Mutex<Session>> {
+    let sessions = YOLO_SESSIONS.get_or_init(|| Mutex::new(HashMap::new()));
+    sessions.lock().ok()?.get(&(model_path.clone(), gpu)).copied()
+}
+
+fn insert_yolo_session(model_path: &PathBuf, gpu: bool, session: Session) -> &'static Mutex<Session> {
+    let sessions = YOLO_SESSIONS.get_or_init(|| Mutex::new(HashMap::new()));
+    let mut sessions = sessions.lock().unwrap_or_else(|e| e.into_inner());
+    *sessions.entry((model_path.clone(), gpu)).or_insert_with(|| &*Box::leak(Box::new(Mutex::new(session))))
+}
+
+pub fn get_yolo_session(model_path: &PathBuf, use_gpu: bool) -> Result<impl std::ops::DerefMut<Target = Session>, String> {
+    if use_gpu && !YOLO_GPU_UNAVAILABLE.load(Ordering::Relaxed) {
+        if let Some(mutex) = cached_yolo_session(model_path, true) {
+            return mutex.lock().map_err(|e| e.to_string());
+        }
+        log_to_file(&format!("[YOLO] Initializing session with GPU execution provider for {:?}...", model_path));
+        match build_yolo_session(model_path, true) {
+            Ok(session) => {
+                log_to_file("[YOLO] Session initialized successfully with GPU");
+                return insert_yolo_session(model_path, true, session).lock().map_err(|e| e.to_string());
+            }
+            Err(e) => {
+                log_to_file(&format!("[YOLO] GPU session unavailable, falling back to CPU: {}", e));
+                YOLO_GPU_UNAVAILABLE.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+
+    if let Some(mutex) = cached_yolo_session(model_path, false) {
+        return mutex.lock().map_err(|e| e.to_string());
+    }
+    log_to_file(&format!("[YOLO] Initializing session with CPU execution provider for {:?}...", model_path));
+    let session = build_yolo_session(model_path, false)?;
+    log_to_file("[YOLO] Session initialized successfully with CPU");
+    insert_yolo_session(model_path, false, session).lock().map_err(|e| e.to_string())
+}
+
+pub const DEFAULT_YOLO_MODEL: &str = "best.onnx";
+
+fn yolo_models_dir(resources_path: &std::path::Path) -> PathBuf {
+    resources_path.join("resources").join("models")
+}
+
+// resources/models 下所有 .onnx 模型文件名（按名称排序）
+pub fn list_yolo_models(resources_path: &std::path::Path) -> Vec<String> {
+    let mut models: Vec<String> = std::fs::read_dir(yolo_models_dir(resources_path))
+        .map(|entries| {
+            entries.flatten()
+                .map(|e| e.path())
+                .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("onnx")))
+                .filter_map(|p| p.file_name().map(|n| n.to_string_lossy().into_owned()))
+                .collect()
+        })
+        .unwrap_or_default();
+    models.sort();
+    models
+}
+
+// 当前配置的 active_yolo_model 路径；文件不存在时回退到默认 best.onnx
+pub fn active_yolo_model_path(resources_path: &std::path::Path) -> PathBuf {
+    let dir = yolo_models_dir(resources_path);
+    let active = crate::load_state().active_yolo_model;
+    let path = dir.join(&active);
+    if active != DEFAULT_YOLO_MODEL && !path.is_file() {
+        log_to_file(&format!("[YOLO] Model {} not found, falling back to {}", active, DEFAULT_YOLO_MODEL));
+        return dir.join(DEFAULT_YOLO_MODEL);
+    }
+    path
+}
+
+// 配置了 capture_inset 时返回截图中保留区域的 (x, y, w, h)，全 0 时为 None
+fn capture_inset_region(width: u32, height: u32) -> Option<(u32, u32, u32, u32)> {
+    let inset = crate::load_state().capture_inset;
+    if inset.is_empty() {
+        return None;
     }
-    let _ = YOLO_SESSION.set(Mutex::new(session));
-    YOLO_SESSION.get().unwrap().lock().map_err(|e| e.to_string())
+    let region = inset.region(width, height);
+    log_to_file(&format!("Applying capture inset {:?}: region {:?} of {}x{}", inset, region, width, height));
+    Some(region)
 }
 
-pub fn run_yolo_inference(img: &DynamicImage, model_path: &PathBuf, use_gpu: bool) -> Result<Vec<YoloDetection>, String> {
+// 截图后的共用步骤：按 capture_inset 裁掉边缘，返回保留部分及其在原截图中的偏移
+pub fn apply_capture_inset(img: DynamicImage) -> (DynamicImage, u32, u32) {
+    let (w, h) = img.dimensions();
+    match capture_inset_region(w, h) {
+        Some((x, y, rw, rh)) => (img.crop_imm(x, y, rw, rh), x, y),
+        None => (img, 0, 0),
+    }
+}
+
+// 对整张游戏截图做 YOLO：先按配置 capture_inset 裁掉边缘，再把检测框平移回原图坐标，
+// 调用方（overlay 点击、裁剪识别）仍按原截图坐标使用结果
+pub fn run_yolo_on_capture(img: &DynamicImage, model_path: &PathBuf, use_gpu: bool, nms_strategy: NmsStrategy, conf_threshold: f32, iou_threshold: f32) -> Result<Vec<YoloDetection>, String> {
+    let (w, h) = img.dimensions();
+    let Some((rx, ry, rw, rh)) = capture_inset_region(w, h) else {
+        return run_yolo_inference(img, model_path, use_gpu, nms_strategy, conf_threshold, iou_threshold);
+    };
+    let cropped = img.crop_imm(rx, ry, rw, rh);
+    let mut detections = run_yolo_inference(&cropped, model_path, use_gpu, nms_strategy, conf_threshold, iou_threshold)?;
+    for d in detections.iter_mut() {
+        d.x1 += rx as i32;
+        d.x2 += rx as i32;
+        d.y1 += ry as i32;
+        d.y2 += ry as i32;
+    }
+    Ok(detections)
+}
+
+const YOLO_INPUT_SIZE: u32 = 640;
+// YOLO 训练时 letterbox 使用的灰边颜色
+const LETTERBOX_FILL: u8 = 114;
+
+// 保持宽高比缩放后居中填充到正方形输入时的变换参数，用于把模型坐标反算回原图
+#[derive(Debug, Clone, Copy)]
+pub struct Letterbox {
+    pub scale: f32,
+    pub pad_x: f32,
+    pub pad_y: f32,
+}
+
+impl Letterbox {
+    pub fn new(orig_w: u32, orig_h: u32, size: u32) -> Self {
+        let scale = (size as f32 / orig_w.max(1) as f32).min(size as f32 / orig_h.max(1) as f32);
+        let (new_w, new_h) = Self::scaled_dims(orig_w, orig_h, size, scale);
+        Self {
+            scale,
+            pad_x: ((size - new_w) / 2) as f32,
+            pad_y: ((size - new_h) / 2) as f32,
+        }
+    }
+
+    fn scaled_dims(orig_w: u32, orig_h: u32, size: u32, scale: f32) -> (u32, u32) {
+        (
+            ((orig_w as f32 * scale).round() as u32).clamp(1, size),
+            ((orig_h as f32 * scale).round() as u32).clamp(1, size),
+        )
+    }
+
+    // 模型输入坐标 -> 原图坐标（先去掉 padding，再除以统一 scale）
+    pub fn to_original(&self, x: f32, y: f32) -> (f32, f32) {
+        ((x - self.pad_x) / self.scale, (y - self.pad_y) / self.scale)
+    }
+
+    // 模型输出的 (xc, yc, w, h) -> 原图 (x1, y1, x2, y2)，落在灰边里的部分裁到原图范围内
+    pub fn box_to_original(&self, xc: f32, yc: f32, w: f32, h: f32, orig_w: u32, orig_h: u32) -> (i32, i32, i32, i32) {
+        let (x1, y1) = self.to_original(xc - w / 2.0, yc - h / 2.0);
+        let (x2, y2) = self.to_original(xc + w / 2.0, yc + h / 2.0);
+        (
+            x1.clamp(0.0, orig_w as f32) as i32,
+            y1.clamp(0.0, orig_h as f32) as i32,
+            x2.clamp(0.0, orig_w as f32) as i32,
+            y2.clamp(0.0, orig_h as f32) as i32,
+        )
+    }
+}
+
+// 把截图等比缩放并居中贴到 size x size 的灰底画布上，避免超宽屏/16:10 截图被拉伸变形
+pub fn letterbox_image(img: &DynamicImage, size: u32) -> (image::RgbImage, Letterbox) {
+    let (orig_w, orig_h) = img.dimensions();
+    let lb = Letterbox::new(orig_w, orig_h, size);
+    let (new_w, new_h) = Letterbox::scaled_dims(orig_w, orig_h, size, lb.scale);
+    let resized = img.resize_exact(new_w, new_h, FilterType::Lanczos3).to_rgb8();
+    let mut canvas = image::RgbImage::from_pixel(size, size, image::Rgb([LETTERBOX_FILL; 3]));
+    image::imageops::replace(&mut canvas, &resized, lb.pad_x as i64, lb.pad_y as i64);
+    (canvas, lb)
+}
+
+pub fn run_yolo_inference(img: &DynamicImage, model_path: &PathBuf, use_gpu: bool, nms_strategy: NmsStrategy, conf_threshold: f32, iou_threshold: f32) -> Result<Vec<YoloDetection>, String> {
     let mut session = get_yolo_session(model_path, use_gpu)?;
     let (orig_w, orig_h) = img.dimensions();
 
-    // 1. 预处理 (640x640)
-    let resized = img.resize_exact(640, 640, FilterType::Lanczos3);
-    let rgb_img = resized.to_rgb8();
+    // 1. 预处理：letterbox 到 640x640
+    let (rgb_img, letterbox) = letterbox_image(img, YOLO_INPUT_SIZE);
     
     let mut input_array = Array::zeros((1, 3, 640, 640));
     for (x, y, pixel) in rgb_img.enumerate_pixels() {
@@ -112,7 +393,6 @@ pub fn run_yolo_inference(img: &DynamicImage, model_path: &PathBuf, use_gpu: boo
     let num_anchors = shape[2] as usize;
 
     let mut candidates = Vec::new();
-    let conf_threshold = 0.25;
 
     for i in 0..num_anchors {
         let mut max_score = 0.0;
@@ -132,40 +412,135 @@ pub fn run_yolo_inference(img: &DynamicImage, model_path: &PathBuf, use_gpu: boo
             let w = data[2 * num_anchors + i];
             let h = data[3 * num_anchors + i];
 
-            let x1 = (xc - w / 2.0) * (orig_w as f32 / 640.0);
-            let y1 = (yc - h / 2.0) * (orig_h as f32 / 640.0);
-            let x2 = (xc + w / 2.0) * (orig_w as f32 / 640.0);
-            let y2 = (yc + h / 2.0) * (orig_h as f32 / 640.0);
+            let (x1, y1, x2, y2) = letterbox.box_to_original(xc, yc, w, h, orig_w, orig_h);
 
             candidates.push(YoloDetection {
-                x1: x1 as i32,
-                y1: y1 as i32,
-                x2: x2 as i32,
-                y2: y2 as i32,
+                x1,
+                y1,
+                x2,
+                y2,
                 confidence: max_score,
                 class_id,
             });
         }
     }
 
-    Ok(nms(candidates, 0.45))
+    let mut detections = nms(candidates, iou_threshold, nms_strategy);
+
+    // nms 结果已按置信度降序排列，直接截断即为 top-N
+    let max_detections = crate::load_state().max_detections;
+    let dropped = if max_detections > 0 && detections.len() > max_detections {
+        let dropped = detections.len() - max_detections;
+        detections.truncate(max_detections);
+        println!("[YOLO] Kept top {} detections, dropped {}", max_detections, dropped);
+        dropped
+    } else {
+        0
+    };
+    LAST_DROPPED_DETECTIONS.store(dropped, Ordering::Relaxed);
+
+    let mut per_class: Vec<(String, usize)> = Vec::new();
+    for d in &detections {
+        let name = yolo_class_name(d.class_id);
+        match per_class.iter_mut().find(|(n, _)| *n == name) {
+            Some(entry) => entry.1 += 1,
+            None => per_class.push((name, 1)),
+        }
+    }
+    log_to_file(&format!("[YOLO] {} detections by class: {:?}", detections.len(), per_class));
+
+    Ok(detections)
+}
+
+// 最近一次推理因 max_detections 被丢弃的检测框数量
+pub fn last_dropped_detections() -> usize {
+    LAST_DROPPED_DETECTIONS.load(Ordering::Relaxed)
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct YoloModelValidation {
+    pub path: String,
+    pub output_shape: Vec<i64>,
+    pub num_classes: usize,
+    pub expected_classes: usize,
+    pub matches: bool,
 }
 
-fn nms(mut detections: Vec<YoloDetection>, iou_threshold: f32) -> Vec<YoloDetection> {
+// 校验自定义 ONNX 模型：独立加载（不替换当前使用的会话），用全零输入跑一次推理，
+// 由输出形状 [1, 4 + num_classes, anchors] 推断类别数，并与 yolo_classes.json 的类别数比较
+pub fn validate_yolo_model(model_path: &PathBuf) -> Result<YoloModelValidation, String> {
+    if !model_path.exists() {
+        return Err(format!("模型文件不存在: {:?}", model_path));
+    }
+
+    let mut session = Session::builder()
+        .map_err(|e| format!("创建Session Builder失败: {}", e))?
+        .with_optimization_level(GraphOptimizationLevel::Level1)
+        .map_err(|e| format!("设置优化级别失败: {}", e))?
+        .commit_from_file(model_path)
+        .map_err(|e| format!("加载ONNX模型失败: {}. 模型路径: {:?}", e, model_path))?;
+
+    let input_shape = [1, 3, 640, 640];
+    let input_tensor = Value::from_array((input_shape, vec![0.0f32; 3 * 640 * 640])).map_err(|e: ort::Error| e.to_string())?;
+    let outputs = session.run(vec![("images", input_tensor)])
+        .map_err(|e: ort::Error| format!("模型推理失败（输入需为 images [1,3,640,640]）: {}", e))?;
+    let output_value = outputs.get("output0").ok_or("模型缺少 output0 输出")?;
+    let (shape, _) = output_value.try_extract_tensor::<f32>().map_err(|e: ort::Error| e.to_string())?;
+
+    let output_shape: Vec<i64> = shape.iter().copied().collect();
+    if output_shape.len() != 3 || output_shape[1] <= 4 {
+        return Err(format!("无法识别的输出形状 {:?}，期望 [1, 4 + num_classes, anchors]", output_shape));
+    }
+
+    let num_classes = (output_shape[1] - 4) as usize;
+    let expected_classes = yolo_classes().read().unwrap().len();
+    println!("[YOLO] Validated model {:?}: output {:?}, {} classes (expected {})", model_path, output_shape, num_classes, expected_classes);
+
+    Ok(YoloModelValidation {
+        path: model_path.to_string_lossy().to_string(),
+        output_shape,
+        num_classes,
+        expected_classes,
+        matches: num_classes == expected_classes,
+    })
+}
+
+fn sort_by_confidence(detections: &mut [YoloDetection]) {
     detections.sort_by(|a, b| b.confidence.partial_cmp(&a.confidence).unwrap_or(std::cmp::Ordering::Equal));
+}
+
+// 贪心 NMS：按置信度从高到低，抑制与已保留框 IoU 超过阈值的框
+fn suppress_overlaps(mut detections: Vec<YoloDetection>, iou_threshold: f32) -> Vec<YoloDetection> {
+    sort_by_confidence(&mut detections);
     let mut result = Vec::new();
 
     while !detections.is_empty() {
         let best = detections.remove(0);
-        result.push(best.clone());
-        detections.retain(|d| {
-            calculate_iou(&best, d) < iou_threshold
-        });
+        detections.retain(|d| calculate_iou(&best, d) < iou_threshold);
+        result.push(best);
     }
 
     result
 }
 
+// 结果按置信度降序；PerClass 时各类别独立抑制，跨类别的重叠框全部保留
+pub fn nms(detections: Vec<YoloDetection>, iou_threshold: f32, strategy: NmsStrategy) -> Vec<YoloDetection> {
+    match strategy {
+        NmsStrategy::ClassAgnostic => suppress_overlaps(detections, iou_threshold),
+        NmsStrategy::PerClass => {
+            let mut groups: std::collections::BTreeMap<usize, Vec<YoloDetection>> = std::collections::BTreeMap::new();
+            for d in detections {
+                groups.entry(d.class_id).or_default().push(d);
+            }
+            let mut result: Vec<YoloDetection> = groups.into_values()
+                .flat_map(|group| suppress_overlaps(group, iou_threshold))
+                .collect();
+            sort_by_confidence(&mut result);
+            result
+        }
+    }
+}
+
 fn calculate_iou(a: &YoloDetection, b: &YoloDetection) -> f32 {
     let x1 = a.x1.max(b.x1);
     let y1 = a.y1.max(b.y1);
@@ -183,26 +558,48 @@ fn calculate_iou(a: &YoloDetection, b: &YoloDetection) -> f32 {
     intersection_area as f32 / (area_a + area_b - intersection_area) as f32
 }
 
+// 与上一次的检测结果按 IoU 合并：同类别且 IoU >= iou_threshold 的框沿用旧坐标（仅更新置信度），
+// 新出现的框加入，未再出现的旧框移除。连续扫描时 overlay 的框不会整体闪烁重绘。
+// 返回 (合并结果, 保留数, 新增数, 移除数)
+pub fn merge_detections(previous: &[YoloDetection], new: Vec<YoloDetection>, iou_threshold: f32) -> (Vec<YoloDetection>, usize, usize, usize) {
+    let mut used = vec![false; previous.len()];
+    let mut merged = Vec::with_capacity(new.len());
+    let mut kept = 0;
+
+    for det in new {
+        let best = previous.iter().enumerate()
+            .filter(|(i, p)| !used[*i] && p.class_id == det.class_id)
+            .map(|(i, p)| (i, calculate_iou(p, &det)))
+            .filter(|(_, iou)| *iou >= iou_threshold)
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+
+        match best {
+            Some((i, _)) => {
+                used[i] = true;
+                kept += 1;
+                merged.push(YoloDetection { confidence: det.confidence, ..previous[i].clone() });
+            }
+            None => merged.push(det),
+        }
+    }
+
+    let added = merged.len() - kept;
+    let removed = previous.len() - kept;
+    (merged, kept, added, removed)
+}
+
 pub fn recognize_monsters_yolo(app: &tauri::AppHandle) -> Result<Vec<String>, String> {
-    use xcap::Window;
     use std::time::Instant;
 
     let start_total = Instant::now();
     let resources_path = app.path().resource_dir().map_err(|e| e.to_string())?;
-    let model_path = resources_path.join("resources").join("models").join("best.onnx");
+    let model_path = active_yolo_model_path(&resources_path);
 
     // 截图逻辑
-    let windows = Window::all().map_err(|e| e.to_string())?;
-    let bazaar_window = windows.into_iter().find(|w| {
-        let title = w.title().to_lowercase();
-        let app_name = w.app_name().to_lowercase();
-        let is_bazaar = title.contains("the bazaar") || app_name.contains("the bazaar") || 
-                        title.contains("thebazaar") || app_name.contains("thebazaar");
-        is_bazaar && !title.contains("bazaarhelper")
-    });
+    let bazaar_window = find_game_window(None)?;
 
     let screenshot = if let Some(window) = bazaar_window {
-        window.capture_image().map_err(|e| e.to_string())?
+        capture_game_window(&window)?.0
     } else {
         use xcap::Monitor;
         let monitors = Monitor::all().map_err(|e| e.to_string())?;
@@ -211,17 +608,17 @@ pub fn recognize_monsters_yolo(app: &tauri::AppHandle) -> Result<Vec<String>, St
     };
 
     let img = DynamicImage::ImageRgba8(screenshot);
-    let detections = run_yolo_inference(&img, &model_path, true)?; // 默认使用GPU
+    let state = crate::load_state();
+    let detections = run_yolo_on_capture(&img, &model_path, true, state.nms_strategy, state.yolo_conf_threshold, state.yolo_iou_threshold)?; // 默认使用GPU
     
     let mut identified_monsters = Vec::new();
 
-    // 1. 分离 Detected Objects
-    // names: ['day', 'event', 'item', 'monstericon', 'randomicon', 'shopicon', 'skill']
-    // event_id = 1, monstericon_id = 3
-    let events: Vec<&YoloDetection> = detections.iter().filter(|d| d.class_id == 1).collect();
-    let monster_icons: Vec<&YoloDetection> = detections.iter().filter(|d| d.class_id == 3).collect();
+    // 1. 分离 Detected Objects（类别 id 由 yolo_classes.json 决定）
+    let events: Vec<&YoloDetection> = detections.iter().filter(|d| d.is_class(CLASS_EVENT)).collect();
+    let monster_icons: Vec<&YoloDetection> = detections.iter().filter(|d| d.is_class(CLASS_MONSTER_ICON)).collect();
 
-    // 2. 判定逻辑: Event + MonsterIcon Overlap > 50%
+    // 2. 判定逻辑: Event + MonsterIcon Overlap >= monster_overlap_ratio (默认 50%)
+    let overlap_ratio = crate::load_state().monster_overlap_ratio;
     for event in events {
         let mut is_monster_event = false;
         
@@ -229,7 +626,7 @@ pub fn recognize_monsters_yolo(app: &tauri::AppHandle) -> Result<Vec<String>, St
             let overlay_area = intersection_area_val(event, icon);
             let icon_area = ((icon.x2 - icon.x1) * (icon.y2 - icon.y1)) as f32;
             
-            if icon_area > 0.0 && (overlay_area / icon_area) >= 0.5 {
+            if icon_area > 0.0 && (overlay_area / icon_area) >= overlap_ratio {
                  is_monster_event = true;
                  break;
             }
@@ -264,12 +661,109 @@ fn intersection_area_val(a: &YoloDetection, b: &YoloDetection) -> f32 {
     (x2 - x1).max(0) as f32 * (y2 - y1).max(0) as f32
 }
 
+// dHash：缩放到 9x8 灰度图，逐行比较相邻像素得到 64 位指纹，汉明距离越小越相似
+fn dhash(img: &DynamicImage) -> u64 {
+    let small = img.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash
+}
+
+// 全库 ORB 匹配前的哈希预筛选（配置 phash_prefilter / phash_top_k）：
+// 只保留与截图 dHash 汉明距离最小的 K 个模板，未开启或模板数不超过 K 时原样返回
+fn prefilter_by_phash<'a>(scene: &DynamicImage, templates: Vec<&'a TemplateCache>) -> Vec<&'a TemplateCache> {
+    let state = crate::load_state();
+    if !state.phash_prefilter || state.phash_top_k == 0 || templates.len() <= state.phash_top_k {
+        return templates;
+    }
+
+    let scene_hash = dhash(scene);
+    let mut ranked: Vec<(u32, &TemplateCache)> = templates.into_iter()
+        .map(|t| ((t.phash ^ scene_hash).count_ones(), t))
+        .collect();
+    ranked.sort_by_key(|(distance, _)| *distance);
+    ranked.truncate(state.phash_top_k);
+    println!("[pHash] Prefiltered to {} templates (distance {}..{})",
+             ranked.len(), ranked.first().map(|r| r.0).unwrap_or(0), ranked.last().map(|r| r.0).unwrap_or(0));
+    ranked.into_iter().map(|(_, t)| t).collect()
+}
+
+const HSV_BINS: usize = 8;
+// 直方图预筛选保留的模板比例及最少保留数，其余再做 ORB 精匹配
+const HIST_KEEP_RATIO: f32 = 0.2;
+const HIST_MIN_KEEP: usize = 50;
+
+// 降采样到 64x64 后统计 8x8x8 的 HSV 直方图，归一化为概率分布
+fn hsv_histogram(img: &DynamicImage) -> Vec<f32> {
+    let small = img.resize_exact(64, 64, FilterType::Triangle).to_rgb8();
+    let mut hist = vec![0f32; HSV_BINS * HSV_BINS * HSV_BINS];
+    for p in small.pixels() {
+        let (r, g, b) = (p[0] as f32 / 255.0, p[1] as f32 / 255.0, p[2] as f32 / 255.0);
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let delta = max - min;
+        let h = if delta <= 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * (((g - b) / delta).rem_euclid(6.0))
+        } else if max == g {
+            60.0 * ((b - r) / delta + 2.0)
+        } else {
+            60.0 * ((r - g) / delta + 4.0)
+        };
+        let s = if max <= 0.0 { 0.0 } else { delta / max };
+        let bin = |v: f32| ((v * HSV_BINS as f32) as usize).min(HSV_BINS - 1);
+        let idx = bin(h / 360.0) * HSV_BINS * HSV_BINS + bin(s) * HSV_BINS + bin(max);
+        hist[idx] += 1.0;
+    }
+    let total: f32 = hist.iter().sum();
+    if total > 0.0 {
+        hist.iter_mut().for_each(|v| *v /= total);
+    }
+    hist
+}
+
+// 巴氏系数：两个归一化直方图的相似度，1 为完全相同
+fn bhattacharyya(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| (x * y).sqrt()).sum()
+}
+
+// 卡牌识别前的颜色直方图预筛选：按巴氏系数保留最相似的 20%（至少 HIST_MIN_KEEP 个），
+// 没有直方图的模板（旧缓存）总是保留
+fn prefilter_by_histogram<'a>(scene: &DynamicImage, templates: &'a [TemplateCache]) -> Vec<&'a TemplateCache> {
+    let keep = ((templates.len() as f32 * HIST_KEEP_RATIO).ceil() as usize).max(HIST_MIN_KEEP);
+    if templates.len() <= keep {
+        return templates.iter().collect();
+    }
+
+    let scene_hist = hsv_histogram(scene);
+    let (with_hist, mut kept): (Vec<&TemplateCache>, Vec<&TemplateCache>) = templates.iter().partition(|t| !t.hsv_hist.is_empty());
+    let mut ranked: Vec<(f32, &TemplateCache)> = with_hist.into_iter()
+        .map(|t| (bhattacharyya(&scene_hist, &t.hsv_hist), t))
+        .collect();
+    ranked.sort_by(|a, b| b.0.total_cmp(&a.0));
+    ranked.truncate(keep);
+    println!("[Histogram] Prefiltered to {} of {} card templates (similarity {:.3}..{:.3})",
+             ranked.len() + kept.len(), templates.len(),
+             ranked.first().map(|r| r.0).unwrap_or(0.0), ranked.last().map(|r| r.0).unwrap_or(0.0));
+    kept.extend(ranked.into_iter().map(|(_, t)| t));
+    kept
+}
+
 fn match_single_image_to_db(img: &DynamicImage, day_filter: Option<String>) -> Option<String> {
-    let full_cache = TEMPLATE_CACHE.get()?;
+    let full_cache = monster_templates()?;
     let cache: Vec<&TemplateCache> = if let Some(ref target_day) = day_filter {
-        full_cache.iter().filter(|t| t.day == *target_day).collect()
+        let target = crate::canonical_day_label(target_day);
+        full_cache.iter().filter(|t| crate::canonical_day_label(&t.day) == target).collect()
     } else {
-        full_cache.iter().collect()
+        prefilter_by_phash(img, full_cache.iter().collect())
     };
 
     // 预处理图像：转换为 OpenCV Mat
@@ -280,13 +774,10 @@ fn match_single_image_to_db(img: &DynamicImage, day_filter: Option<String>) -> O
         Ok(mat)
     })();
 
-    let gray_img = gray_img_res.ok()?;
+    let gray_img = preprocess_gray(gray_img_res.ok()?).ok()?;
 
     // 提取特征点
-    let mut orb = ORB::create(1000, 1.2f32, 8, 31, 0, 2, opencv::features2d::ORB_ScoreType::HARRIS_SCORE, 31, 20).ok()?;
-    let mut keypoints = Vector::<KeyPoint>::new();
-    let mut descriptors = Mat::default();
-    orb.detect_and_compute(&gray_img, &Mat::default(), &mut keypoints, &mut descriptors, false).ok()?;
+    let (_, descriptors) = feature_extractor(MONSTER_TEMPLATE_FEATURES).detect_and_compute(&gray_img).ok()?;
 
     if descriptors.empty() { return None; }
 
@@ -343,6 +834,9 @@ pub struct LoadingProgress {
     pub total: usize,
     pub is_complete: bool,
     pub current_name: String,
+    // 已发布到 TEMPLATE_CACHE、可用于识别的模板数（从图片构建时分批发布，小于等于 loaded）
+    #[serde(default)]
+    pub published: usize,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -356,6 +850,48 @@ struct TemplateCache {
     sample_png: Vec<u8>,
     sample_w: u32,
     sample_h: u32,
+    phash: u64, // 模板图的 dHash，用于 prefilter_by_phash
+    hsv_hist: Vec<f32>, // 卡牌模板的 HSV 颜色直方图（归一化），用于 prefilter_by_histogram；怪物模板为空
+    source: Option<TemplateSource>, // 怪物模板源图片的指纹，用于增量重建；卡牌模板为 None
+}
+
+impl TemplateCache {
+    // 不带 sample_png 的副本：识别只用特征点，源图片字节只在写回磁盘缓存时需要
+    fn without_sample(&self) -> TemplateCache {
+        TemplateCache {
+            name: self.name.clone(),
+            day: self.day.clone(),
+            keypoints: self.keypoints.clone(),
+            descriptors: self.descriptors.clone(),
+            descriptor_rows: self.descriptor_rows,
+            descriptor_cols: self.descriptor_cols,
+            sample_png: Vec::new(),
+            sample_w: self.sample_w,
+            sample_h: self.sample_h,
+            phash: self.phash,
+            hsv_hist: self.hsv_hist.clone(),
+            source: self.source,
+        }
+    }
+}
+
+// 模板源图片的修改时间 / 大小 / 内容哈希
+#[derive(Clone, Copy, Serialize, Deserialize)]
+struct TemplateSource {
+    mtime: u64,
+    len: u64,
+    hash: u64,
+}
+
+fn file_stamp(path: &PathBuf) -> Option<(u64, u64)> {
+    let meta = std::fs::metadata(path).ok()?;
+    let mtime = meta.modified().ok()?.duration_since(std::time::UNIX_EPOCH).ok()?.as_millis() as u64;
+    Some((mtime, meta.len()))
+}
+
+// FNV-1a：哈希值要写进缓存文件，需要跨编译器版本稳定，不能用 DefaultHasher
+fn content_hash(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325u64, |h, b| (h ^ *b as u64).wrapping_mul(0x100000001b3))
 }
 
 #[derive(Deserialize)]
@@ -365,9 +901,613 @@ struct MonsterEntry {
     name_zh: Option<String>,
 }
 
-static TEMPLATE_CACHE: OnceLock<Vec<TemplateCache>> = OnceLock::new();
-static CARD_TEMPLATE_CACHE: OnceLock<Vec<TemplateCache>> = OnceLock::new();
+// 模板缓存可整体替换（导入/重建），读取方拿到 Arc 快照，匹配过程中不持有锁
+static TEMPLATE_CACHE: RwLock<Option<Arc<Vec<TemplateCache>>>> = RwLock::new(None);
+static CARD_TEMPLATE_CACHE: RwLock<Option<Arc<Vec<TemplateCache>>>> = RwLock::new(None);
+
+fn monster_templates() -> Option<Arc<Vec<TemplateCache>>> {
+    TEMPLATE_CACHE.read().ok()?.clone()
+}
+
+fn card_templates() -> Option<Arc<Vec<TemplateCache>>> {
+    CARD_TEMPLATE_CACHE.read().ok()?.clone()
+}
+
+fn set_monster_templates(templates: Vec<TemplateCache>) {
+    if let Ok(mut cache) = TEMPLATE_CACHE.write() {
+        *cache = Some(Arc::new(templates));
+    }
+}
+
+// 构建过程中的部分发布：每批都要拷贝一次已完成的模板，去掉 sample_png 避免反复深拷贝整份图片数据
+fn publish_partial_monster_templates(templates: &[TemplateCache]) {
+    set_monster_templates(templates.iter().map(TemplateCache::without_sample).collect());
+}
+
+fn set_card_templates(templates: Vec<TemplateCache>) {
+    if let Ok(mut cache) = CARD_TEMPLATE_CACHE.write() {
+        *cache = Some(Arc::new(templates));
+    }
+}
+
+// 下一次 preload_templates_async 不读取安装包自带的缓存（强制从图片重建时使用）
+static SKIP_BUNDLED_MONSTER_CACHE: AtomicBool = AtomicBool::new(false);
+
+// 删除 AppData 下的怪物特征缓存文件并清空内存中的模板，之后需重新调用 preload_templates_async。
+// 资源目录里的预打包缓存不删除（安装目录可能只读），重建时跳过它即可
+pub fn reset_monster_templates(cache_dir: &PathBuf) -> Result<(), String> {
+    let path = cache_dir.join(feature_cache_name("monster_features_opencv_v2.bin"));
+    if path.exists() {
+        std::fs::remove_file(&path).map_err(|e| format!("Failed to remove {:?}: {}", path, e))?;
+        log_to_file(&format!("Removed monster cache file {:?}", path));
+    }
+    SKIP_BUNDLED_MONSTER_CACHE.store(true, Ordering::SeqCst);
+    if let Ok(mut cache) = TEMPLATE_CACHE.write() {
+        *cache = None;
+    }
+    Ok(())
+}
+
+pub fn monster_template_count() -> usize {
+    monster_templates().map_or(0, |t| t.len())
+}
+
+// 清空所有内存中的识别结果缓存（不影响模板缓存），返回清除的条目数。
+// 新增的按图像哈希缓存的识别结果都应在这里一并清理
+pub fn clear_recognition_caches() -> usize {
+    MOUSE_SCAN_CACHE.lock().map(|mut c| c.take().is_some() as usize).unwrap_or(0)
+}
+
+// 鼠标指向识别的短时结果缓存：按住热键时节流间隔内会反复扫描同一画面，
+// 鼠标几乎没动且裁剪区域画面没变时直接复用上次结果，跳过全库 ORB 匹配
+struct MouseScanCache {
+    at: std::time::Instant,
+    mouse: (i32, i32),
+    signature: Vec<u8>,
+    candidates: Vec<(String, usize, f32)>,
+}
+
+static MOUSE_SCAN_CACHE: Mutex<Option<MouseScanCache>> = Mutex::new(None);
+// 鼠标移动超过该像素数视为换了目标
+const MOUSE_SCAN_CACHE_MOVE_PX: i32 = 8;
+// 8x8 灰度缩略图的平均绝对差（0-255）超过该值视为画面已变化
+const MOUSE_SCAN_CACHE_MAX_DIFF: f32 = 6.0;
+
+// 快速画面指纹：裁剪区域缩到 8x8 灰度，只用于判断画面是否明显变化
+fn region_signature(img: &DynamicImage) -> Vec<u8> {
+    img.resize_exact(8, 8, FilterType::Triangle).to_luma8().into_raw()
+}
+
+fn signature_diff(a: &[u8], b: &[u8]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return f32::MAX;
+    }
+    a.iter().zip(b).map(|(&x, &y)| (x as f32 - y as f32).abs()).sum::<f32>() / a.len() as f32
+}
+
+fn cached_mouse_scan(mouse: (i32, i32), signature: &[u8]) -> Option<Vec<(String, usize, f32)>> {
+    let ttl = crate::load_state().recognition_cache_ms;
+    if ttl == 0 {
+        return None;
+    }
+    let cache = MOUSE_SCAN_CACHE.lock().ok()?;
+    let entry = cache.as_ref()?;
+    let moved = (entry.mouse.0 - mouse.0).abs().max((entry.mouse.1 - mouse.1).abs());
+    if entry.at.elapsed() > std::time::Duration::from_millis(ttl)
+        || moved > MOUSE_SCAN_CACHE_MOVE_PX
+        || signature_diff(&entry.signature, signature) > MOUSE_SCAN_CACHE_MAX_DIFF
+    {
+        return None;
+    }
+    Some(entry.candidates.clone())
+}
+
+// 导出文件格式：bincode 序列化的 (怪物模板缓存, 卡牌模板缓存)，两部分各自带指纹
+pub fn export_template_cache(path: &PathBuf) -> Result<(usize, usize), String> {
+    let monsters = monster_templates().unwrap_or_default();
+    let cards = card_templates().unwrap_or_default();
+    if monsters.is_empty() && cards.is_empty() {
+        return Err("Templates not loaded".into());
+    }
+
+    let data = bincode::serialize(&(
+        encode_template_cache(&monsters, MONSTER_TEMPLATE_FEATURES)?,
+        encode_template_cache(&cards, CARD_TEMPLATE_FEATURES)?,
+    )).map_err(|e| e.to_string())?;
+    std::fs::write(path, data).map_err(|e| format!("Failed to write {:?}: {}", path, e))?;
+    log_to_file(&format!("Exported {} monster / {} card templates to {:?}", monsters.len(), cards.len(), path));
+    Ok((monsters.len(), cards.len()))
+}
+
+pub fn import_template_cache(path: &PathBuf) -> Result<(usize, usize), String> {
+    let data = std::fs::read(path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+    let (monster_data, card_data) = bincode::deserialize::<(Vec<u8>, Vec<u8>)>(&data)
+        .map_err(|e| format!("Invalid template cache file: {}", e))?;
+    let monsters = decode_template_cache::<TemplateCache>(&monster_data, MONSTER_TEMPLATE_FEATURES)?;
+    let cards = decode_template_cache::<TemplateCache>(&card_data, CARD_TEMPLATE_FEATURES)?;
+    let counts = (monsters.len(), cards.len());
+
+    // 空的部分不覆盖当前缓存
+    if !monsters.is_empty() { set_monster_templates(monsters); }
+    if !cards.is_empty() { set_card_templates(cards); }
+    log_to_file(&format!("Imported {} monster / {} card templates from {:?}", counts.0, counts.1, path));
+    Ok(counts)
+}
+
+// 模板特征点数量分布，用于排查识别不稳定：特征点很少的模板几乎不可能匹配成功
+const KEYPOINT_BUCKETS: [(i32, i32, &str); 7] = [
+    (0, 0, "0"),
+    (1, 19, "1-19"),
+    (20, 49, "20-49"),
+    (50, 99, "50-99"),
+    (100, 199, "100-199"),
+    (200, 499, "200-499"),
+    (500, i32::MAX, "500+"),
+];
+// 低于该特征点数的模板列入 low_keypoint_templates
+const LOW_KEYPOINT_THRESHOLD: i32 = 20;
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct TemplateSetStats {
+    pub count: usize,
+    pub zero_keypoints: usize,
+    pub min_keypoints: i32,
+    pub max_keypoints: i32,
+    pub avg_keypoints: f32,
+    pub histogram: Vec<(String, usize)>,
+    pub low_keypoint_templates: Vec<String>,
+    pub memory_bytes: usize,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TemplateStats {
+    pub monsters: TemplateSetStats,
+    pub cards: TemplateSetStats,
+    pub events: TemplateSetStats,
+    pub total_memory_bytes: usize,
+}
+
+// entries: (名称, descriptor_rows, 占用字节数)
+fn summarize_templates<'a>(entries: impl Iterator<Item = (&'a str, i32, usize)>) -> TemplateSetStats {
+    let mut stats = TemplateSetStats {
+        histogram: KEYPOINT_BUCKETS.iter().map(|(_, _, label)| (label.to_string(), 0)).collect(),
+        min_keypoints: i32::MAX,
+        ..Default::default()
+    };
+    let mut total_rows: i64 = 0;
+
+    for (name, rows, bytes) in entries {
+        stats.count += 1;
+        stats.memory_bytes += bytes;
+        total_rows += rows as i64;
+        stats.min_keypoints = stats.min_keypoints.min(rows);
+        stats.max_keypoints = stats.max_keypoints.max(rows);
+        if rows == 0 { stats.zero_keypoints += 1; }
+        if rows < LOW_KEYPOINT_THRESHOLD { stats.low_keypoint_templates.push(name.to_string()); }
+        if let Some(i) = KEYPOINT_BUCKETS.iter().position(|&(lo, hi, _)| rows >= lo && rows <= hi) {
+            stats.histogram[i].1 += 1;
+        }
+    }
+
+    if stats.count == 0 {
+        stats.min_keypoints = 0;
+    } else {
+        stats.avg_keypoints = total_rows as f32 / stats.count as f32;
+    }
+    stats
+}
+
+fn template_memory(t: &TemplateCache) -> usize {
+    std::mem::size_of::<TemplateCache>() + t.name.len() + t.day.len()
+        + t.keypoints.len() * std::mem::size_of::<(f32, f32)>()
+        + t.descriptors.len() + t.sample_png.len()
+}
+
+pub fn get_template_stats() -> TemplateStats {
+    let monsters = monster_templates().unwrap_or_default();
+    let cards = card_templates().unwrap_or_default();
+    let events: &[EventTemplateCache] = EVENT_TEMPLATE_CACHE.get().map(|v| v.as_slice()).unwrap_or(&[]);
+
+    let monsters = summarize_templates(monsters.iter().map(|t| (t.name.as_str(), t.descriptor_rows, template_memory(t))));
+    let cards = summarize_templates(cards.iter().map(|t| (t.name.as_str(), t.descriptor_rows, template_memory(t))));
+    let events = summarize_templates(events.iter().map(|t| {
+        let bytes = std::mem::size_of::<EventTemplateCache>() + t.id.len() + t.name.len() + t.descriptors.len();
+        (t.name.as_str(), t.descriptor_rows, bytes)
+    }));
+
+    let total_memory_bytes = monsters.memory_bytes + cards.memory_bytes + events.memory_bytes;
+    TemplateStats { monsters, cards, events, total_memory_bytes }
+}
 static LOADING_PROGRESS: OnceLock<Arc<Mutex<LoadingProgress>>> = OnceLock::new();
+// 从图片构建怪物模板时每批的数量，每批完成后发布一次部分缓存
+const TEMPLATE_PUBLISH_BATCH: usize = 50;
+
+// ORB 前的 CLAHE 对比度增强（配置 enhance_contrast，启动时设置一次，模板和截图必须一致）
+static ENHANCE_CONTRAST: AtomicBool = AtomicBool::new(false);
+
+pub fn set_enhance_contrast(enabled: bool) {
+    ENHANCE_CONTRAST.store(enabled, Ordering::Relaxed);
+}
+
+// 开启对比度增强或使用非 ORB 算法时模板需要重新生成，因此使用独立的缓存文件（预打包缓存不适用）
+fn feature_cache_name(base: &str) -> String {
+    let mut name = base.to_string();
+    if feature_algorithm() == FeatureAlgorithm::Akaze {
+        name = name.replace(".bin", "_akaze.bin");
+    }
+    if ENHANCE_CONTRAST.load(Ordering::Relaxed) {
+        name = name.replace(".bin", "_clahe.bin");
+    }
+    name
+}
+
+fn apply_clahe(gray: &Mat) -> Result<Mat, opencv::Error> {
+    let mut clahe = opencv::imgproc::create_clahe(2.0, opencv::core::Size::new(8, 8))?;
+    let mut enhanced = Mat::default();
+    clahe.apply(gray, &mut enhanced)?;
+    Ok(enhanced)
+}
+
+// 灰度图预处理：暗场景下提升局部对比度，增加 ORB 特征点数量
+fn preprocess_gray(gray: Mat) -> Result<Mat, opencv::Error> {
+    if !ENHANCE_CONTRAST.load(Ordering::Relaxed) || gray.empty() {
+        return Ok(gray);
+    }
+    apply_clahe(&gray)
+}
+
+// ORB 特征点评分方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrbScoreType {
+    #[default]
+    Harris,
+    Fast,
+}
+
+// ORB 参数（配置 orb_params，启动时设置一次）：模板生成和截图匹配必须使用同一组参数，
+// 修改任何一项都会改变缓存指纹，旧缓存自动失效重建
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct OrbParams {
+    pub scale_factor: f32,
+    pub n_levels: i32,
+    pub edge_threshold: i32,
+    pub wta_k: i32,
+    pub score_type: OrbScoreType,
+    pub patch_size: i32,
+    pub fast_threshold: i32,
+}
+
+impl Default for OrbParams {
+    fn default() -> Self {
+        Self {
+            scale_factor: 1.2,
+            n_levels: 8,
+            edge_threshold: 31,
+            wta_k: 2,
+            score_type: OrbScoreType::Harris,
+            patch_size: 31,
+            fast_threshold: 20,
+        }
+    }
+}
+
+impl OrbParams {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.scale_factor > 1.0) {
+            return Err(format!("ORB scale factor must be greater than 1, got {}", self.scale_factor));
+        }
+        if self.n_levels < 1 {
+            return Err(format!("ORB level count must be at least 1, got {}", self.n_levels));
+        }
+        if !(2..=4).contains(&self.wta_k) {
+            return Err(format!("ORB WTA_K must be 2, 3 or 4, got {}", self.wta_k));
+        }
+        if self.patch_size < 2 {
+            return Err(format!("ORB patch size must be at least 2, got {}", self.patch_size));
+        }
+        if self.edge_threshold < 0 || self.fast_threshold < 0 {
+            return Err("ORB edge / FAST thresholds must not be negative".into());
+        }
+        Ok(())
+    }
+}
+
+static ORB_PARAMS: RwLock<Option<OrbParams>> = RwLock::new(None);
+
+pub fn set_orb_params(params: OrbParams) {
+    if let Ok(mut p) = ORB_PARAMS.write() {
+        *p = Some(params);
+    }
+}
+
+fn orb_params() -> OrbParams {
+    ORB_PARAMS.read().ok().and_then(|p| *p).unwrap_or_default()
+}
+
+// 各类模板的特征点数量
+const MONSTER_TEMPLATE_FEATURES: i32 = 1000;
+const CARD_TEMPLATE_FEATURES: i32 = 300; // 用户要求特征点少一些, 用 300
+const EVENT_TEMPLATE_FEATURES: i32 = 1000; // 从500提升到1000，提取更多特征点
+
+fn create_orb(n_features: i32) -> opencv::Result<opencv::core::Ptr<ORB>> {
+    let p = orb_params();
+    let score_type = match p.score_type {
+        OrbScoreType::Harris => opencv::features2d::ORB_ScoreType::HARRIS_SCORE,
+        OrbScoreType::Fast => opencv::features2d::ORB_ScoreType::FAST_SCORE,
+    };
+    ORB::create(n_features, p.scale_factor, p.n_levels, p.edge_threshold, 0, p.wta_k,
+        score_type, p.patch_size, p.fast_threshold)
+}
+
+// 特征提取算法（配置 feature_algorithm，启动时设置一次）。模板与截图必须使用同一种算法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeatureAlgorithm {
+    #[default]
+    Orb,
+    Akaze,
+}
+
+impl FeatureAlgorithm {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "orb" => Ok(Self::Orb),
+            "akaze" => Ok(Self::Akaze),
+            other => Err(format!("Unknown feature algorithm: {} (expected orb or akaze)", other)),
+        }
+    }
+}
+
+static FEATURE_ALGORITHM: RwLock<FeatureAlgorithm> = RwLock::new(FeatureAlgorithm::Orb);
+
+pub fn set_feature_algorithm(algorithm: FeatureAlgorithm) {
+    if let Ok(mut a) = FEATURE_ALGORITHM.write() {
+        *a = algorithm;
+    }
+}
+
+fn feature_algorithm() -> FeatureAlgorithm {
+    FEATURE_ALGORITHM.read().map(|a| *a).unwrap_or_default()
+}
+
+// 灰度图 -> (特征点, 描述符)。实现需保证描述符为 CV_8U 二进制描述符，缓存按字节存储
+trait FeatureExtractor {
+    fn detect_and_compute(&self, gray: &Mat) -> opencv::Result<(Vector<KeyPoint>, Mat)>;
+}
+
+struct OrbExtractor {
+    n_features: i32,
+}
+
+impl FeatureExtractor for OrbExtractor {
+    fn detect_and_compute(&self, gray: &Mat) -> opencv::Result<(Vector<KeyPoint>, Mat)> {
+        let mut orb = create_orb(self.n_features)?;
+        let mut keypoints = Vector::<KeyPoint>::new();
+        let mut descriptors = Mat::default();
+        orb.detect_and_compute(gray, &Mat::default(), &mut keypoints, &mut descriptors, false)?;
+        Ok((keypoints, descriptors))
+    }
+}
+
+// AKAZE 在纹理单一或反光的卡面上通常比 ORB 找到更多稳定特征点；默认 MLDB 描述符同为二进制，使用 Hamming 距离
+struct AkazeExtractor {
+    max_features: i32,
+}
+
+impl FeatureExtractor for AkazeExtractor {
+    fn detect_and_compute(&self, gray: &Mat) -> opencv::Result<(Vector<KeyPoint>, Mat)> {
+        let mut akaze = AKAZE::create_def()?;
+        let mut detected = Vector::<KeyPoint>::new();
+        akaze.detect(gray, &mut detected, &Mat::default())?;
+
+        // AKAZE 没有特征点数量上限，按响应值保留最强的 max_features 个，与 ORB 的 n_features 含义一致
+        let mut sorted: Vec<KeyPoint> = detected.to_vec();
+        sorted.sort_by(|a, b| b.response().total_cmp(&a.response()));
+        sorted.truncate(self.max_features.max(0) as usize);
+        let mut keypoints = Vector::<KeyPoint>::from_iter(sorted);
+
+        let mut descriptors = Mat::default();
+        akaze.compute(gray, &mut keypoints, &mut descriptors)?;
+        Ok((keypoints, descriptors))
+    }
+}
+
+fn feature_extractor(n_features: i32) -> Box<dyn FeatureExtractor> {
+    match feature_algorithm() {
+        FeatureAlgorithm::Orb => Box::new(OrbExtractor { n_features }),
+        FeatureAlgorithm::Akaze => Box::new(AkazeExtractor { max_features: n_features }),
+    }
+}
+
+// 模板缓存格式版本：TemplateCache / EventTemplateCache 结构变化时递增
+const TEMPLATE_CACHE_FORMAT_VERSION: u32 = 6;
+
+// 写入缓存文件头部的指纹，读取时与当前参数不一致则拒绝该缓存
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct CacheFingerprint {
+    format_version: u32,
+    algorithm: FeatureAlgorithm,
+    n_features: i32,
+    scale_factor: f32,
+    n_levels: i32,
+    edge_threshold: i32,
+    wta_k: i32,
+    score_type: OrbScoreType,
+    patch_size: i32,
+    fast_threshold: i32,
+    enhance_contrast: bool,
+}
+
+impl CacheFingerprint {
+    fn current(n_features: i32) -> Self {
+        let p = orb_params();
+        Self {
+            format_version: TEMPLATE_CACHE_FORMAT_VERSION,
+            algorithm: feature_algorithm(),
+            n_features,
+            scale_factor: p.scale_factor,
+            n_levels: p.n_levels,
+            edge_threshold: p.edge_threshold,
+            wta_k: p.wta_k,
+            score_type: p.score_type,
+            patch_size: p.patch_size,
+            fast_threshold: p.fast_threshold,
+            enhance_contrast: ENHANCE_CONTRAST.load(Ordering::Relaxed),
+        }
+    }
+}
+
+fn encode_template_cache<T: Serialize>(templates: &[T], n_features: i32) -> Result<Vec<u8>, String> {
+    bincode::serialize(&(CacheFingerprint::current(n_features), templates)).map_err(|e| e.to_string())
+}
+
+// 旧格式（无指纹）的缓存会在这里反序列化失败或指纹不匹配，调用方据此重新生成
+fn decode_template_cache<T: serde::de::DeserializeOwned>(data: &[u8], n_features: i32) -> Result<Vec<T>, String> {
+    let (fingerprint, templates) = bincode::deserialize::<(CacheFingerprint, Vec<T>)>(data)
+        .map_err(|e| format!("Invalid or outdated cache format: {}", e))?;
+    let expected = CacheFingerprint::current(n_features);
+    if fingerprint != expected {
+        return Err(format!("Cache fingerprint mismatch: cache={:?}, current={:?}", fingerprint, expected));
+    }
+    Ok(templates)
+}
+
+// 加入指纹之前（无文件头）的缓存布局：bincode 序列化的 Vec<LegacyTemplateCache>。
+// 安装包自带的 monster_features_opencv_v2.bin 与老用户 AppData 里的 card_features_opencv.bin 都是这种格式
+#[derive(Deserialize)]
+struct LegacyTemplateCache {
+    name: String,
+    day: String,
+    keypoints: Vec<(f32, f32)>,
+    descriptors: Vec<u8>,
+    descriptor_rows: i32,
+    descriptor_cols: i32,
+    sample_png: Vec<u8>,
+    sample_w: u32,
+    sample_h: u32,
+}
+
+// 旧缓存一律用默认 ORB 参数、未开启对比度增强生成，只有当前参数与之一致时描述符才能直接沿用
+fn decode_legacy_template_cache(data: &[u8], n_features: i32) -> Option<Vec<LegacyTemplateCache>> {
+    let defaults = OrbParams::default();
+    let legacy = CacheFingerprint {
+        format_version: TEMPLATE_CACHE_FORMAT_VERSION,
+        algorithm: FeatureAlgorithm::Orb,
+        n_features,
+        scale_factor: defaults.scale_factor,
+        n_levels: defaults.n_levels,
+        edge_threshold: defaults.edge_threshold,
+        wta_k: defaults.wta_k,
+        score_type: defaults.score_type,
+        patch_size: defaults.patch_size,
+        fast_threshold: defaults.fast_threshold,
+        enhance_contrast: false,
+    };
+    if CacheFingerprint::current(n_features) != legacy {
+        return None;
+    }
+    // 新格式文件（只是指纹不匹配）不能按旧布局解析
+    if bincode::deserialize::<(CacheFingerprint, Vec<TemplateCache>)>(data).is_ok() {
+        return None;
+    }
+    bincode::deserialize::<Vec<LegacyTemplateCache>>(data).ok().filter(|t| !t.is_empty())
+}
+
+// 旧怪物缓存迁移：sample_png 就是源图片的原始字节，可据此补上 dHash 与源文件指纹（mtime 记 0，
+// 增量重建时按内容哈希比对并刷新），无需重新提取特征
+fn migrate_legacy_monster_cache(data: &[u8]) -> Option<Vec<TemplateCache>> {
+    let legacy = decode_legacy_template_cache(data, MONSTER_TEMPLATE_FEATURES)?;
+    Some(legacy.into_par_iter().map(|t| {
+        let phash = image::load_from_memory(&t.sample_png).map(|img| dhash(&img)).unwrap_or(0);
+        let source = (!t.sample_png.is_empty()).then(|| TemplateSource {
+            mtime: 0,
+            len: t.sample_png.len() as u64,
+            hash: content_hash(&t.sample_png),
+        });
+        TemplateCache {
+            name: t.name,
+            day: t.day,
+            keypoints: t.keypoints,
+            descriptors: t.descriptors,
+            descriptor_rows: t.descriptor_rows,
+            descriptor_cols: t.descriptor_cols,
+            sample_png: t.sample_png,
+            sample_w: t.sample_w,
+            sample_h: t.sample_h,
+            phash,
+            hsv_hist: Vec::new(),
+            source,
+        }
+    }).collect())
+}
+
+// 旧卡牌缓存迁移：不含图片数据，HSV 直方图从 resources/images/{id}.webp 重新计算（day 字段存的是卡牌 ID）
+fn migrate_legacy_card_cache(data: &[u8], resources_dir: &std::path::Path) -> Option<Vec<TemplateCache>> {
+    let legacy = decode_legacy_template_cache(data, CARD_TEMPLATE_FEATURES)?;
+    Some(legacy.into_par_iter().map(|t| {
+        let img_path = resources_dir.join("images").join(format!("{}.webp", t.day));
+        let hsv_hist = image::open(&img_path).map(|img| hsv_histogram(&img)).unwrap_or_default();
+        TemplateCache {
+            name: t.name,
+            day: t.day,
+            keypoints: t.keypoints,
+            descriptors: t.descriptors,
+            descriptor_rows: t.descriptor_rows,
+            descriptor_cols: t.descriptor_cols,
+            sample_png: t.sample_png,
+            sample_w: t.sample_w,
+            sample_h: t.sample_h,
+            phash: 0,
+            hsv_hist,
+            source: None,
+        }
+    }).collect())
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ContrastMeasurement {
+    pub position: u8,
+    pub mean_brightness: f64,
+    pub keypoints_raw: usize,
+    pub keypoints_clahe: usize,
+}
+
+// 对当前怪物槽位分别统计增强前后的 ORB 特征点数，用于评估暗场景下是否值得开启
+pub fn measure_contrast_enhancement() -> Result<Vec<ContrastMeasurement>, String> {
+    let (img, frame) = capture_monster_scene()?;
+    let (width, height) = img.dimensions();
+
+    let count_keypoints = |gray: &Mat| -> Result<usize, opencv::Error> {
+        let mut orb = create_orb(MONSTER_TEMPLATE_FEATURES)?;
+        let mut keypoints = Vector::<KeyPoint>::new();
+        orb.detect(gray, &mut keypoints, &Mat::default())?;
+        Ok(keypoints.len())
+    };
+
+    let mut results = Vec::new();
+    for (i, (x, y, w, h)) in compute_monster_slots(width, height, &frame).into_iter().enumerate() {
+        let slice = img.crop_imm(x, y, w, h);
+        let mut buff = Vec::new();
+        slice.write_to(&mut std::io::Cursor::new(&mut buff), image::ImageFormat::Png).map_err(|e| e.to_string())?;
+        let gray = imdecode(&Mat::from_slice(&buff).map_err(|e| e.to_string())?, IMREAD_GRAYSCALE).map_err(|e| e.to_string())?;
+        if gray.empty() { continue; }
+
+        let mean_brightness = opencv::core::mean(&gray, &Mat::default()).map_err(|e| e.to_string())?[0];
+        let keypoints_raw = count_keypoints(&gray).map_err(|e| e.to_string())?;
+        let enhanced = apply_clahe(&gray).map_err(|e| e.to_string())?;
+        let keypoints_clahe = count_keypoints(&enhanced).map_err(|e| e.to_string())?;
+
+        println!("[Contrast] Slot {}: brightness {:.1}, keypoints {} -> {} (CLAHE)", i + 1, mean_brightness, keypoints_raw, keypoints_clahe);
+        results.push(ContrastMeasurement {
+            position: (i + 1) as u8,
+            mean_brightness,
+            keypoints_raw,
+            keypoints_clahe,
+        });
+    }
+    Ok(results)
+}
 
 pub fn get_loading_progress() -> LoadingProgress {
     LOADING_PROGRESS
@@ -379,6 +1519,7 @@ pub fn get_loading_progress() -> LoadingProgress {
             total: 0,
             is_complete: false,
             current_name: "".to_string(),
+            published: 0,
         })
 }
 
@@ -386,22 +1527,14 @@ pub fn get_loading_progress() -> LoadingProgress {
 fn extract_features_orb(image_path: &str, n_features: i32) -> Result<(Vec<(f32, f32)>, Vec<u8>, i32, i32), opencv::Error> {
     // 读取图片 (支持中文路径)
     let content = std::fs::read(image_path).map_err(|e| opencv::Error::new(opencv::core::StsError, format!("Read error: {}", e)))?;
-    let img = imdecode(&Mat::from_slice(&content)?, IMREAD_GRAYSCALE)?;
+    let img = preprocess_gray(imdecode(&Mat::from_slice(&content)?, IMREAD_GRAYSCALE)?)?;
     
     if img.empty() {
         return Ok((Vec::new(), Vec::new(), 0, 0));
     }
 
-    // 初始化 ORB
-    let mut orb = ORB::create(n_features, 1.2f32, 8, 31, 0, 2, 
-        opencv::features2d::ORB_ScoreType::HARRIS_SCORE, 31, 20)?;
-
-    // 提取特征点和描述符
-    let mut keypoints = Vector::<KeyPoint>::new();
-    let mut descriptors = Mat::default();
-    let mask = Mat::default();
-
-    orb.detect_and_compute(&img, &mask, &mut keypoints, &mut descriptors, false)?;
+    // 提取特征点和描述符（ORB 或 AKAZE，取决于 feature_algorithm）
+    let (keypoints, descriptors) = feature_extractor(n_features).detect_and_compute(&img)?;
 
     if descriptors.empty() {
         return Ok((Vec::new(), Vec::new(), 0, 0));
@@ -446,30 +1579,23 @@ pub fn extract_features_from_dynamic_image(img: &DynamicImage, n_features: i32)
     use opencv::core::_InputArray;
     let buf_mat = Mat::from_slice(&bytes)?;
     let input_array = _InputArray::from_mat(&buf_mat)?;
-    let gray_img = imdecode(&input_array, IMREAD_GRAYSCALE)?;
+    let gray_img = preprocess_gray(imdecode(&input_array, IMREAD_GRAYSCALE)?)?;
     
     if gray_img.empty() {
         return Ok(Mat::default());
     }
 
-    // 初始化 ORB (截图也同样使用 1000 个特征点)
-    let mut orb = ORB::create(n_features, 1.2f32, 8, 31, 0, 2, 
-        opencv::features2d::ORB_ScoreType::HARRIS_SCORE, 31, 20)?;
-
-    let mut keypoints = Vector::<KeyPoint>::new();
-    let mut descriptors = Mat::default();
-    let mask = Mat::default();
-
-    orb.detect_and_compute(&gray_img, &mask, &mut keypoints, &mut descriptors, false)?;
+    // 截图与模板使用同一种特征提取算法
+    let (_, descriptors) = feature_extractor(n_features).detect_and_compute(&gray_img)?;
 
     Ok(descriptors)
 }
 
 pub fn match_card_descriptors(scene_desc: &Mat) -> Result<Option<serde_json::Value>, String> {
-    let cache = CARD_TEMPLATE_CACHE.get().ok_or("Card templates not loaded")?;
+    let cache = card_templates().ok_or("Card templates not loaded")?;
     let mut results: Vec<(&TemplateCache, usize, f32)> = Vec::new();
 
-    for template in cache {
+    for template in cache.iter() {
         if template.descriptors.is_empty() { continue; }
         use opencv::core::CV_8U;
         let mut template_desc = match unsafe { Mat::new_rows_cols(template.descriptor_rows, template.descriptor_cols, CV_8U) } {
@@ -508,10 +1634,10 @@ pub fn match_card_descriptors(scene_desc: &Mat) -> Result<Option<serde_json::Val
 }
 
 pub fn match_monster_descriptors_from_mat(scene_descriptors: &Mat) -> Result<Option<String>, String> {
-    let cache = TEMPLATE_CACHE.get().ok_or("Monster templates not loaded")?;
+    let cache = monster_templates().ok_or("Monster templates not loaded")?;
     let mut results = Vec::new();
 
-    for template in cache {
+    for template in cache.iter() {
         if template.descriptors.is_empty() { continue; }
         use opencv::core::CV_8U;
         let rows = template.descriptor_rows;
@@ -557,14 +1683,34 @@ pub fn match_monster_descriptors_from_mat(scene_descriptors: &Mat) -> Result<Opt
     Ok(None)
 }
 
-// 从 Mat 匹配事件描述符，返回事件ID
+// 事件匹配候选：事件模板特征点通常比怪物少，阈值单独配置（event_min_matches / event_min_confidence）
+#[derive(Debug, Clone, Serialize)]
+pub struct EventCandidate {
+    pub id: String,
+    pub name: String,
+    pub match_count: usize,
+    pub confidence: f32,
+}
+
+const EVENT_CANDIDATE_LIMIT: usize = 3;
+
+// 从 Mat 匹配事件描述符，返回最佳事件ID
 pub fn match_event_descriptors_from_mat(scene_descriptors: &Mat) -> Result<Option<String>, String> {
+    Ok(match_event_candidates(scene_descriptors)?.into_iter().next().map(|c| c.id))
+}
+
+// 与 match_card_descriptors 相同：按匹配点数排序，返回达到阈值的前 EVENT_CANDIDATE_LIMIT 个候选
+pub fn match_event_candidates(scene_descriptors: &Mat) -> Result<Vec<EventCandidate>, String> {
     let cache = EVENT_TEMPLATE_CACHE.get().ok_or("Event templates not loaded")?;
+    let (min_matches, min_confidence) = {
+        let state = crate::load_state();
+        (state.event_min_matches, state.event_min_confidence)
+    };
     let mut results = Vec::new();
     
     println!("[Event Recognition] Scene has {} descriptors", scene_descriptors.rows());
 
-    for template in cache {
+    for template in cache.iter() {
         if template.descriptors.is_empty() { continue; }
         use opencv::core::CV_8U;
         let rows = template.descriptor_rows;
@@ -602,27 +1748,129 @@ pub fn match_event_descriptors_from_mat(scene_descriptors: &Mat) -> Result<Optio
                   i+1, name, matches, template_kp, scene_descriptors.rows(), score);
     }
     
-    if let Some((best_id, best_name, max_matches, best_score, _)) = results.first() {
-        // 事件识别阈值：匹配点数 >= 12 且 得分 > 0.12 (降低阈值以提高召回率)
-        if *max_matches >= 12 && *best_score > 0.12 {
-             println!("[Event Recognition] ✓ Matched: {} (Matches: {}, Score: {:.4})", best_name, max_matches, best_score);
-             return Ok(Some(best_id.clone()));
-        } else {
-             println!("[Event Recognition] ✗ No match above threshold (Best: {} with {} matches, {:.4} score)", best_name, max_matches, best_score);
+    let candidates: Vec<EventCandidate> = results.iter()
+        .filter(|(_, _, matches, score, _)| *matches >= min_matches && *score > min_confidence)
+        .take(EVENT_CANDIDATE_LIMIT)
+        .map(|(id, name, matches, score, _)| EventCandidate { id: id.clone(), name: name.clone(), match_count: *matches, confidence: *score })
+        .collect();
+
+    match (candidates.first(), results.first()) {
+        (Some(best), _) => println!("[Event Recognition] ✓ Matched: {} (Matches: {}, Score: {:.4}), {} candidate(s)", best.name, best.match_count, best.confidence, candidates.len()),
+        (None, Some((_, best_name, max_matches, best_score, _))) => {
+            println!("[Event Recognition] ✗ No match above threshold (Best: {} with {} matches, {:.4} score)", best_name, max_matches, best_score)
         }
+        (None, None) => {}
     }
 
-    Ok(None)
+    Ok(candidates)
 }
 
 // ORB 匹配函数 - 使用 Lowe's Ratio Test
+// 从字节重建 CV_8U 描述符 Mat；长度与 rows*cols 不符时返回 None
+fn descriptors_mat(data: &[u8], rows: i32, cols: i32) -> Option<Mat> {
+    if data.is_empty() || data.len() != (rows * cols) as usize { return None; }
+    let mut mat = unsafe { Mat::new_rows_cols(rows, cols, opencv::core::CV_8U) }.ok()?;
+    unsafe {
+        std::ptr::copy_nonoverlapping(data.as_ptr(), mat.data_mut() as *mut u8, data.len());
+    }
+    Some(mat)
+}
+
+// 并行 ORB 匹配：截图描述符先拷贝成字节，每个 rayon 工作线程各自重建 Mat（不跨线程共享 Mat），
+// 模板 Mat 在每个闭包内独立重建，BFMatcher 在 match_orb_descriptors 内按次创建
+fn par_match_templates<'a>(scene_desc: &Mat, templates: &[&'a TemplateCache]) -> Result<Vec<(&'a TemplateCache, usize)>, String> {
+    let (rows, cols) = (scene_desc.rows(), scene_desc.cols());
+    let scene_bytes = scene_desc.data_bytes().map_err(|e| e.to_string())?.to_vec();
+
+    Ok(templates.par_iter()
+        .map_init(
+            || descriptors_mat(&scene_bytes, rows, cols),
+            |scene, template| {
+                let scene = scene.as_ref()?;
+                let template_desc = descriptors_mat(&template.descriptors, template.descriptor_rows, template.descriptor_cols)?;
+                match match_orb_descriptors(scene, &template_desc) {
+                    Ok(matches) => Some((*template, matches)),
+                    Err(e) => {
+                        println!("[警告] 匹配 {} 时出错: {}", template.name, e);
+                        None
+                    }
+                }
+            },
+        )
+        .flatten()
+        .collect())
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct MatchBenchmark {
+    pub templates: usize,
+    pub rounds: usize,
+    pub serial_ms: f64,   // 每轮平均
+    pub parallel_ms: f64, // 每轮平均
+    pub best: Option<(String, usize)>,
+    pub same_best: bool,
+}
+
+// 串行逐个匹配与 par_match_templates 的耗时对比：模板来自怪物特征缓存文件（如预打包的
+// monster_features_opencv_v2.bin），场景为 scene 的特征；两种方式的最佳结果应一致
+pub fn benchmark_template_matching(cache_file: &PathBuf, scene: &DynamicImage, rounds: usize) -> Result<MatchBenchmark, String> {
+    let data = std::fs::read(cache_file).map_err(|e| format!("Failed to read {:?}: {}", cache_file, e))?;
+    let templates = decode_template_cache::<TemplateCache>(&data, MONSTER_TEMPLATE_FEATURES)
+        .or_else(|e| migrate_legacy_monster_cache(&data).ok_or(e))?;
+    let refs: Vec<&TemplateCache> = templates.iter().collect();
+    let scene_desc = extract_features_from_dynamic_image(scene, 1000).map_err(|e| e.to_string())?;
+    let rounds = rounds.max(1);
+    // 与 recognize_monsters 相同的归约：平局保留先出现的模板
+    fn pick<'a>(best: (&'a TemplateCache, usize), cur: (&'a TemplateCache, usize)) -> (&'a TemplateCache, usize) {
+        if cur.1 > best.1 { cur } else { best }
+    }
+
+    let start = std::time::Instant::now();
+    let mut serial_best = None;
+    for _ in 0..rounds {
+        serial_best = refs.iter()
+            .filter_map(|t| {
+                let desc = descriptors_mat(&t.descriptors, t.descriptor_rows, t.descriptor_cols)?;
+                match_orb_descriptors(&scene_desc, &desc).ok().map(|m| (*t, m))
+            })
+            .reduce(pick);
+    }
+    let serial_ms = start.elapsed().as_secs_f64() * 1000.0 / rounds as f64;
+
+    let start = std::time::Instant::now();
+    let mut parallel_best = None;
+    for _ in 0..rounds {
+        parallel_best = par_match_templates(&scene_desc, &refs)?.into_iter().reduce(pick);
+    }
+    let parallel_ms = start.elapsed().as_secs_f64() * 1000.0 / rounds as f64;
+
+    let summary = |b: Option<(&TemplateCache, usize)>| b.map(|(t, m)| (t.name.clone(), m));
+    let best = summary(parallel_best);
+    Ok(MatchBenchmark {
+        templates: refs.len(),
+        rounds,
+        serial_ms,
+        parallel_ms,
+        same_best: summary(serial_best) == best,
+        best,
+    })
+}
+
 fn match_orb_descriptors(desc1: &Mat, desc2: &Mat) -> Result<usize, opencv::Error> {
     if desc1.empty() || desc2.empty() {
         return Ok(0);
     }
 
-    // 创建 BFMatcher (Hamming 距离)
-    let matcher = BFMatcher::create(NORM_HAMMING, false)?;
+    // 创建 BFMatcher：浮点描述符（如 KAZE）用 L2；AKAZE(MLDB) 与 WTA_K=2 的 ORB 用 Hamming；
+    // WTA_K 为 3/4 时 ORB 每个元素占 2 位，OpenCV 要求用 NORM_HAMMING2
+    let norm = if desc1.depth() == opencv::core::CV_32F {
+        NORM_L2
+    } else if feature_algorithm() == FeatureAlgorithm::Orb && orb_params().wta_k > 2 {
+        NORM_HAMMING2
+    } else {
+        NORM_HAMMING
+    };
+    let matcher = BFMatcher::create(norm, false)?;
     
     // KNN 匹配，k=2
     let mut matches = Vector::<Vector::<DMatch>>::new();
@@ -647,63 +1895,53 @@ fn match_orb_descriptors(desc1: &Mat, desc2: &Mat) -> Result<usize, opencv::Erro
 
 pub async fn preload_templates_async(resources_dir: PathBuf, cache_dir: PathBuf) -> Result<(), String> {
     log_to_file(&format!("Start loading templates. Resource Dir: {:?}, Cache Dir: {:?}", resources_dir, cache_dir));
-    let progress = Arc::new(Mutex::new(LoadingProgress {
+    let fresh = LoadingProgress {
         loaded: 0,
         total: 0,
         is_complete: false,
         current_name: "".to_string(),
-    }));
-    let _ = LOADING_PROGRESS.set(progress.clone());
+        published: 0,
+    };
+    // 重建缓存时会再次进入这里，复用同一个进度对象，前端轮询才能看到新的进度
+    let progress = LOADING_PROGRESS.get_or_init(|| Arc::new(Mutex::new(fresh.clone()))).clone();
+    if let Ok(mut p) = progress.lock() {
+        *p = fresh;
+    }
     
     // Define both paths
-    let cache_file = cache_dir.join("monster_features_opencv_v2.bin");
-    let bundled_cache = resources_dir.join("monster_features_opencv_v2.bin");
-
-    // 1. 优先从资源目录加载（预打包的缓存）
-    if bundled_cache.exists() {
-        log_to_file(&format!("Found bundled cache file at {:?}. Using it.", bundled_cache));
-        if let Ok(data) = std::fs::read(&bundled_cache) {
-            if let Ok(cached_templates) = bincode::deserialize::<Vec<TemplateCache>>(&data) {
-                if !cached_templates.is_empty() {
-                    log_to_file(&format!("Loaded {} templates from bundled cache", cached_templates.len()));
-                    if let Ok(mut p) = progress.lock() {
-                        p.loaded = cached_templates.len();
-                        p.total = cached_templates.len();
-                        p.is_complete = true;
-                    }
-                    let _ = TEMPLATE_CACHE.set(cached_templates);
-                    return Ok(());
-                }
-            }
+    let cache_file = cache_dir.join(feature_cache_name("monster_features_opencv_v2.bin"));
+    let bundled_cache = resources_dir.join(feature_cache_name("monster_features_opencv_v2.bin"));
+
+    // 1. 读取已有缓存作为增量重建的基础：优先 AppData（包含最近一次增量更新），其次预打包缓存
+    let mut previous: HashMap<String, TemplateCache> = HashMap::new();
+    let mut loaded_from_app_cache = false;
+    let skip_bundled = SKIP_BUNDLED_MONSTER_CACHE.swap(false, Ordering::SeqCst);
+    for path in [&cache_file, &bundled_cache] {
+        if !path.exists() || (skip_bundled && path == &bundled_cache) {
+            continue;
         }
-    }
-
-    // 2. 尝试从 AppData 缓存加载
-    if cache_file.exists() {
-        log_to_file(&format!("Found cache file at {:?}", cache_file));
-        if let Ok(data) = std::fs::read(&cache_file) {
-            if let Ok(cached_templates) = bincode::deserialize::<Vec<TemplateCache>>(&data) {
-                if !cached_templates.is_empty() {
-                    log_to_file(&format!("Loaded {} templates from OpenCV cache", cached_templates.len()));
-                    println!("从 OpenCV 缓存加载了 {} 个怪物特征点模板", cached_templates.len());
-                    if let Ok(mut p) = progress.lock() {
-                        p.loaded = cached_templates.len();
-                        p.total = cached_templates.len();
-                        p.is_complete = true;
-                    }
-                    let _ = TEMPLATE_CACHE.set(cached_templates);
-                    return Ok(());
-                } else {
-                    log_to_file("Cache file is empty (0 templates). Rebuilding from images...");
-                }
-            } else {
-                log_to_file("Failed to deserialize cache file.");
+        let mut migrated = false;
+        let decoded = std::fs::read(path)
+            .map_err(|e| e.to_string())
+            .and_then(|data| decode_template_cache::<TemplateCache>(&data, MONSTER_TEMPLATE_FEATURES).or_else(|e| {
+                // 旧格式缓存迁移一次，随后以新格式写入 AppData
+                let templates = migrate_legacy_monster_cache(&data).ok_or(e)?;
+                migrated = true;
+                Ok(templates)
+            }));
+        match decoded {
+            Ok(templates) if !templates.is_empty() => {
+                log_to_file(&format!("Loaded {} cached templates from {:?}{}", templates.len(), path, if migrated { " (migrated from legacy format)" } else { "" }));
+                previous = templates.into_iter().map(|t| (t.name.clone(), t)).collect();
+                loaded_from_app_cache = path == &cache_file && !migrated;
+                break;
             }
-        } else {
-             log_to_file("Failed to read cache file.");
+            Ok(_) => log_to_file(&format!("Cache file {:?} is empty (0 templates), ignoring it", path)),
+            Err(e) => log_to_file(&format!("Rejected cache {:?}: {}", path, e)),
         }
-    } else {
-        log_to_file("Cache file not found, rebuilding from images.");
+    }
+    if previous.is_empty() {
+        log_to_file("No usable cache found, rebuilding all templates from images.");
     }
 
     // 2. 从原始图片加载 (使用 Rayon 并行)
@@ -761,7 +1999,11 @@ pub async fn preload_templates_async(resources_dir: PathBuf, cache_dir: PathBuf)
     let mut image_tasks = Vec::new();
     let mut seen_names = HashSet::new();
 
-    for (key, entry) in monsters.iter() {
+    // 按 key 排序遍历：同名不同 _DayN 的怪物和陷阱变体去重后保留哪一天不随 HashMap 顺序变化
+    let mut sorted_monsters: Vec<_> = monsters.iter().collect();
+    sorted_monsters.sort_by(|a, b| a.0.cmp(b.0));
+
+    for (key, entry) in sorted_monsters {
         if let Some(day) = &entry.available {
             let mut found_path = name_to_path.get(key).cloned();
             
@@ -805,11 +2047,11 @@ pub async fn preload_templates_async(resources_dir: PathBuf, cache_dir: PathBuf)
                 if let Ok(metadata) = std::fs::metadata(&path) {
                     if metadata.len() > 0 {
                         seen_names.insert(clean_name.clone());
-                        image_tasks.push((clean_name, day.clone(), path));
+                        image_tasks.push((clean_name, crate::canonical_day_label(day), path));
                     }
                 }
             } else {
-                 if day == "Day 10+" {
+                 if crate::canonical_day_label(day) == crate::day_bucket_label(10) {
                      log_to_file(&format!("Missing Day 10+ monster image: {}", key));
                  }
             }
@@ -818,60 +2060,128 @@ pub async fn preload_templates_async(resources_dir: PathBuf, cache_dir: PathBuf)
 
     let total = image_tasks.len();
     log_to_file(&format!("Found {} images to process.", total));
-    
-    if let Ok(mut p) = progress.lock() { p.total = total; }
 
-    println!("缓存未命中，开始使用 OpenCV ORB 计算 {} 个特征点模板...", total);
+    // 与磁盘上的源图片对比：修改时间和大小一致直接复用；不一致时再比对内容哈希（如重新安装后 mtime 变化），
+    // 只有新增或内容变化的图片才重新提取特征
+    let mut cache: Vec<TemplateCache> = Vec::with_capacity(total);
+    let mut stale_tasks = Vec::new();
+    let mut refreshed = 0;
+    for (name, day, path) in image_tasks {
+        let reused = previous.remove(&name).and_then(|mut cached| {
+            // 天数只是标签，不影响特征：变化时改写该字段并写回缓存，无需重建
+            let day_changed = cached.day != day;
+            cached.day = day.clone();
+            let src = cached.source?;
+            let (mtime, len) = file_stamp(&path)?;
+            if src.mtime == mtime && src.len == len {
+                if day_changed {
+                    refreshed += 1;
+                }
+                return Some(cached);
+            }
+            let data = std::fs::read(&path).ok()?;
+            if content_hash(&data) != src.hash {
+                return None;
+            }
+            cached.source = Some(TemplateSource { mtime, len, hash: src.hash });
+            refreshed += 1;
+            Some(cached)
+        });
+        match reused {
+            Some(template) => cache.push(template),
+            None => stale_tasks.push((name, day, path)),
+        }
+    }
+    // 剩下的旧条目在数据库中已不存在
+    let removed = previous.len();
+    let stale = stale_tasks.len();
+    log_to_file(&format!("Template cache diff: {} reused ({} refreshed stamps/days), {} to rebuild, {} removed", cache.len(), refreshed, stale, removed));
+
+    if let Ok(mut p) = progress.lock() {
+        p.total = total;
+        p.loaded = cache.len();
+    }
 
-    // 使用 Rayon 并行处理所有图片
-    let cache: Vec<TemplateCache> = image_tasks.into_par_iter().filter_map(|(name, day, path)| {
-        let path_str = path.to_str()?;
-        
-        // 使用 OpenCV 提取特征
-        match extract_features_orb(path_str, 1000) {
-            Ok((keypoints, descriptors, desc_rows, desc_cols)) => {
-                // 读取原始图片数据用于调试
-                let sample_png = std::fs::read(&path).unwrap_or_default();
-                let (sample_w, sample_h) = if let Ok(img) = image::open(&path) {
-                    (img.width(), img.height())
-                } else {
-                    (0, 0)
-                };
+    if stale > 0 {
+        println!("复用 {} 个缓存模板，开始使用 OpenCV ORB 计算 {} 个新增或变化的特征点模板...", cache.len(), stale);
+        // 已复用的模板先发布，重建期间即可识别
+        if !cache.is_empty() {
+            publish_partial_monster_templates(&cache);
+            if let Ok(mut p) = progress.lock() { p.published = cache.len(); }
+        }
+    }
 
-                // 更新进度
-                if let Some(p_arc) = LOADING_PROGRESS.get() {
-                    if let Ok(mut p) = p_arc.lock() {
-                        p.loaded += 1;
-                        p.current_name = name.clone();
+    // 使用 Rayon 并行处理需要重建的图片；每完成 TEMPLATE_PUBLISH_BATCH 个就发布一次部分缓存，
+    // 首次运行时已加载的怪物无需等待全部构建完成即可识别
+    let mut processed = 0;
+    for batch in stale_tasks.chunks(TEMPLATE_PUBLISH_BATCH) {
+        processed += batch.len();
+        let built: Vec<TemplateCache> = batch.par_iter().cloned().filter_map(|(name, day, path)| {
+            let path_str = path.to_str()?;
+        
+            // 使用 OpenCV 提取特征
+            match extract_features_orb(path_str, MONSTER_TEMPLATE_FEATURES) {
+                Ok((keypoints, descriptors, desc_rows, desc_cols)) => {
+                    // 读取原始图片数据用于调试
+                    let sample_png = std::fs::read(&path).unwrap_or_default();
+                    let source = file_stamp(&path).map(|(mtime, len)| TemplateSource { mtime, len, hash: content_hash(&sample_png) });
+                    let (sample_w, sample_h, phash) = if let Ok(img) = image::open(&path) {
+                        (img.width(), img.height(), dhash(&img))
+                    } else {
+                        (0, 0, 0)
+                    };
+
+                    // 更新进度
+                    if let Some(p_arc) = LOADING_PROGRESS.get() {
+                        if let Ok(mut p) = p_arc.lock() {
+                            p.loaded += 1;
+                            p.current_name = name.clone();
+                        }
                     }
-                }
-
-                Some(TemplateCache {
-                    name,
-                    day,
-                    keypoints,
-                    descriptors,
-                    descriptor_rows: desc_rows,
-                    descriptor_cols: desc_cols,
-                    sample_png,
-                    sample_w,
-                    sample_h,
-                })
-            }
-            Err(e) => {
-                log_to_file(&format!("Warning: extraction failed for {}: {}", name, e));
-                println!("警告: 提取 {} 的特征失败: {}", name, e);
-                None
+
+                    Some(TemplateCache {
+                        name,
+                        day,
+                        keypoints,
+                        descriptors,
+                        descriptor_rows: desc_rows,
+                        descriptor_cols: desc_cols,
+                        sample_png,
+                        sample_w,
+                        sample_h,
+                        phash,
+                        hsv_hist: Vec::new(),
+                        source,
+                    })
+                }
+                Err(e) => {
+                    log_to_file(&format!("Warning: extraction failed for {}: {}", name, e));
+                    println!("警告: 提取 {} 的特征失败: {}", name, e);
+                    None
+                }
             }
+        }).collect();
+        cache.extend(built);
+
+        // 最后一批由下方的完整发布处理
+        if processed < stale {
+            publish_partial_monster_templates(&cache);
+            if let Ok(mut p) = progress.lock() { p.published = cache.len(); }
+            log_to_file(&format!("Published partial template cache: {}/{}", cache.len(), total));
         }
-    }).collect();
+    }
 
-    // 3. 保存到二进制缓存
-    let _ = std::fs::create_dir_all(&cache_dir);
-    if let Ok(encoded) = bincode::serialize(&cache) {
-        let _ = std::fs::write(&cache_file, encoded);
-        println!("OpenCV 特征点模板已保存到缓存: {:?}", cache_file);
-        log_to_file("Global cache saved.");
+    // 3. 有变化时合并写回二进制缓存（从预打包缓存复用时也写一份到 AppData）
+    let dirty = stale > 0 || removed > 0 || refreshed > 0 || !loaded_from_app_cache;
+    if dirty {
+        let _ = std::fs::create_dir_all(&cache_dir);
+        if let Ok(encoded) = encode_template_cache(&cache, MONSTER_TEMPLATE_FEATURES) {
+            let _ = std::fs::write(&cache_file, encoded);
+            println!("OpenCV 特征点模板已保存到缓存: {:?}", cache_file);
+            log_to_file("Global cache saved.");
+        }
+    } else {
+        println!("从 OpenCV 缓存加载了 {} 个怪物特征点模板", cache.len());
     }
     
     // 如果没有预打包缓存，提示用户可以复制生成的缓存
@@ -881,52 +2191,148 @@ pub async fn preload_templates_async(resources_dir: PathBuf, cache_dir: PathBuf)
     
     log_to_file(&format!("Template loading complete. Cache size: {}", cache.len()));
 
-    if let Ok(mut p) = progress.lock() { p.is_complete = true; }
-    let _ = TEMPLATE_CACHE.set(cache);
+    if let Ok(mut p) = progress.lock() {
+        p.published = cache.len();
+        p.is_complete = true;
+    }
+    set_monster_templates(cache);
     println!("OpenCV ORB 特征点模板加载完成");
     Ok(())
 }
 
 
-// 跨平台获取鼠标位置
-fn get_mouse_position() -> (i32, i32) {
+pub const CURSOR_POSITION_ERROR: &str = "couldn't read cursor position";
+
+// 跨平台获取鼠标位置（带重试）
+// 部分机器上 device_query / enigo 会返回过期值或 (0,0)，直接拿去裁剪会得到垃圾匹配结果，
+// 因此要求坐标落在某个显示器范围内，否则重试，最终返回明确的错误
+pub fn get_cursor_position() -> Result<(i32, i32), String> {
+    use xcap::Monitor;
+
+    let monitors: Vec<(i32, i32, i32, i32)> = Monitor::all()
+        .map(|ms| ms.iter().map(|m| (m.x(), m.y(), m.width() as i32, m.height() as i32)).collect())
+        .unwrap_or_default();
+    let in_some_monitor = |x: i32, y: i32| {
+        // 枚举显示器失败时无法校验，只排除 (0,0)
+        if monitors.is_empty() { return (x, y) != (0, 0); }
+        monitors.iter().any(|&(mx, my, mw, mh)| x >= mx && x < mx + mw && y >= my && y < my + mh)
+    };
+
     let device_state = DeviceState::new();
-    let mouse = device_state.get_mouse();
-    (mouse.coords.0, mouse.coords.1)
+    for attempt in 0..3 {
+        let (x, y) = device_state.get_mouse().coords;
+        // (0,0) 通常意味着读取失败，只有再次读取仍为 (0,0) 时才接受
+        if in_some_monitor(x, y) && ((x, y) != (0, 0) || attempt > 0) {
+            return Ok((x, y));
+        }
+        std::thread::sleep(std::time::Duration::from_millis(15));
+    }
+
+    // 降级：使用 enigo 再读一次
+    use enigo::{Enigo, Mouse, Settings};
+    if let Ok(enigo) = Enigo::new(&Settings::default()) {
+        if let Ok((x, y)) = enigo.location() {
+            if in_some_monitor(x, y) {
+                return Ok((x, y));
+            }
+        }
+    }
+
+    println!("[Cursor] Failed to read a valid cursor position");
+    Err(CURSOR_POSITION_ERROR.to_string())
+}
+
+// 置信度（0~100）= 匹配数 / sqrt(截图特征点数 × 模板特征点数) × 100，再乘以匹配数饱和系数 min(1, 匹配数 / 30)。
+// - 用几何平均代替 min(截图, 模板)：特征点很少的模板不会因为分母小而虚高
+// - 饱和系数：匹配数不足 30 时按比例压低，避免“3 个匹配点 = 100%”这类结果
+// 因此不同怪物之间的置信度可以直接比较
+const CONFIDENCE_FULL_MATCHES: f32 = 30.0;
+
+fn normalized_confidence(matches: usize, scene_kp: i32, template_kp: i32) -> f32 {
+    let denom = ((scene_kp.max(0) as f32) * (template_kp.max(0) as f32)).sqrt();
+    if denom <= 0.0 { return 0.0; }
+    let ratio = (matches as f32 / denom).min(1.0);
+    let saturation = (matches as f32 / CONFIDENCE_FULL_MATCHES).min(1.0);
+    ratio * saturation * 100.0
+}
+
+// 鼠标识别返回的候选数量
+pub const MONSTER_CANDIDATE_LIMIT: usize = 5;
+
+// 怪物裁剪边长占截图高度的比例：1080p 下约 400 像素（原固定值），4K 下约 800 像素可框住整个怪物卡面
+const MONSTER_CROP_RATIO: f32 = 0.37;
+const MONSTER_CROP_MIN: i32 = 300;
+const MONSTER_CROP_MAX: i32 = 1000;
+
+fn monster_crop_size(img_h: u32) -> i32 {
+    ((img_h as f32 * MONSTER_CROP_RATIO).round() as i32).clamp(MONSTER_CROP_MIN, MONSTER_CROP_MAX)
+}
+
+// 公共函数：鼠标触发的怪物识别，返回识别状态：达标时给出第一名（陷阱类展开为 | 分隔的并列名称），
+// 未达标时给出最接近的候选与差距
+pub fn scan_and_identify_monster_at_mouse() -> Result<MonsterMatchStatus, String> {
+    Ok(monster_match_status(&scan_and_identify_monster_candidates()?))
+}
+
+// 最近一次鼠标识别的耗时（毫秒）与统计，前端通过 get_recognition_diagnostics 读取，用户反馈问题时附上
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DiagnosticsSnapshot {
+    pub timestamp_ms: u64,
+    pub capture_ms: f64,
+    pub feature_ms: f64,
+    pub match_ms: f64,
+    pub scene_keypoints: i32,
+    pub templates_compared: usize,
+    pub candidate_count: usize,
+    pub best_match: Option<String>,
+    pub best_matches: usize,
+    pub best_confidence: f32,
+    // 命中 MOUSE_SCAN_CACHE 时跳过了特征提取与比对
+    pub cache_hit: bool,
 }
 
-// 公共函数：鼠标触发的怪物识别
-pub fn scan_and_identify_monster_at_mouse() -> Result<Option<String>, String> {
+static RECOGNITION_DIAGNOSTICS: RwLock<Option<DiagnosticsSnapshot>> = RwLock::new(None);
+
+pub fn recognition_diagnostics() -> Option<DiagnosticsSnapshot> {
+    RECOGNITION_DIAGNOSTICS.read().ok().and_then(|d| d.clone())
+}
+
+fn elapsed_ms(start: std::time::Instant) -> f64 {
+    start.elapsed().as_secs_f64() * 1000.0
+}
+
+fn record_diagnostics(mut snapshot: DiagnosticsSnapshot, candidates: &[(String, usize, f32)]) {
+    snapshot.timestamp_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    snapshot.candidate_count = candidates.len();
+    if let Some((name, matches, confidence)) = candidates.first() {
+        snapshot.best_match = Some(name.clone());
+        snapshot.best_matches = *matches;
+        snapshot.best_confidence = *confidence;
+    }
+    if let Ok(mut d) = RECOGNITION_DIAGNOSTICS.write() {
+        *d = Some(snapshot);
+    }
+}
+
+// 鼠标指向的怪物候选：按匹配点数降序的前 MONSTER_CANDIDATE_LIMIT 个 (名称, 匹配点数, 置信度)，
+// 同一怪物的多天模板只保留最好的一个，名称去掉 _Day 后缀
+pub fn scan_and_identify_monster_candidates() -> Result<Vec<(String, usize, f32)>, String> {
     use xcap::Monitor;
 
     // 1. 获取鼠标位置（跨平台）
-    let (mouse_x, mouse_y) = get_mouse_position();
+    let (mouse_x, mouse_y) = get_cursor_position()?;
 
     // 2. 查找窗口并截图
-    let windows = xcap::Window::all().map_err(|e| e.to_string())?;
     // 优先查找包含鼠标且标题匹配 "The Bazaar" 的窗口
-    let bazaar_window = windows.into_iter().find(|w| {
-        let title = w.title().to_lowercase();
-        let app_name = w.app_name().to_lowercase();
-        let is_bazaar = title.contains("the bazaar") || app_name.contains("the bazaar") || 
-                        title.contains("thebazaar") || app_name.contains("thebazaar");
-        
-        if is_bazaar {
-            let wx = w.x();
-            let wy = w.y();
-            let ww = w.width();
-            let wh = w.height();
-            // 检查鼠标是否在窗口范围内
-            mouse_x >= wx && mouse_x < wx + ww as i32 &&
-            mouse_y >= wy && mouse_y < wy + wh as i32
-        } else {
-            false
-        }
-    });
+    let capture_start = std::time::Instant::now();
+    let bazaar_window = find_game_window(Some((mouse_x, mouse_y)))?;
 
     let (screenshot, win_x, win_y) = if let Some(window) = bazaar_window {
         log_to_file(&format!("Found matching window under mouse: {}, App: {}", window.title(), window.app_name()));
-        (window.capture_image().map_err(|e| e.to_string())?, window.x(), window.y())
+        capture_game_window(&window)?
     } else {
         log_to_file("No matching Bazaar window under mouse, capturing monitor under cursor.");
         // Find monitor containing the mouse
@@ -945,16 +2351,26 @@ pub fn scan_and_identify_monster_at_mouse() -> Result<Option<String>, String> {
         (target_monitor.capture_image().map_err(|e| e.to_string())?, target_monitor.x(), target_monitor.y())
     };
 
-    let img = DynamicImage::ImageRgba8(screenshot);
+    // 裁掉 capture_inset 排除的边缘，截图原点随之平移
+    let screenshot = DynamicImage::ImageRgba8(screenshot);
+    let (_, full_h) = screenshot.dimensions();
+    let (img, inset_x, inset_y) = apply_capture_inset(screenshot);
+    let (win_x, win_y) = (win_x + inset_x as i32, win_y + inset_y as i32);
     let (img_w, img_h) = img.dimensions();
+    let mut diagnostics = DiagnosticsSnapshot { capture_ms: elapsed_ms(capture_start), ..Default::default() };
 
-    // 3. 计算裁剪区域 400x400
+    // 3. 计算裁剪区域（边长随截图高度缩放）
     // 鼠标在截图内的相对坐标
     let rel_x = mouse_x - win_x;
     let rel_y = mouse_y - win_y;
+    if rel_x < 0 || rel_y < 0 || rel_x >= img_w as i32 || rel_y >= img_h as i32 {
+        // 鼠标在排除的 HUD 边缘内：按无候选处理，检测热键（默认右键）不弹错误提示
+        log_to_file(&format!("Cursor ({},{}) is inside the excluded capture inset, skipping scan", mouse_x, mouse_y));
+        return Ok(Vec::new());
+    }
     
-    // 定义裁剪框 (以鼠标为中心)
-    let crop_size = 400;
+    // 定义裁剪框 (以鼠标为中心)，边长按裁边前的截图高度计算
+    let crop_size = monster_crop_size(full_h);
     let half_size = crop_size / 2;
     
     // 确保不越界
@@ -966,130 +2382,314 @@ pub fn scan_and_identify_monster_at_mouse() -> Result<Option<String>, String> {
     let crop_w = if crop_x + crop_size as u32 > img_w { img_w.saturating_sub(crop_x) } else { crop_size as u32 };
     let crop_h = if crop_y + crop_size as u32 > img_h { img_h.saturating_sub(crop_y) } else { crop_size as u32 };
 
-    if crop_w < 50 || crop_h < 50 {
+    if let Err(e) = check_crop_size(crop_w, crop_h) {
         log_to_file(&format!("Error: Crop area too small ({}x{}). Mouse: ({},{}), Win: ({},{}), Rel: ({},{}), Img: {}x{}", 
             crop_w, crop_h, mouse_x, mouse_y, win_x, win_y, rel_x, rel_y, img_w, img_h));
-        return Err("裁剪区域太小或鼠标已移出窗口范围".into());
+        return Err(e);
     }
 
     let cropped_img = img.crop_imm(crop_x, crop_y, crop_w, crop_h);
     // 可选：保存调试图片
     // cropped_img.save("debug_mouse_crop.png").ok();
 
+    let signature = region_signature(&cropped_img);
+    if let Some(candidates) = cached_mouse_scan((mouse_x, mouse_y), &signature) {
+        log_to_file("Mouse scan cache hit, reusing previous candidates");
+        diagnostics.cache_hit = true;
+        record_diagnostics(diagnostics, &candidates);
+        return Ok(candidates);
+    }
+
     // 4. 提取特征并匹配
+    let feature_start = std::time::Instant::now();
     let scene_desc = extract_features_from_dynamic_image(&cropped_img, 1000).map_err(|e| e.to_string())?;
+    diagnostics.feature_ms = elapsed_ms(feature_start);
+    diagnostics.scene_keypoints = scene_desc.rows();
     if scene_desc.empty() {
-        return Ok(None);
+        record_diagnostics(diagnostics, &[]);
+        return Ok(Vec::new());
     }
     
     // 5. 对比所有模板
-    let cache = TEMPLATE_CACHE.get().ok_or("Templates not loaded")?;
+    let full_cache = monster_templates().ok_or("Templates not loaded")?;
+    let cache = prefilter_by_phash(&cropped_img, full_cache.iter().collect());
     log_to_file(&format!("Scanning against {} templates", cache.len()));
-    let mut results: Vec<(String, usize, f32)> = Vec::new(); // (Name, Matches, Confidence)
+    let match_start = std::time::Instant::now();
+    let scene_rows = scene_desc.rows();
+    let mut results: Vec<(String, usize, f32)> = par_match_templates(&scene_desc, &cache)?
+        .into_iter()
+        .map(|(template, matches)| {
+            // 计算置信度（公式见 normalized_confidence）
+            let confidence = normalized_confidence(matches, scene_rows, template.descriptor_rows);
+            (template.name.clone(), matches, confidence)
+        })
+        .collect(); // (Name, Matches, Confidence)
+    log_to_file(&format!("Matched {} templates in {:?}", cache.len(), match_start.elapsed()));
+    diagnostics.match_ms = elapsed_ms(match_start);
+    diagnostics.templates_compared = cache.len();
+    
+    // 6. 排序、按怪物去重
+    results.sort_by(|a, b| b.1.cmp(&a.1).then(b.2.total_cmp(&a.2))); // 按匹配数降序
+
+    let mut candidates: Vec<(String, usize, f32)> = Vec::new();
+    for (name, matches, confidence) in results {
+        let base_name = monster_base_name(&name);
+        if candidates.iter().any(|c| c.0 == base_name) { continue; }
+        candidates.push((base_name, matches, confidence));
+        if candidates.len() >= MONSTER_CANDIDATE_LIMIT { break; }
+    }
 
-    for template in cache {
-        if template.descriptors.is_empty() { continue; }
+    if let Ok(mut cache) = MOUSE_SCAN_CACHE.lock() {
+        *cache = Some(MouseScanCache {
+            at: std::time::Instant::now(),
+            mouse: (mouse_x, mouse_y),
+            signature,
+            candidates: candidates.clone(),
+        });
+    }
+    record_diagnostics(diagnostics, &candidates);
+    Ok(candidates)
+}
 
-        use opencv::core::CV_8U;
-        // 重建模板描述符
-        let mut template_desc = match unsafe { Mat::new_rows_cols(template.descriptor_rows, template.descriptor_cols, CV_8U) } {
-            Ok(m) => m,
-            Err(e) => {
-                log_to_file(&format!("OpenCV Error creating Mat for template {}: {}", template.name, e));
-                continue;
-            }
-        };
-        if template.descriptors.len() == (template.descriptor_rows * template.descriptor_cols) as usize {
-            unsafe {
-                std::ptr::copy_nonoverlapping(template.descriptors.as_ptr(), template_desc.data_mut() as *mut u8, template.descriptors.len());
-            }
-        } else {
-            continue;
-        }
+// 模板名 "名称_DayN" -> "名称"
+fn monster_base_name(name: &str) -> String {
+    name.split("_Day").next().unwrap_or(name).to_string()
+}
 
-        if let Ok(matches) = match_orb_descriptors(&scene_desc, &template_desc) {
-            let temp_kp_count = template.descriptor_rows as f32;
-            let scene_kp_count = scene_desc.rows() as f32;
-            
-            // 计算置信度
-            let min_kp = temp_kp_count.min(scene_kp_count);
-            let confidence = if min_kp > 0.0 {
-                 matches as f32 / min_kp * 100.0
-            } else { 0.0 };
-            
-            results.push((template.name.clone(), matches, confidence));
+// 阈值判断: 第一名匹配数 > MONSTER_MIN_MATCHES 且 > MONSTER_MARGIN_RATIO 倍第二名才算识别成功
+const MONSTER_MIN_MATCHES: usize = 25;
+const MONSTER_MARGIN_RATIO: f32 = 1.5;
+
+// 未达标时第一名至少要有这么多匹配点才值得让用户选择；低于该值多半是鼠标下没有怪物时的噪声匹配
+const MONSTER_CANDIDATE_MIN_MATCHES: usize = MONSTER_MIN_MATCHES / 2;
+
+pub fn has_candidate_signal(candidates: &[(String, usize, f32)]) -> bool {
+    candidates.first().is_some_and(|c| c.1 >= MONSTER_CANDIDATE_MIN_MATCHES)
+}
+
+// 鼠标指向识别的结果状态：未达标时也带上最接近的候选和差距，前端据此提示用户是否手动指定
+#[derive(Debug, Clone, Serialize)]
+pub struct MonsterMatchStatus {
+    pub matched: bool,
+    // 达标时的识别结果（陷阱类可能是 | 分隔的多个名称）
+    pub name: Option<String>,
+    pub best_name: Option<String>,
+    pub best_matches: usize,
+    pub second_matches: usize,
+    // 第一名需要达到的匹配点数（同时满足最低点数和领先第二名的倍数）
+    pub threshold: usize,
+    pub message: String,
+}
+
+pub fn monster_match_status(candidates: &[(String, usize, f32)]) -> MonsterMatchStatus {
+    let best = candidates.first();
+    let best_matches = best.map(|c| c.1).unwrap_or(0);
+    let second_matches = candidates.get(1).map(|c| c.1).unwrap_or(0);
+    let threshold = (MONSTER_MIN_MATCHES + 1).max((MONSTER_MARGIN_RATIO * second_matches as f32).floor() as usize + 1);
+    let name = pick_confident_monster(candidates);
+    let message = match (best, &name) {
+        (None, _) => "截图区域内没有提取到可匹配的特征".to_string(),
+        (Some(b), Some(_)) => format!("识别成功: {} (匹配 {} 点)", b.0, b.1),
+        (Some(b), None) if best_matches <= MONSTER_MIN_MATCHES => {
+            format!("最接近的是 {}，匹配 {} 点，差 {} 点达标", b.0, b.1, threshold - best_matches)
         }
+        (Some(b), None) => {
+            format!("最接近的是 {}（{} 点），但与第二名（{} 点）差距不足，需要 {} 点", b.0, b.1, second_matches, threshold)
+        }
+    };
+    MonsterMatchStatus {
+        matched: name.is_some(),
+        name,
+        best_name: best.map(|c| c.0.clone()),
+        best_matches,
+        second_matches,
+        threshold,
+        message,
     }
-    
-    // 6. 排序和阈值判断
-    results.sort_by(|a, b| b.1.cmp(&a.1)); // 按匹配数降序
+}
 
-    if results.is_empty() { return Ok(None); }
+pub fn pick_confident_monster(candidates: &[(String, usize, f32)]) -> Option<String> {
+    let top1 = candidates.first()?;
+    let top2_score = candidates.get(1).map(|c| c.1 as f32).unwrap_or(0.0);
 
-    let top1 = &results[0];
-    let top2_score = if results.len() > 1 { results[1].1 as f32 } else { 0.0 };
-    
-    // 阈值检查: 匹配数 > 25 且 Top1 > 1.5 * Top2
-    if top1.1 > 25 && (top1.1 as f32 > 1.5 * top2_score) {
-        println!("鼠标指向识别成功: {} (匹配: {}, 2nd: {})", top1.0, top1.1, top2_score);
+    if top1.1 > MONSTER_MIN_MATCHES && (top1.1 as f32 > MONSTER_MARGIN_RATIO * top2_score) {
+        println!("鼠标指向识别成功: {} (匹配: {}, 置信度: {:.1}%, 2nd: {})", top1.0, top1.1, top1.2, top2_score);
         
         // 关键改进：处理“陷阱”类多重匹配
         // 如果识别结果包含“陷阱”，则寻找所有同类型的陷阱变体并一起作为结果返回
-        let base_name = if top1.0.contains("_Day") {
-            top1.0.split("_Day").next().unwrap_or(&top1.0).to_string()
-        } else {
-            top1.0.clone()
-        };
+        let base_name = &top1.0;
 
         if base_name.contains("陷阱") {
             if base_name.contains("吹箭枪陷阱") {
-                return Ok(Some("毒素 吹箭枪陷阱|黑曜石 吹箭枪陷阱|炽焰 吹箭枪陷阱".to_string()));
+                return Some("毒素 吹箭枪陷阱|黑曜石 吹箭枪陷阱|炽焰 吹箭枪陷阱".to_string());
             } else if base_name.contains("铁蒺藜陷阱") {
-                return Ok(Some("炽焰 铁蒺藜陷阱|黑曜石 铁蒺藜陷阱|毒素 铁蒺藜陷阱".to_string()));
+                return Some("炽焰 铁蒺藜陷阱|黑曜石 铁蒺藜陷阱|毒素 铁蒺藜陷阱".to_string());
             } else if base_name.contains("滚石陷阱") {
-                return Ok(Some("毒素 滚石陷阱|黑曜石 滚石陷阱|炽焰 滚石陷阱".to_string()));
+                return Some("毒素 滚石陷阱|黑曜石 滚石陷阱|炽焰 滚石陷阱".to_string());
             }
         }
 
-        return Ok(Some(base_name));
+        return Some(base_name.clone());
     }
 
-    Ok(None)
+    None
 }
 
-pub fn recognize_monsters(day_filter: Option<String>) -> Result<Vec<MonsterRecognitionResult>, String> {
-    use xcap::Window;
-    use std::time::Instant;
+// 独占全屏下窗口截图可能失败或返回全黑/零尺寸图像，前端据此提示改用无边框窗口模式
+pub const BLACK_CAPTURE_ERROR: &str = "BlackCapture";
+
+// 标题或进程名包含 The Bazaar，且不含任一排除关键字（配置 excluded_window_titles，不区分大小写）
+fn is_game_window(w: &xcap::Window, excluded: &[String]) -> bool {
+    let title = w.title().to_lowercase();
+    let app_name = w.app_name().to_lowercase();
+    let is_bazaar = title.contains("the bazaar") || title.contains("thebazaar") ||
+                    app_name.contains("the bazaar") || app_name.contains("thebazaar");
+    let is_excluded = excluded.iter()
+        .map(|e| e.trim().to_lowercase())
+        .any(|e| !e.is_empty() && (title.contains(&e) || app_name.contains(&e)));
+    is_bazaar && !is_excluded
+}
 
-    let start_total = Instant::now();
+// 统一的游戏窗口查找；cursor 为 Some 时只接受包含该点的窗口
+pub fn find_game_window(cursor: Option<(i32, i32)>) -> Result<Option<xcap::Window>, String> {
+    let excluded = crate::load_state().excluded_window_titles;
+    let windows = xcap::Window::all().map_err(|e| e.to_string())?;
+    Ok(windows.into_iter().find(|w| {
+        is_game_window(w, &excluded) && match cursor {
+            Some((x, y)) => x >= w.x() && x < w.x() + w.width() as i32 && y >= w.y() && y < w.y() + w.height() as i32,
+            None => true,
+        }
+    }))
+}
 
-    // 截图逻辑
-    let windows = Window::all().map_err(|e| e.to_string())?;
-    let bazaar_window = windows.into_iter().find(|w| {
-        let title = w.title().to_lowercase();
-        let app_name = w.app_name().to_lowercase();
-        
-        let is_excluded = 
-            title.contains("visual studio code") || app_name.contains("visual studio code") ||
-            title.contains("obs") || app_name.contains("obs") ||
-            title.contains("mediaplayer") || app_name.contains("mediaplayer") ||
-            title.contains("bazaarhelper") || app_name.contains("bazaarhelper");
+// 抽样检查：零尺寸或所有采样像素都接近纯黑
+fn is_blank_capture(img: &image::RgbaImage) -> bool {
+    let (w, h) = img.dimensions();
+    if w == 0 || h == 0 { return true; }
+    let step_x = (w / 64).max(1);
+    let step_y = (h / 64).max(1);
+    for y in (0..h).step_by(step_y as usize) {
+        for x in (0..w).step_by(step_x as usize) {
+            let p = img.get_pixel(x, y);
+            if p[0] > 8 || p[1] > 8 || p[2] > 8 {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+// 截取游戏窗口，返回 (截图, 截图左上角的屏幕坐标)；结果为空白时按配置 fullscreen_capture_fallback
+// 改为截取窗口所在的显示器，并裁剪到窗口与显示器的交集，调用方按返回的原点换算坐标
+pub fn capture_game_window(window: &xcap::Window) -> Result<(image::RgbaImage, i32, i32), String> {
+    let captured = window.capture_image();
+    let failure = match captured {
+        Ok(img) if !is_blank_capture(&img) => return Ok((img, window.x(), window.y())),
+        Ok(img) => format!("窗口截图为空白 ({}x{})", img.width(), img.height()),
+        Err(e) => format!("窗口截图失败: {}", e),
+    };
+    println!("[Capture] {}, game may be in exclusive fullscreen", failure);
+    log_to_file(&format!("[Capture] {}", failure));
+
+    if crate::load_state().fullscreen_capture_fallback {
+        let cx = window.x() + window.width() as i32 / 2;
+        let cy = window.y() + window.height() as i32 / 2;
+        let monitor = xcap::Monitor::all().ok().and_then(|ms| ms.into_iter().find(|m| {
+            cx >= m.x() && cx < m.x() + m.width() as i32 && cy >= m.y() && cy < m.y() + m.height() as i32
+        }));
+        if let Some(m) = monitor {
+            if let Ok(img) = m.capture_image() {
+                if !is_blank_capture(&img) {
+                    // 窗口矩形与显示器截图的交集（相对显示器左上角）
+                    let left = (window.x() - m.x()).clamp(0, img.width() as i32) as u32;
+                    let top = (window.y() - m.y()).clamp(0, img.height() as i32) as u32;
+                    let right = (window.x() + window.width() as i32 - m.x()).clamp(0, img.width() as i32) as u32;
+                    let bottom = (window.y() + window.height() as i32 - m.y()).clamp(0, img.height() as i32) as u32;
+                    if right > left && bottom > top {
+                        let (origin_x, origin_y) = (m.x() + left as i32, m.y() + top as i32);
+                        println!("[Capture] Fell back to monitor capture, window area {}x{} at ({}, {})", right - left, bottom - top, origin_x, origin_y);
+                        let cropped = image::imageops::crop_imm(&img, left, top, right - left, bottom - top).to_image();
+                        return Ok((cropped, origin_x, origin_y));
+                    }
+                }
+            }
+        }
+    }
+
+    Err(format!("{}: {}。游戏可能处于独占全屏模式，请在游戏设置中切换为无边框窗口模式", BLACK_CAPTURE_ERROR, failure))
+}
+
+// 窗口模式下截图包含标题栏/边框：返回客户区在截图中的 (x, y, w, h)
+#[cfg(target_os = "windows")]
+fn client_area_in_capture(window: &xcap::Window) -> Option<(u32, u32, u32, u32)> {
+    use windows::Win32::Foundation::{HWND, POINT, RECT};
+    use windows::Win32::Graphics::Gdi::ClientToScreen;
+    use windows::Win32::UI::WindowsAndMessaging::GetClientRect;
+
+    let hwnd = HWND(window.id() as usize as *mut std::ffi::c_void);
+    let mut rect = RECT::default();
+    let mut origin = POINT { x: 0, y: 0 };
+    unsafe {
+        GetClientRect(hwnd, &mut rect).ok()?;
+        if !ClientToScreen(hwnd, &mut origin).as_bool() { return None; }
+    }
+
+    let inset_x = (origin.x - window.x()).max(0) as u32;
+    let inset_y = (origin.y - window.y()).max(0) as u32;
+    let client_w = (rect.right - rect.left).max(0) as u32;
+    let client_h = (rect.bottom - rect.top).max(0) as u32;
+    Some((inset_x, inset_y, client_w, client_h))
+}
+
+#[cfg(not(target_os = "windows"))]
+fn client_area_in_capture(_window: &xcap::Window) -> Option<(u32, u32, u32, u32)> {
+    None
+}
+
+// 裁掉 capture_inset 后的画面在完整截图中的位置，默认识别区域按完整截图标定
+#[derive(Debug, Clone, Copy)]
+struct SceneFrame {
+    offset_x: u32,
+    offset_y: u32,
+    full_width: u32,
+    full_height: u32,
+}
 
-        let is_bazaar = 
-            title.contains("the bazaar") || title.contains("thebazaar") || 
-            app_name.contains("the bazaar") || app_name.contains("thebazaar");
+// 截取游戏窗口（找不到时退回主显示器），供怪物槽位识别与预览共用
+fn capture_monster_scene() -> Result<(DynamicImage, SceneFrame), String> {
+    use std::time::Instant;
 
-        is_bazaar && !is_excluded
-    });
+    // 截图逻辑（排除列表见配置 excluded_window_titles）
+    let bazaar_window = find_game_window(None)?;
 
     let start_capture = Instant::now();
     let screenshot = if let Some(window) = bazaar_window {
         println!("[OpenCV Recognition] Found window: '{}' (App: '{}'), Pos: {:?}, Size: {:?}", 
                  window.title(), window.app_name(), (window.x(), window.y()), (window.width(), window.height()));
-        window.capture_image().map_err(|e| {
+        let (captured, origin_x, origin_y) = capture_game_window(&window).map_err(|e| {
             println!("[OpenCV Recognition] Error capturing window: {}. Ensure screen recording permission is granted.", e);
-            e.to_string()
-        })?
+            e
+        })?;
+
+        // 窗口模式：裁掉标题栏和边框，使槽位按游戏内容区计算；
+        // 客户区偏移相对窗口左上角，显示器降级截图被裁掉的部分要扣除
+        let shift_x = (origin_x - window.x()).max(0) as u32;
+        let shift_y = (origin_y - window.y()).max(0) as u32;
+        let client_area = client_area_in_capture(&window)
+            .map(|(x, y, w, h)| (x.saturating_sub(shift_x), y.saturating_sub(shift_y), w, h));
+        match client_area {
+            Some((x, y, w, h)) if (x, y) != (0, 0) || w < captured.width() || h < captured.height() => {
+                let w = w.min(captured.width().saturating_sub(x));
+                let h = h.min(captured.height().saturating_sub(y));
+                if w > 0 && h > 0 {
+                    println!("[OpenCV Recognition] Windowed mode, client area inset: ({}, {}), size: {}x{}", x, y, w, h);
+                    image::imageops::crop_imm(&captured, x, y, w, h).to_image()
+                } else {
+                    captured
+                }
+            }
+            _ => captured,
+        }
     } else {
         println!("[OpenCV Recognition] 'The Bazaar' window not found, falling back to monitor 0");
         use xcap::Monitor;
@@ -1099,38 +2699,181 @@ pub fn recognize_monsters(day_filter: Option<String>) -> Result<Vec<MonsterRecog
     };
     println!("[Timer] 截图耗时: {:?}", start_capture.elapsed());
 
-    let img = DynamicImage::ImageRgba8(screenshot);
+    // 裁掉 capture_inset，同时记下保留区域的位置，供 compute_monster_slots 换算默认识别区域
+    let (full_width, full_height) = screenshot.dimensions();
+    let (img, offset_x, offset_y) = apply_capture_inset(DynamicImage::ImageRgba8(screenshot));
+    Ok((img, SceneFrame { offset_x, offset_y, full_width, full_height }))
+}
+
+// 怪物识别区域（相对裁掉 capture_inset 后截图宽高的比例 0~1），由用户在设置里框选；
+// 未设置时使用按完整截图标定的 DEFAULT
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MonsterScanRegion {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+}
+
+impl MonsterScanRegion {
+    // 默认布局：水平 20%~80%，竖直 10%~60%
+    pub const DEFAULT: MonsterScanRegion = MonsterScanRegion { x: 0.20, y: 0.10, w: 0.60, h: 0.50 };
+
+    pub fn validate(&self) -> Result<(), String> {
+        let in_unit = |v: f32| (0.0..=1.0).contains(&v);
+        if !in_unit(self.x) || !in_unit(self.y) || !in_unit(self.w) || !in_unit(self.h) {
+            return Err(format!("Monster scan region values must be in [0, 1], got {:?}", self));
+        }
+        if self.w < 0.05 || self.h < 0.05 {
+            return Err(format!("Monster scan region is too small: {:?}", self));
+        }
+        if self.x + self.w > 1.001 || self.y + self.h > 1.001 {
+            return Err(format!("Monster scan region exceeds the screenshot: {:?}", self));
+        }
+        Ok(())
+    }
+}
+
+// 怪物槽位：用户框选的区域来自裁剪后的预览画面，直接按当前画面切分；
+// 默认区域按完整截图标定，先在完整截图上切分，再与 capture_inset 保留区域求交并换算到当前画面
+fn compute_monster_slots(width: u32, height: u32, frame: &SceneFrame) -> Vec<(u32, u32, u32, u32)> {
+    if let Some(region) = crate::load_state().monster_scan_region {
+        return split_scan_region(&region, width, height);
+    }
+    split_scan_region(&MonsterScanRegion::DEFAULT, frame.full_width, frame.full_height)
+        .into_iter()
+        .filter_map(|(x, y, w, h)| {
+            let x1 = x.max(frame.offset_x);
+            let y1 = y.max(frame.offset_y);
+            let x2 = (x + w).min(frame.offset_x + width);
+            let y2 = (y + h).min(frame.offset_y + height);
+            (x2 > x1 && y2 > y1).then(|| (x1 - frame.offset_x, y1 - frame.offset_y, x2 - x1, y2 - y1))
+        })
+        .collect()
+}
+
+// 默认区域与 capture_inset 保留区域的交集，按当前画面的比例表示，供设置页预览显示
+fn default_scan_region_in(width: u32, height: u32, frame: &SceneFrame) -> MonsterScanRegion {
+    let d = MonsterScanRegion::DEFAULT;
+    let to_frame = |v: f32, full: u32, offset: u32, size: u32| {
+        ((v * full as f32 - offset as f32) / size.max(1) as f32).clamp(0.0, 1.0)
+    };
+    let x1 = to_frame(d.x, frame.full_width, frame.offset_x, width);
+    let y1 = to_frame(d.y, frame.full_height, frame.offset_y, height);
+    let x2 = to_frame(d.x + d.w, frame.full_width, frame.offset_x, width);
+    let y2 = to_frame(d.y + d.h, frame.full_height, frame.offset_y, height);
+    MonsterScanRegion { x: x1, y: y1, w: x2 - x1, h: y2 - y1 }
+}
+
+// 识别区域水平三等分，返回 (x, y, w, h)
+fn split_scan_region(region: &MonsterScanRegion, width: u32, height: u32) -> Vec<(u32, u32, u32, u32)> {
+    let region_y = (height as f32 * region.y) as u32;
+    let region_h = (height as f32 * region.h) as u32;
+    let total_region_w = (width as f32 * region.w) as u32;
+    let region_x_start = (width as f32 * region.x) as u32;
+
+    let slot_w = total_region_w / 3;
+    let slot_h = region_h;
+
+    (0..3u32)
+        .map(|i| (region_x_start + i * slot_w, region_y, slot_w, slot_h))
+        .filter(|&(x, y, w, h)| x + w <= width && y + h <= height)
+        .collect()
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct MonsterSlotPreview {
+    pub position: u8,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub image_base64: String, // PNG
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct MonsterScanPreview {
+    // 原始截图尺寸
+    pub width: u32,
+    pub height: u32,
+    pub region: MonsterScanRegion,
+    pub image_base64: String, // PNG，宽度缩到 MONSTER_SCAN_PREVIEW_WIDTH 以内
+}
+
+const MONSTER_SCAN_PREVIEW_WIDTH: u32 = 1280;
+
+// 整张截图的缩略图和当前识别区域，供设置页框选 monster_scan_region
+pub fn preview_monster_scan_region() -> Result<MonsterScanPreview, String> {
+    use base64::Engine;
+
+    let (img, frame) = capture_monster_scene()?;
+    let (width, height) = img.dimensions();
+    let thumb = if width > MONSTER_SCAN_PREVIEW_WIDTH {
+        img.resize(MONSTER_SCAN_PREVIEW_WIDTH, u32::MAX, FilterType::Triangle)
+    } else {
+        img
+    };
+    let mut png = Vec::new();
+    thumb.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .map_err(|e| e.to_string())?;
+    Ok(MonsterScanPreview {
+        width,
+        height,
+        region: crate::load_state().monster_scan_region.unwrap_or_else(|| default_scan_region_in(width, height, &frame)),
+        image_base64: base64::engine::general_purpose::STANDARD.encode(&png),
+    })
+}
+
+// 仅截图并切分槽位，不做匹配，用于确认当前分辨率下槽位是否框住了怪物
+pub fn preview_monster_slots() -> Result<Vec<MonsterSlotPreview>, String> {
+    use base64::Engine;
+
+    let (img, frame) = capture_monster_scene()?;
+    let (width, height) = img.dimensions();
+
+    let mut previews = Vec::new();
+    for (i, (x, y, w, h)) in compute_monster_slots(width, height, &frame).into_iter().enumerate() {
+        let slice = img.crop_imm(x, y, w, h);
+        let mut png = Vec::new();
+        slice.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .map_err(|e| e.to_string())?;
+        previews.push(MonsterSlotPreview {
+            position: (i + 1) as u8,
+            x, y,
+            width: w,
+            height: h,
+            image_base64: base64::engine::general_purpose::STANDARD.encode(&png),
+        });
+    }
+    println!("[OpenCV Recognition] Slot preview: {}x{} screen, {} slots", width, height, previews.len());
+    Ok(previews)
+}
+
+pub fn recognize_monsters(day_filter: Option<String>) -> Result<Vec<MonsterRecognitionResult>, String> {
+    use std::time::Instant;
+
+    let start_total = Instant::now();
+
+    let (img, frame) = capture_monster_scene()?;
     let (width, height) = img.dimensions();
 
-    let full_cache = TEMPLATE_CACHE.get().ok_or("Templates not loaded")?;
+    let full_cache = monster_templates().ok_or("Templates not loaded")?;
     let cache: Vec<&TemplateCache> = if let Some(ref target_day) = day_filter {
-        if target_day == "Day 10+" {
-            full_cache.iter().filter(|t| t.day == "Day 10" || t.day == "Day 10+").collect()
-        } else {
-            full_cache.iter().filter(|t| t.day == *target_day).collect()
-        }
+        // 旧缓存里可能还是 "Day 10" / "Day 12" 等原始标签，统一按分组比较
+        let target = crate::canonical_day_label(target_day);
+        full_cache.iter().filter(|t| crate::canonical_day_label(&t.day) == target).collect()
     } else {
         full_cache.iter().collect()
     };
     println!("[OpenCV Recognition] 开始匹配，库中共有 {} 个目标怪兽", cache.len());
 
     let mut results = Vec::new();
-    let region_y = (height as f32 * 0.10) as u32;
-    let region_h = (height as f32 * 0.50) as u32;
-    let total_region_w = (width as f32 * 0.60) as u32;
-    let region_x_start = (width as f32 * 0.20) as u32;
-
-    let slot_w = total_region_w / 3;
-    let slot_h = region_h;
 
     let start_match = Instant::now();
     save_debug_image(&img, "monster_full_screenshot");
 
-    for i in 0..3 {
+    for (i, (x, y, slot_w, slot_h)) in compute_monster_slots(width, height, &frame).into_iter().enumerate() {
         let start_slot = Instant::now();
-        let x = region_x_start + (i as u32 * slot_w);
-        let y = region_y;
-        if x + slot_w > width || y + slot_h > height { continue; }
 
         let slice = img.crop_imm(x, y, slot_w, slot_h);
         save_debug_image(&slice, &format!("monster_slot_{}", i + 1));
@@ -1149,63 +2892,24 @@ pub fn recognize_monsters(day_filter: Option<String>) -> Result<Vec<MonsterRecog
             continue;
         }
 
-        let mut best_name = "Unknown".to_string();
-        let mut max_matches = 0;
-        let mut best_score = 0.0f32;
-
-        // 遍历所有模板进行匹配
-        for template in &cache {
-            if template.descriptors.is_empty() {
-                continue;
-            }
-
-            // 将模板描述符从字节数组重建为 Mat
-            // 创建临时 Vec 并 clone 到 Mat
-            use opencv::core::CV_8U;
-            let rows = template.descriptor_rows;
-            let cols = template.descriptor_cols;
-            
-            // 创建空 Mat 并拷贝数据
-            let mut template_desc = match unsafe { Mat::new_rows_cols(rows, cols, CV_8U) } {
-                Ok(mat) => mat,
-                Err(e) => {
-                    println!("创建 Mat 失败: {}", e);
-                    continue;
-                }
-            };
-            if template.descriptors.len() == (rows * cols) as usize {
-                unsafe {
-                    let src_ptr = template.descriptors.as_ptr();
-                    let dst_ptr = template_desc.data_mut() as *mut u8;
-                    std::ptr::copy_nonoverlapping(src_ptr, dst_ptr, template.descriptors.len());
-                }
-            } else {
-                continue;
-            }
-
-            // 使用 ORB 匹配
-            match match_orb_descriptors(&scene_descriptors, &template_desc) {
-                Ok(matches) => {
-                    if matches > max_matches {
-                        max_matches = matches;
-                        best_name = template.name.clone();
-                        
-                        // 计算置信度
-                        let scene_kp_count = scene_descriptors.rows() as f32;
-                        let template_kp_count = template.descriptor_rows as f32;
-                        let min_kp = scene_kp_count.min(template_kp_count);
-                        best_score = if min_kp > 0.0 {
-                            matches as f32 / min_kp
-                        } else {
-                            0.0
-                        };
-                    }
-                }
-                Err(e) => {
-                    println!("[警告] 匹配 {} 时出错: {}", template.name, e);
-                }
+        // 未按天数过滤时才做哈希预筛选
+        let slot_cache = if day_filter.is_none() { prefilter_by_phash(&slice, cache.clone()) } else { cache.clone() };
+
+        // 并行匹配所有模板，归约出匹配点数最多的一个；结果保持模板顺序，
+        // 平局时保留最先出现的模板（max_by_key 会取最后一个，与原先串行循环的 `>` 不一致）
+        let best = par_match_templates(&scene_descriptors, &slot_cache)?
+            .into_iter()
+            .reduce(|best, cur| if cur.1 > best.1 { cur } else { best });
+
+        let (best_name, max_matches, best_score) = match best {
+            Some((template, matches)) if matches > 0 => {
+                // 计算置信度
+                let min_kp = (scene_descriptors.rows() as f32).min(template.descriptor_rows as f32);
+                let score = if min_kp > 0.0 { matches as f32 / min_kp } else { 0.0 };
+                (template.name.clone(), matches, score)
             }
-        }
+            _ => ("Unknown".to_string(), 0, 0.0f32),
+        };
 
         println!("[Slot {}] OpenCV ORB 识别得出: '{}', 匹配点数: {}, 置信度: {:.2}%, 耗时: {:?}", 
                  i + 1, best_name, max_matches, best_score * 100.0, start_slot.elapsed());
@@ -1250,17 +2954,19 @@ pub fn save_debug_image(img: &DynamicImage, name: &str) {
 pub async fn preload_card_templates_async(resources_dir: PathBuf, cache_dir: PathBuf) -> Result<(), String> {
     log_to_file(&format!("Start loading card templates. Resource Dir: {:?}, Cache Dir: {:?}", resources_dir, cache_dir));
     
-    let cache_file = cache_dir.join("card_features_opencv.bin");
-    let bundled_cache = resources_dir.join("card_features_opencv.bin");
+    // v2: 模板增加 HSV 直方图
+    let cache_file = cache_dir.join(feature_cache_name("card_features_opencv_v2.bin"));
+    let bundled_cache = resources_dir.join(feature_cache_name("card_features_opencv_v2.bin"));
 
     // 1. 优先从资源目录加载
     if bundled_cache.exists() {
         if let Ok(data) = std::fs::read(&bundled_cache) {
-            if let Ok(cached_templates) = bincode::deserialize::<Vec<TemplateCache>>(&data) {
-                if !cached_templates.is_empty() {
+            match decode_template_cache::<TemplateCache>(&data, CARD_TEMPLATE_FEATURES) {
+                Err(e) => log_to_file(&format!("Rejected bundled card cache {:?}: {}", bundled_cache, e)),
+                Ok(cached_templates) => if !cached_templates.is_empty() {
                     log_to_file(&format!("Loaded {} card templates from bundled cache", cached_templates.len()));
                     println!("[Card Templates] Loaded {} templates from bundled cache: {:?}", cached_templates.len(), bundled_cache);
-                    let _ = CARD_TEMPLATE_CACHE.set(cached_templates);
+                    set_card_templates(cached_templates);
                     return Ok(());
                 }
             }
@@ -1270,18 +2976,35 @@ pub async fn preload_card_templates_async(resources_dir: PathBuf, cache_dir: Pat
     // 2. 尝试从 AppData 缓存加载
     if cache_file.exists() {
         if let Ok(data) = std::fs::read(&cache_file) {
-            if let Ok(cached_templates) = bincode::deserialize::<Vec<TemplateCache>>(&data) {
-                if !cached_templates.is_empty() {
+            match decode_template_cache::<TemplateCache>(&data, CARD_TEMPLATE_FEATURES) {
+                Err(e) => log_to_file(&format!("Rejected card cache {:?}: {}", cache_file, e)),
+                Ok(cached_templates) => if !cached_templates.is_empty() {
                     log_to_file(&format!("Loaded {} card templates from OpenCV cache", cached_templates.len()));
                     println!("[Card Templates] Loaded {} templates from cache: {:?}", cached_templates.len(), cache_file);
-                    let _ = CARD_TEMPLATE_CACHE.set(cached_templates);
+                    set_card_templates(cached_templates);
                     return Ok(());
                 }
             }
         }
     }
 
-    // 3. 从 items_db.json 加载并计算
+    // 3. 迁移旧格式（无指纹、无直方图）的卡牌缓存，省去全量重新提取特征
+    let legacy_caches = [cache_dir.join("card_features_opencv.bin"), resources_dir.join("card_features_opencv.bin")];
+    for legacy_path in legacy_caches.iter().filter(|p| p.exists()) {
+        let migrated = std::fs::read(legacy_path).ok().and_then(|data| migrate_legacy_card_cache(&data, &resources_dir));
+        if let Some(cache) = migrated {
+            log_to_file(&format!("Migrated {} card templates from legacy cache {:?}", cache.len(), legacy_path));
+            println!("[Card Templates] Migrated {} templates from legacy cache: {:?}", cache.len(), legacy_path);
+            if let Ok(serialized) = encode_template_cache(&cache, CARD_TEMPLATE_FEATURES) {
+                let _ = std::fs::create_dir_all(&cache_dir);
+                let _ = std::fs::write(&cache_file, &serialized);
+            }
+            set_card_templates(cache);
+            return Ok(());
+        }
+    }
+
+    // 4. 从 items_db.json 加载并计算
     let db_path = resources_dir.join("items_db.json");
     if !db_path.exists() {
         return Err(format!("items_db.json not found at {:?}", db_path));
@@ -1312,9 +3035,9 @@ pub async fn preload_card_templates_async(resources_dir: PathBuf, cache_dir: Pat
     
     let cache: Vec<TemplateCache> = tasks.into_par_iter().filter_map(|(name, id, path)| {
         let path_str = path.to_str()?;
-        // 用户要求特征点少一些, 用 300
-        match extract_features_orb(path_str, 300) {
+        match extract_features_orb(path_str, CARD_TEMPLATE_FEATURES) {
             Ok((keypoints, descriptors, rows, cols)) => {
+                let hsv_hist = image::open(&path).map(|img| hsv_histogram(&img)).unwrap_or_default();
                 Some(TemplateCache {
                     name, // 这里存中文名
                     day: id, // 这里借用 day 字段存 ID
@@ -1325,6 +3048,9 @@ pub async fn preload_card_templates_async(resources_dir: PathBuf, cache_dir: Pat
                     sample_png: Vec::new(), 
                     sample_w: 0,
                     sample_h: 0,
+                    phash: 0, // 卡牌不做哈希预筛选
+                    hsv_hist,
+                    source: None,
                 })
             }
             Err(_) => None,
@@ -1334,42 +3060,55 @@ pub async fn preload_card_templates_async(resources_dir: PathBuf, cache_dir: Pat
     log_to_file(&format!("Successfully built cache for {} cards", cache.len()));
     
     // 保存到文件以便下次加速
-    if let Ok(serialized) = bincode::serialize(&cache) {
+    if let Ok(serialized) = encode_template_cache(&cache, CARD_TEMPLATE_FEATURES) {
         let _ = std::fs::write(&cache_file, &serialized);
         let _ = std::fs::write(&bundled_cache, &serialized);
         log_to_file(&format!("Saved card templates cache: appdata={:?}, resources={:?}", cache_file, bundled_cache));
         println!("[Card Templates] Cache saved: appdata={:?}, resources={:?}", cache_file, bundled_cache);
     }
 
-    let _ = CARD_TEMPLATE_CACHE.set(cache);
+    set_card_templates(cache);
     Ok(())
 }
 
+// 卡牌候选排序方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CandidateOrder {
+    #[default]
+    MatchCount,
+    Confidence,
+}
+
+// recognize_card_at_mouse 的可选参数，缺省字段保持原有行为（最多 3 个，匹配点数 > 12 且置信度 > 0.12）
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CardRecognitionOptions {
+    pub max_candidates: usize,
+    pub min_matches: usize,
+    pub min_confidence: f32,
+    pub order_by: CandidateOrder,
+}
+
+impl Default for CardRecognitionOptions {
+    fn default() -> Self {
+        Self { max_candidates: 3, min_matches: 12, min_confidence: 0.12, order_by: CandidateOrder::MatchCount }
+    }
+}
+
 #[tauri::command]
-pub async fn recognize_card_at_mouse() -> Result<Option<serde_json::Value>, String> {
-    use xcap::{Window, Monitor};
-    use enigo::{Enigo, Mouse, Settings};
+pub async fn recognize_card_at_mouse(app: tauri::AppHandle, options: Option<CardRecognitionOptions>) -> Result<Option<serde_json::Value>, String> {
+    use xcap::Monitor;
+    let options = options.unwrap_or_default();
 
     // 1. 获取鼠标位置
-    let enigo = match Enigo::new(&Settings::default()) {
-        Ok(e) => e,
-        Err(e) => return Err(format!("Failed to init Enigo: {:?}", e)),
-    };
-    let (mouse_x, mouse_y) = match enigo.location() {
-        Ok(loc) => loc,
-        Err(e) => return Err(format!("Failed to get mouse location: {:?}", e)),
-    };
+    let (mouse_x, mouse_y) = get_cursor_position()?;
 
     // 2. 截图
-    let windows = Window::all().map_err(|e| e.to_string())?;
-    let bazaar_window = windows.into_iter().find(|w| {
-        let title = w.title().to_lowercase();
-        let app_name = w.app_name().to_lowercase();
-        title.contains("the bazaar") || app_name.contains("the bazaar")
-    });
+    let bazaar_window = find_game_window(None)?;
 
     let (screenshot, win_x, win_y) = if let Some(window) = bazaar_window {
-        (window.capture_image().map_err(|e| e.to_string())?, window.x(), window.y())
+        capture_game_window(&window)?
     } else {
         let monitors = Monitor::all().map_err(|e| e.to_string())?;
         let target_monitor = monitors.into_iter().find(|m| {
@@ -1397,7 +3136,7 @@ pub async fn recognize_card_at_mouse() -> Result<Option<serde_json::Value>, Stri
     let crop_w = if crop_x + target_w > img_w { img_w.saturating_sub(crop_x) } else { target_w };
     let crop_h = if crop_y + target_h > img_h { img_h.saturating_sub(crop_y) } else { target_h };
 
-    if crop_w < 50 || crop_h < 50 { return Err("Invalid crop size".into()); }
+    check_crop_size(crop_w, crop_h)?;
     let mut cropped_img = img.crop_imm(crop_x, crop_y, crop_w, crop_h);
     
     // 4K 优化：针对高分辨率截图，缩减尺寸以加快特征提取和比对（由 512 提升至 800 以保留更多细节）
@@ -1411,11 +3150,12 @@ pub async fn recognize_card_at_mouse() -> Result<Option<serde_json::Value>, Stri
     let scene_desc = extract_features_from_dynamic_image(&cropped_img, 500).map_err(|e| e.to_string())?;
     if scene_desc.empty() { return Ok(None); }
     
-    // 4. 比对
-    let cache = CARD_TEMPLATE_CACHE.get().ok_or("Card templates not loaded")?;
+    // 4. 比对：先按颜色直方图筛掉差异过大的模板，再做 ORB 精匹配
+    let full_cache = card_templates().ok_or("Card templates not loaded")?;
+    let cache = prefilter_by_histogram(&cropped_img, &full_cache);
     let mut results: Vec<(&TemplateCache, usize, f32)> = Vec::new();
 
-    for template in cache {
+    for template in cache.iter().copied() {
         if template.descriptors.is_empty() { continue; }
         use opencv::core::CV_8U;
         let mut template_desc = match unsafe { Mat::new_rows_cols(template.descriptor_rows, template.descriptor_cols, CV_8U) } {
@@ -1431,20 +3171,24 @@ pub async fn recognize_card_at_mouse() -> Result<Option<serde_json::Value>, Stri
         }
     }
     
-    results.sort_by(|a, b| b.1.cmp(&a.1));
+    match options.order_by {
+        CandidateOrder::MatchCount => results.sort_by(|a, b| b.1.cmp(&a.1)),
+        CandidateOrder::Confidence => results.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap_or(std::cmp::Ordering::Equal)),
+    }
 
     // Print raw top 3 candidates for debugging
-    println!("[Card Recognition] Top 3 Candidates:");
+    println!("[Card Recognition] Top 3 Candidates ({:?}):", options.order_by);
     for i in 0..results.len().min(3) {
         let (top, matches, confidence) = results[i];
         println!("  {}. {} (ID: {}) - Matches: {}, Conf: {:.4}", i+1, top.name, top.day, matches, confidence);
     }
 
     let mut matches_found = Vec::new();
-    for i in 0..results.len().min(10) { // 先取前10个候选
+    let pool = options.max_candidates.max(10);
+    for i in 0..results.len().min(pool) { // 先取前10个候选（max_candidates 更大时相应扩大）
         let (top, matches, confidence) = results[i];
-        // 阈值：匹配点数 > 12 且 置信度 > 0.12
-        if matches > 12 && confidence > 0.12 {
+        // 阈值：匹配点数 > min_matches 且 置信度 > min_confidence（默认 12 / 0.12）
+        if matches > options.min_matches && confidence > options.min_confidence {
              matches_found.push(serde_json::json!({
                  "id": top.day, // ID 存储在 day 字段
                  "name": top.name,
@@ -1452,11 +3196,12 @@ pub async fn recognize_card_at_mouse() -> Result<Option<serde_json::Value>, Stri
                  "match_count": matches
              }));
         }
-        if matches_found.len() >= 3 { break; }
+        if matches_found.len() >= options.max_candidates { break; }
     }
 
     if !matches_found.is_empty() {
         println!("[Card Recognition] Found {} matches", matches_found.len());
+        crate::reveal_overlay_on_first_scan(&app);
         return Ok(Some(serde_json::json!(matches_found)));
     }
     
@@ -1489,12 +3234,12 @@ pub async fn load_event_templates(app: tauri::AppHandle) -> Result<(), String> {
     log_to_file("Loading event templates...");
 
     // 1. 尝试从资源目录加载 (Bundled)
-    let feature_bin_path = app.path().resolve("resources/event_features_opencv.bin", tauri::path::BaseDirectory::Resource);
+    let feature_bin_path = app.path().resolve(format!("resources/{}", feature_cache_name("event_features_opencv.bin")), tauri::path::BaseDirectory::Resource);
     if let Ok(bin_path) = feature_bin_path {
          if bin_path.exists() {
              match std::fs::read(&bin_path) {
                  Ok(data) => {
-                     match bincode::deserialize::<Vec<EventTemplateCache>>(&data) {
+                     match decode_template_cache::<EventTemplateCache>(&data, EVENT_TEMPLATE_FEATURES) {
                          Ok(cached_templates) => {
                              if !cached_templates.is_empty() {
                                  println!("Loaded {} event templates from binary package (Resource)", cached_templates.len());
@@ -1518,11 +3263,11 @@ pub async fn load_event_templates(app: tauri::AppHandle) -> Result<(), String> {
 
     // 2. 尝试从 AppCache 加载 (Generated)
     if let Ok(cache_dir) = app.path().app_cache_dir() {
-        let cached_bin = cache_dir.join("event_features_opencv.bin");
+        let cached_bin = cache_dir.join(feature_cache_name("event_features_opencv.bin"));
         if cached_bin.exists() {
             match std::fs::read(&cached_bin) {
                 Ok(data) => {
-                    match bincode::deserialize::<Vec<EventTemplateCache>>(&data) {
+                    match decode_template_cache::<EventTemplateCache>(&data, EVENT_TEMPLATE_FEATURES) {
                         Ok(cached_templates) => {
                              if !cached_templates.is_empty() {
                                  println!("Loaded {} event templates from generated cache", cached_templates.len());
@@ -1547,18 +3292,8 @@ pub async fn load_event_templates(app: tauri::AppHandle) -> Result<(), String> {
     log_to_file("Event cache not found or invalid. Starting generation from source images...");
 
     // 3. 生成特征
-    // 初始化 ORB (增加特征点数量以提高匹配率)
-    let mut orb = ORB::create(
-        1000, // nfeatures (从500提升到1000，提取更多特征点)
-        1.2f32, // scaleFactor
-        8, // nlevels
-        31, // edgeThreshold
-        0, // firstLevel
-        2, // WTA_K
-        opencv::features2d::ORB_ScoreType::HARRIS_SCORE, // scoreType
-        31, // patchSize
-        20 // fastThreshold
-    ).map_err(|e| format!("Failed to create ORB detector: {}", e))?;
+    // 特征提取器 (增加特征点数量以提高匹配率)
+    let extractor = feature_extractor(EVENT_TEMPLATE_FEATURES);
     
     // 读取 event_encounters.json
     let event_json_path = app.path().resolve("resources/event_encounters.json", tauri::path::BaseDirectory::Resource)
@@ -1657,15 +3392,22 @@ pub async fn load_event_templates(app: tauri::AppHandle) -> Result<(), String> {
              // log_to_file(&format!("Loaded empty image for {}", id));
              continue;
         }
+        let img = match preprocess_gray(img) {
+            Ok(img) => img,
+            Err(e) => {
+                log_to_file(&format!("Preprocess failed for {}: {}", id, e));
+                continue;
+            }
+        };
 
         // 计算特征
-        let mut keypoints = Vector::<KeyPoint>::new();
-        let mut descriptors = Mat::default();
-        
-        if let Err(e) = orb.detect_and_compute(&img, &Mat::default(), &mut keypoints, &mut descriptors, false) {
-             log_to_file(&format!("ORB compute failed for {}: {}", id, e));
-             continue;
-        }
+        let (_, descriptors) = match extractor.detect_and_compute(&img) {
+            Ok(result) => result,
+            Err(e) => {
+                log_to_file(&format!("Feature compute failed for {}: {}", id, e));
+                continue;
+            }
+        };
         
         if descriptors.rows() == 0 || descriptors.cols() == 0 {
              // log_to_file(&format!("No descriptors found for {}", id));
@@ -1714,8 +3456,8 @@ pub async fn load_event_templates(app: tauri::AppHandle) -> Result<(), String> {
             if let Err(e) = std::fs::create_dir_all(&cache_dir) {
                  log_to_file(&format!("Failed to create cache dir: {}", e));
             } else {
-                let cache_path = cache_dir.join("event_features_opencv.bin");
-                match bincode::serialize(&templates) {
+                let cache_path = cache_dir.join(feature_cache_name("event_features_opencv.bin"));
+                match encode_template_cache(&templates, EVENT_TEMPLATE_FEATURES) {
                     Ok(data) => {
                         if let Err(e) = std::fs::write(&cache_path, data) {
                              log_to_file(&format!("Failed to write generated cache to file: {}", e));
@@ -1737,34 +3479,17 @@ pub async fn load_event_templates(app: tauri::AppHandle) -> Result<(), String> {
 
 // 识别事件（从鼠标位置）
 #[tauri::command]
-pub async fn recognize_event_at_mouse() -> Result<Option<serde_json::Value>, String> {
+pub async fn recognize_event_at_mouse(app: tauri::AppHandle) -> Result<Option<serde_json::Value>, String> {
     use xcap::Monitor;
 
     // 1. 获取鼠标位置（跨平台）
-    let (mouse_x, mouse_y) = get_mouse_position();
+    let (mouse_x, mouse_y) = get_cursor_position()?;
 
     // 2. 截图
-    let windows = xcap::Window::all().map_err(|e| e.to_string())?;
-    let bazaar_window = windows.into_iter().find(|w| {
-        let title = w.title().to_lowercase();
-        let app_name = w.app_name().to_lowercase();
-        let is_bazaar = title.contains("the bazaar") || app_name.contains("the bazaar") || 
-                        title.contains("thebazaar") || app_name.contains("thebazaar");
-        
-        if is_bazaar {
-            let wx = w.x();
-            let wy = w.y();
-            let ww = w.width();
-            let wh = w.height();
-            mouse_x >= wx && mouse_x < wx + ww as i32 &&
-            mouse_y >= wy && mouse_y < wy + wh as i32
-        } else {
-            false
-        }
-    });
+    let bazaar_window = find_game_window(Some((mouse_x, mouse_y)))?;
 
     let (screenshot, win_x, win_y) = if let Some(window) = bazaar_window {
-        (window.capture_image().map_err(|e| e.to_string())?, window.x(), window.y())
+        capture_game_window(&window)?
     } else {
         let monitors = Monitor::all().map_err(|e| e.to_string())?;
         let target_monitor = monitors.into_iter().find(|m| {
@@ -1793,9 +3518,7 @@ pub async fn recognize_event_at_mouse() -> Result<Option<serde_json::Value>, Str
     let crop_w = if crop_x + crop_size as u32 > img_w { img_w.saturating_sub(crop_x) } else { crop_size as u32 };
     let crop_h = if crop_y + crop_size as u32 > img_h { img_h.saturating_sub(crop_y) } else { crop_size as u32 };
 
-    if crop_w < 50 || crop_h < 50 {
-        return Err("裁剪区域太小或鼠标已移出窗口范围".into());
-    }
+    check_crop_size(crop_w, crop_h)?;
 
     let cropped_img = img.crop_imm(crop_x, crop_y, crop_w, crop_h);
     
@@ -1803,41 +3526,19 @@ pub async fn recognize_event_at_mouse() -> Result<Option<serde_json::Value>, Str
     let scene_desc = extract_features_from_dynamic_image(&cropped_img, 500).map_err(|e| e.to_string())?;
     if scene_desc.empty() { return Ok(None); }
     
-    // 4. 与事件模板比对
-    let cache = EVENT_TEMPLATE_CACHE.get().ok_or("Event templates not loaded")?;
-    let mut results: Vec<(&EventTemplateCache, usize, f32)> = Vec::new();
-
-    for template in cache {
-        if template.descriptors.is_empty() { continue; }
-        use opencv::core::CV_8U;
-        let mut template_desc = match unsafe { Mat::new_rows_cols(template.descriptor_rows, template.descriptor_cols, CV_8U) } {
-            Ok(m) => m,
-            Err(_) => continue,
-        };
-        unsafe { std::ptr::copy_nonoverlapping(template.descriptors.as_ptr(), template_desc.data_mut() as *mut u8, template.descriptors.len()); }
-
-        if let Ok(matches) = match_orb_descriptors(&scene_desc, &template_desc) {
-            let min_kp = (template.descriptor_rows as f32).min(scene_desc.rows() as f32);
-            let confidence = if min_kp > 0.0 { matches as f32 / min_kp } else { 0.0 };
-            results.push((template, matches, confidence));
-        }
+    // 4. 与事件模板比对：与右键识别共用 match_event_candidates，阈值来自 event_min_matches / event_min_confidence
+    let candidates = match_event_candidates(&scene_desc)?;
+    if let Some(best) = candidates.first() {
+        crate::reveal_overlay_on_first_scan(&app);
+        return Ok(Some(serde_json::json!({
+            "id": best.id,
+            "name": best.name,
+            "confidence": best.confidence,
+            "match_count": best.match_count,
+            "candidates": candidates
+        })));
     }
-    
-    results.sort_by(|a, b| b.1.cmp(&a.1));
 
-    // 取最佳匹配（阈值：匹配点数 > 15 且置信度 > 0.15）
-    if let Some((best, matches, confidence)) = results.first() {
-        if *matches > 15 && *confidence > 0.15 {
-            println!("[Event Recognition] Matched: {} (confidence: {:.2}, matches: {})", best.name, confidence, matches);
-            return Ok(Some(serde_json::json!({
-                "id": best.id,
-                "name": best.name,
-                "confidence": confidence,
-                "match_count": matches
-            })));
-        }
-    }
-    
     println!("[Event Recognition] No event matches found above threshold.");
     Ok(None)
 }
@@ -42,10 +42,39 @@ interface ItemData {
   displayImg?: string;
 }
 
+// 后端热键：单键为虚拟键码，组合键为 { modifiers, key }
+type HotkeyValue = number | { modifiers: number[]; key: number };
+
+interface MonsterCandidate {
+  name: string;
+  matches: number;
+  confidence: number; // 0-100
+}
+
+// recognize_monsters 的识别区域，相对截图宽高的比例
+interface ScanRegion {
+  x: number;
+  y: number;
+  w: number;
+  h: number;
+}
+
+// 鼠标指向识别未达标时的状态说明
+interface MonsterMatchStatus {
+  matched: boolean;
+  name: string | null;
+  best_name: string | null;
+  best_matches: number;
+  second_matches: number;
+  threshold: number;
+  message: string;
+}
+
 interface SyncPayload {
   hand_items: ItemData[];
   stash_items: ItemData[];
   all_tags: string[];
+  unresolved_template_ids?: string[];
 }
 
 interface TierInfo {
@@ -131,6 +160,10 @@ const HERO_COLORS: Record<string, string> = {
   "Common": '#E0E0E0'
 };
 
+// 与后端 day_bucket_label 保持一致：第 10 天及以后共用 "Day 10+" 池
+const DAY_BUCKET_MAX = 10;
+const dayBucketLabel = (day: number) => (day >= DAY_BUCKET_MAX ? `Day ${DAY_BUCKET_MAX}+` : `Day ${day}`);
+
 export default function App() {
   const [activeTab, setActiveTab] = useState<TabType>("monster");
   const [syncData, setSyncData] = useState<SyncPayload & { monster: any[] }>({ 
@@ -144,6 +177,13 @@ export default function App() {
   const [allMonsters, setAllMonsters] = useState<Record<string, MonsterData>>({});
   const [selectedDay, setSelectedDay] = useState<string>("");
   const [identifiedNames, setIdentifiedNames] = useState<string[]>([]); // 存储按顺序识别到的怪物名
+  const [buildCopied, setBuildCopied] = useState(false);
+  const [settingsTransferStatus, setSettingsTransferStatus] = useState<string | null>(null);
+  const [diagnosticsCopied, setDiagnosticsCopied] = useState(false);
+  const [overlayClickThrough, setOverlayClickThrough] = useState(true);
+  const [monsterCandidates, setMonsterCandidates] = useState<MonsterCandidate[]>([]); // 识别不确定时的候选列表，由用户点选
+  const [monsterMatchStatus, setMonsterMatchStatus] = useState<MonsterMatchStatus | null>(null); // 候选列表的说明（最接近的是谁、差几点）
+  const [monsterVariants, setMonsterVariants] = useState<{ name: string; days: number[] } | null>(null); // 最近识别怪物出现的所有天数
   const [pinnedItems, setPinnedItems] = useState<Map<string, number>>(new Map()); // 存储置顶物品ID和置顶时间戳
   const [pinnedCounter, setPinnedCounter] = useState(0); // 置顶计数器，用于确定置顶顺序
   const [isRecognizing, setIsRecognizing] = useState(false); // 是否正在识别怪物
@@ -332,7 +372,7 @@ export default function App() {
       if (activeTab === "search") {
         setIsSearching(true);
         try {
-          const res = await invoke<ItemData[]>("search_items", { query: searchQuery });
+          const res = await invoke<ItemData[]>("search_items", { query: { ...searchQuery, fuzzy: true } }); // 无精确结果时回退模糊匹配
           
           // Filter out "中型包裹" and apply multi-select tag filters
           let filtered = res.filter(item => 
@@ -434,13 +474,17 @@ export default function App() {
   const currentScale = useRef(1);
 
   // 新增：识别热键状态
-  const [detectionHotkey, setDetectionHotkey] = useState<number | null>(null);
-  const [cardDetectionHotkey, setCardDetectionHotkey] = useState<number | null>(null);
-  const [toggleCollapseHotkey, setToggleCollapseHotkey] = useState<number | null>(null);
-  const [detailDisplayHotkey, setDetailDisplayHotkey] = useState<number | null>(null);
+  const [detectionHotkey, setDetectionHotkey] = useState<HotkeyValue | null>(null);
+  const [cardDetectionHotkey, setCardDetectionHotkey] = useState<HotkeyValue | null>(null);
+  const [toggleCollapseHotkey, setToggleCollapseHotkey] = useState<HotkeyValue | null>(null);
+  const [detailDisplayHotkey, setDetailDisplayHotkey] = useState<HotkeyValue | null>(null);
   const [isRecordingHotkey, setIsRecordingHotkey] = useState(false);
   const [isRecordingCardHotkey, setIsRecordingCardHotkey] = useState(false);
   const [isRecordingToggleHotkey, setIsRecordingToggleHotkey] = useState(false);
+  // 怪物识别区域框选：截图缩略图 + 正在拖拽的矩形（相对比例 0~1）
+  const [scanRegionPreview, setScanRegionPreview] = useState<{ image_base64: string; width: number; height: number } | null>(null);
+  const [scanRegionDraft, setScanRegionDraft] = useState<ScanRegion | null>(null);
+  const scanRegionDragStart = useRef<{ x: number; y: number } | null>(null);
   const [isRecordingYoloHotkey, setIsRecordingYoloHotkey] = useState(false);
   const [isRecordingDetailHotkey, setIsRecordingDetailHotkey] = useState(false);
   
@@ -458,6 +502,11 @@ export default function App() {
   const [downloadProgress, setDownloadProgress] = useState(0);
   const [isInstalling, setIsInstalling] = useState(false); // 正在安装状态
   const [errorMessage, setErrorMessage] = useState<string | null>(null);
+  // 后端正在追赶大批量日志时为 true，期间跳过自动 YOLO 扫描
+  const [isLogCatchingUp, setIsLogCatchingUp] = useState(false);
+  const logCatchingUpRef = useRef(false);
+  // 启动时回放历史日志的进度（字节），完成后为 null
+  const [logSyncProgress, setLogSyncProgress] = useState<{ processed: number; total: number } | null>(null);
 
   // 禁用右键菜单
   useEffect(() => {
@@ -481,7 +530,13 @@ export default function App() {
   useEffect(() => {
     const unlisten = listen<string>("scan-error", (event) => {
       console.error("[Backend Error]", event.payload);
-      setErrorMessage(`识别错误: ${event.payload}`);
+      if (event.payload.startsWith("CropTooSmall")) {
+        setErrorMessage("识别错误: 鼠标太靠近屏幕边缘，请将鼠标移到目标中央后重试");
+      } else if (event.payload.startsWith("BlackCapture")) {
+        setErrorMessage("截图失败: 游戏可能处于独占全屏模式，请在游戏设置中切换为无边框窗口模式");
+      } else {
+        setErrorMessage(`识别错误: ${event.payload}`);
+      }
       // 3秒后自动清除
       setTimeout(() => setErrorMessage(null), 5000);
     });
@@ -562,6 +617,8 @@ export default function App() {
     } finally {
       setIsRecognizingCard(false);
       setTimeout(() => setErrorMessage(null), 3000);
+      // 热键触发时，结果展示后再把焦点交还给游戏（受“自动恢复焦点”设置控制）
+      if (switchTab) invoke("restore_game_focus_after_hotkey").catch(() => {});
     }
   };
 
@@ -712,7 +769,7 @@ export default function App() {
 
  // 辅助函数：根据天数数字更新选中的 Tab
  const updateDayTabSelection = (day: number) => {
-   const dayStr = day >= 10 ? "Day 10+" : `Day ${day}`;
+   const dayStr = dayBucketLabel(day);
    setSelectedDay(dayStr);
  };
   // 获取排序后的物品列表（手牌和仓库）
@@ -764,7 +821,11 @@ export default function App() {
 
 
   // 辅助函数：将虚拟键码转换为可读文本
-  const getHotkeyLabel = (code: number) => {
+  const getHotkeyLabel = (hotkey: HotkeyValue): string => {
+    if (typeof hotkey !== "number") {
+      return [...hotkey.modifiers, hotkey.key].map(getHotkeyLabel).join(" + ");
+    }
+    const code = hotkey;
     if (code >= 65 && code <= 90) return `Key ${String.fromCharCode(code)}`;
     if (code >= 48 && code <= 57) return `Key ${code - 48}`;
     if (code >= 112 && code <= 123) return `F${code - 111}`;
@@ -875,18 +936,24 @@ export default function App() {
         setCurrentVersion(appVersion);
         console.log(`[App] 启动初始化。当前版本: v${appVersion}`);
 
-        // 检查更新
-        console.log("[Update] 正在连接服务器检查更新...");
-        setUpdateStatus("checking");
-        const update = await check();
-        console.log("[Update] check() 响应结果:", update);
-        if (update) {
-          console.log(`[Update] 检测到新版本! 远端版本: v${update.version}, 发布日期: ${update.date}`);
-          setUpdateAvailable(update);
-          setUpdateStatus("available");
+        // 检查更新（用户可在设置中关闭自动检查）
+        const updateSettings = await invoke<{ auto_check_updates: boolean }>("get_update_settings")
+          .catch(() => ({ auto_check_updates: true }));
+        if (updateSettings.auto_check_updates) {
+          console.log("[Update] 正在连接服务器检查更新...");
+          setUpdateStatus("checking");
+          const update = await check();
+          console.log("[Update] check() 响应结果:", update);
+          if (update) {
+            console.log(`[Update] 检测到新版本! 远端版本: v${update.version}, 发布日期: ${update.date}`);
+            setUpdateAvailable(update);
+            setUpdateStatus("available");
+          } else {
+            console.log("[Update] 已经是最新版本 (v" + appVersion + ")，无需更新。");
+            setUpdateStatus("none");
+          }
         } else {
-          console.log("[Update] 已经是最新版本 (v" + appVersion + ")，无需更新。");
-          setUpdateStatus("none");
+          console.log("[Update] 已关闭自动检查更新，跳过。");
         }
 
         // 获取公告内容 (从 GitHub 代理)
@@ -992,8 +1059,12 @@ export default function App() {
             ...prev, 
             hand_items: hand, 
             stash_items: stash, 
-            all_tags: payload.all_tags || [] 
+            all_tags: payload.all_tags || [],
+            unresolved_template_ids: payload.unresolved_template_ids || []
           }));
+          if (payload.unresolved_template_ids?.length) {
+            console.warn(`[Sync] ${payload.unresolved_template_ids.length} 个已追踪物品无法匹配数据库:`, payload.unresolved_template_ids);
+          }
         }
       });
 
@@ -1001,7 +1072,7 @@ export default function App() {
       await safeListen<number | null>('trigger-monster-recognition', (dayNum) => {
         console.log("收到自动识别触发事件, Day:", dayNum);
         if (dayNum) {
-          const dayLabel = dayNum >= 10 ? "Day 10+" : `Day ${dayNum}`;
+          const dayLabel = dayBucketLabel(dayNum);
           setSelectedDay(dayLabel);
           setCurrentDay(dayNum);
         }
@@ -1054,13 +1125,15 @@ export default function App() {
       });
 
       // 5. 自动识别并跳转事件
-      await safeListen<{ day: number; monster_name: string }>('auto-jump-to-monster', (payload) => {
-          const { day, monster_name } = payload;
+      await safeListen<{ day: number; monster_name: string; candidate_days?: number[] }>('auto-jump-to-monster', (payload) => {
+          const { day, monster_name, candidate_days } = payload;
           const names = monster_name.includes('|') ? monster_name.split('|') : [monster_name];
 
+          setMonsterCandidates([]);
+          setMonsterVariants(candidate_days && candidate_days.length > 1 ? { name: names[0], days: candidate_days } : null);
           setIsCollapsed(false);
           setCurrentDay(day);
-          setSelectedDay(day >= 10 ? "Day 10+" : `Day ${day}`);
+          setSelectedDay(dayBucketLabel(day));
           setIdentifiedNames(names);
           setExpandedMonsters(prev => {
               const next = new Set(prev);
@@ -1075,10 +1148,15 @@ export default function App() {
 
           setActiveTab("monster");
       });
+      // 重启后恢复上次的跳转（由后端按 restore_last_jump 设置决定是否推送）
+      invoke("restore_last_jump").catch(() => {});
 
       // 6. 野怪匹配事件（来自Overlay右键识别）
       await safeListen<{ name: string; name_zh: string }>('monster-matched', (payload) => {
           console.log("收到野怪匹配事件:", payload);
+          invoke<number[]>("get_monster_variants", { nameZh: payload.name_zh || payload.name })
+            .then(days => setMonsterVariants(days.length > 1 ? { name: payload.name, days } : null))
+            .catch(() => setMonsterVariants(null));
           // 将识别的野怪名称添加到identifiedNames
           setIdentifiedNames(prev => {
               if (!prev.includes(payload.name)) {
@@ -1101,17 +1179,51 @@ export default function App() {
           }, 300);
       });
 
+      // 7. 怪物识别不确定：显示候选列表让用户选择
+      await safeListen<MonsterCandidate[]>('monster-candidates', (candidates) => {
+          setMonsterCandidates(candidates || []);
+      });
+
+      // 只在有候选时推送，显示在候选列表标题处
+      await safeListen<MonsterMatchStatus>('monster-match-status', (status) => {
+          setMonsterMatchStatus(status);
+      });
+
+      await safeListen<boolean>('log-catching-up', (catchingUp) => {
+        logCatchingUpRef.current = catchingUp;
+        setIsLogCatchingUp(catchingUp);
+      });
+      invoke<boolean>("is_log_catching_up").then(catchingUp => {
+        logCatchingUpRef.current = catchingUp;
+        setIsLogCatchingUp(catchingUp);
+      }).catch(() => {});
+
+      await safeListen<{ processed: number; total: number; done: boolean }>('log-sync-progress', (p) => {
+        setLogSyncProgress(p.done ? null : { processed: p.processed, total: p.total });
+      });
+
+      // 新对局开始：清空手牌/仓库、天数和识别记录
+
+      await safeListen<void>('run-started', () => {
+        console.log("[LogMonitor] 新对局开始，重置界面状态");
+        setSyncData(prev => ({ ...prev, hand_items: [], stash_items: [] }));
+        setCurrentDay(1);
+        setSelectedDay("Day 1");
+        setIdentifiedNames([]);
+        setExpandedMonsters(new Set());
+      });
+
       // 5. 天数更新
       await safeListen<number>('day-update', (d) => {
         setCurrentDay(d);
-        setSelectedDay(d >= 10 ? "Day 10+" : `Day ${d}`);
+        setSelectedDay(dayBucketLabel(d));
       });
       
       // 加载热键设置
-      invoke<number | null>("get_detection_hotkey").then(val => isMounted && setDetectionHotkey(val));
-      invoke<number | null>("get_card_detection_hotkey").then(val => isMounted && setCardDetectionHotkey(val));
-      invoke<number | null>("get_toggle_collapse_hotkey").then(val => isMounted && setToggleCollapseHotkey(val));
-      invoke<number | null>("get_detail_display_hotkey").then(val => isMounted && setDetailDisplayHotkey(val));
+      invoke<HotkeyValue | null>("get_detection_hotkey").then(val => isMounted && setDetectionHotkey(val));
+      invoke<HotkeyValue | null>("get_card_detection_hotkey").then(val => isMounted && setCardDetectionHotkey(val));
+      invoke<HotkeyValue | null>("get_toggle_collapse_hotkey").then(val => isMounted && setToggleCollapseHotkey(val));
+      invoke<HotkeyValue | null>("get_detail_display_hotkey").then(val => isMounted && setDetailDisplayHotkey(val));
     };
     
     setupListeners();
@@ -1161,7 +1273,13 @@ export default function App() {
     }
 
     // 启动定时器
-    const yoloTimer = setInterval(runYoloScan, yoloScanInterval * 1000);
+    const yoloTimer = setInterval(() => {
+      if (logCatchingUpRef.current) {
+        console.log("[YOLO Auto] Skipping scan while log is catching up");
+        return;
+      }
+      runYoloScan();
+    }, yoloScanInterval * 1000);
     console.log(`[YOLO Auto] Timer started with interval: ${yoloScanInterval}s`);
 
     // 清理函数
@@ -1189,20 +1307,42 @@ export default function App() {
   }, [yoloHotkey]);
 
   // 基础环境侦测：分辨率适配
-  // 监听 Overlay 的主动同步请求
+  // Overlay 的主动同步请求由后端用保存的布局应答（request-sync-overlay-settings）
+
+  // 启动时以后端保存的详情面板布局为准（localStorage 在重装/清缓存后会丢失）
   useEffect(() => {
-    const unlisten = listen('request-sync-overlay-settings', () => {
-      console.log("[App] Received config sync request from Overlay, sending settings...");
-      invoke('update_overlay_detail_position', { 
-        x: overlayDetailX, 
-        y: overlayDetailY, 
-        scale: overlayDetailScale,
-        width: overlayDetailWidth,
-        height: overlayDetailHeight
-      }).catch(console.error);
-    });
-    return () => { unlisten.then(f => f()); };
-  }, [overlayDetailX, overlayDetailY, overlayDetailScale, overlayDetailWidth, overlayDetailHeight]);
+    invoke<{ x: number, y: number, scale: number, width: number, height: number } | null>('get_overlay_detail_layout').then(layout => {
+      if (!layout) {
+        // 旧版本只把布局存在 localStorage：首次启动时迁移到后端，之后 overlay 的同步请求才有布局可应答
+        const hasLegacyLayout = ["x", "y", "scale", "width", "height"].some(k => localStorage.getItem(`overlay-detail-${k}`) !== null);
+        if (hasLegacyLayout) {
+          console.log("[App] Migrating overlay detail layout from localStorage to backend");
+          invoke('update_overlay_detail_position', {
+            x: overlayDetailX,
+            y: overlayDetailY,
+            scale: overlayDetailScale,
+            width: overlayDetailWidth,
+            height: overlayDetailHeight
+          }).catch(console.error);
+        }
+        return;
+      }
+      setOverlayDetailX(layout.x);
+      setOverlayDetailY(layout.y);
+      setOverlayDetailScale(layout.scale);
+      setOverlayDetailWidth(layout.width);
+      setOverlayDetailHeight(layout.height);
+      localStorage.setItem("overlay-detail-x", layout.x.toString());
+      localStorage.setItem("overlay-detail-y", layout.y.toString());
+      localStorage.setItem("overlay-detail-scale", layout.scale.toString());
+      localStorage.setItem("overlay-detail-width", layout.width.toString());
+      localStorage.setItem("overlay-detail-height", layout.height.toString());
+    }).catch(console.error);
+  }, []);
+
+  useEffect(() => {
+    invoke<boolean>('get_overlay_click_through').then(setOverlayClickThrough).catch(console.error);
+  }, []);
 
   useEffect(() => {
     const detectScale = async () => {
@@ -2014,6 +2154,86 @@ export default function App() {
         </div>
       )}
 
+      {monsterCandidates.length > 0 && (
+        <div style={{
+          position: 'fixed',
+          top: '80px',
+          left: '50%',
+          transform: 'translateX(-50%)',
+          backgroundColor: 'rgba(40, 35, 30, 0.95)',
+          border: '1px solid #d4a84f',
+          padding: '8px 12px',
+          borderRadius: '8px',
+          boxShadow: '0 8px 24px rgba(0,0,0,0.6)',
+          zIndex: 9998,
+          fontSize: '13px',
+          color: '#eee',
+          minWidth: '200px'
+        }}>
+          <div style={{ display: 'flex', justifyContent: 'space-between', marginBottom: '6px', color: '#d4a84f' }}>
+            <span>{monsterMatchStatus?.message ?? '识别不确定'}，请选择怪物</span>
+            <span style={{ cursor: 'pointer', color: '#888' }} onClick={() => setMonsterCandidates([])}>×</span>
+          </div>
+          {monsterCandidates.map(c => (
+            <div
+              key={c.name}
+              style={{ cursor: 'pointer', padding: '4px 6px', borderRadius: '4px', display: 'flex', justifyContent: 'space-between', gap: '12px' }}
+              onMouseEnter={e => (e.currentTarget.style.background = 'rgba(212, 168, 79, 0.2)')}
+              onMouseLeave={e => (e.currentTarget.style.background = 'transparent')}
+              onClick={() => {
+                setMonsterCandidates([]);
+                setIdentifiedNames(prev => prev.includes(c.name) ? prev : [...prev, c.name]);
+                setExpandedMonsters(prev => new Set(prev).add(c.name));
+                setIsCollapsed(false);
+                setActiveTab("monster");
+                // 列表只渲染 selectedDay 的怪物，先切到候选所在的天数再滚动
+                invoke<number[]>("get_monster_variants", { nameZh: c.name })
+                  .then(days => {
+                    setMonsterVariants(days.length > 1 ? { name: c.name, days } : null);
+                    const fallback = allMonsters[c.name]?.available?.match(/Day\s+(\d+)/);
+                    const day = currentDay !== null && days.includes(currentDay)
+                      ? currentDay
+                      : days[0] ?? (fallback ? parseInt(fallback[1], 10) : null);
+                    if (day !== null) {
+                      setCurrentDay(day);
+                      setSelectedDay(dayBucketLabel(day));
+                    }
+                  })
+                  .catch(() => setMonsterVariants(null))
+                  .finally(() => {
+                    setTimeout(() => {
+                      const element = document.getElementById(`monster-${c.name}`);
+                      if (element) element.scrollIntoView({ behavior: 'smooth', block: 'center' });
+                    }, 300);
+                  });
+              }}
+            >
+              <span>{c.name}</span>
+              <span style={{ color: '#888' }}>{c.matches} 点 · {c.confidence.toFixed(0)}%</span>
+            </div>
+          ))}
+        </div>
+      )}
+
+      {(isLogCatchingUp || logSyncProgress) && (
+        <div style={{
+          position: 'fixed',
+          top: '48px',
+          left: '50%',
+          transform: 'translateX(-50%)',
+          backgroundColor: 'rgba(40, 35, 30, 0.9)',
+          color: '#d4a84f',
+          padding: '4px 12px',
+          borderRadius: '6px',
+          fontSize: '12px',
+          zIndex: 9998,
+          pointerEvents: 'none'
+        }}>
+          正在同步日志…
+          {logSyncProgress && logSyncProgress.total > 0 && ` ${Math.floor(logSyncProgress.processed / logSyncProgress.total * 100)}%`}
+        </div>
+      )}
+
       {!isCollapsed && (
         <>
           <div className="resize-handle-width" onMouseDown={handleResizeWidth} title="左右拖动调整宽度" />
@@ -2732,6 +2952,172 @@ export default function App() {
                 </div>
               </div>
 
+              <div className="setting-item">
+                <div style={{ display: 'flex', justifyContent: 'space-between', alignItems: 'center' }}>
+                  <label>Overlay 点击穿透</label>
+                  <button
+                    className="bulk-btn"
+                    style={{
+                      padding: '4px 12px',
+                      background: overlayClickThrough ? 'rgba(76, 175, 80, 0.2)' : 'rgba(244, 67, 54, 0.2)',
+                      borderColor: overlayClickThrough ? '#4CAF50' : '#f44336',
+                      color: overlayClickThrough ? '#4CAF50' : '#f44336'
+                    }}
+                    onClick={() => {
+                      const newVal = !overlayClickThrough;
+                      invoke('set_overlay_click_through', { enabled: newVal })
+                        .then(() => setOverlayClickThrough(newVal))
+                        .catch(console.error);
+                    }}
+                  >
+                    {overlayClickThrough ? '已开启' : '已关闭'}
+                  </button>
+                </div>
+                <div style={{ fontSize: '11px', color: '#888', marginTop: '4px' }}>
+                  关闭后 Overlay 常驻可交互（会挡住其下方的游戏点击）
+                </div>
+              </div>
+
+              <div className="setting-divider" style={{ borderTop: '1px solid rgba(255,255,255,0.1)', margin: '15px 0' }}></div>
+
+              {/* 怪物识别区域框选 */}
+              <div className="setting-item">
+                <div style={{ display: 'flex', justifyContent: 'space-between', marginBottom: '8px' }}>
+                  <label>怪物识别区域</label>
+                  <div style={{ display: 'flex', gap: '6px' }}>
+                    <button className="bulk-btn" style={{ padding: '2px 8px' }} onClick={() => {
+                      invoke<{ image_base64: string; width: number; height: number; region: ScanRegion }>('preview_monster_scan_region').then(preview => {
+                        setScanRegionPreview(preview);
+                        setScanRegionDraft(preview.region);
+                      }).catch(e => {
+                        setErrorMessage(`截图失败: ${e}`);
+                        setTimeout(() => setErrorMessage(null), 5000);
+                      });
+                    }}>框选区域</button>
+                    <button className="bulk-btn" style={{ padding: '2px 8px' }} onClick={() => {
+                      invoke('set_monster_scan_region', { region: null }).catch(console.error);
+                    }}>恢复默认</button>
+                  </div>
+                </div>
+                <div style={{ fontSize: '11px', color: '#888', marginTop: '4px' }}>
+                  游戏带黑边或分辨率特殊时，框选三只怪物所在的区域（会被水平三等分）
+                </div>
+              </div>
+              {scanRegionPreview && (
+                <div style={{
+                  position: 'fixed', inset: 0, backgroundColor: 'rgba(0,0,0,0.85)', zIndex: 10000,
+                  display: 'flex', flexDirection: 'column', alignItems: 'center', justifyContent: 'center', gap: '10px'
+                }}>
+                  <div style={{ color: '#fff', fontSize: '13px' }}>在截图上拖拽框选怪物区域</div>
+                  <div
+                    style={{ position: 'relative', maxWidth: '90vw', maxHeight: '75vh', cursor: 'crosshair', userSelect: 'none' }}
+                    onMouseDown={e => {
+                      const rect = e.currentTarget.getBoundingClientRect();
+                      const x = Math.min(Math.max((e.clientX - rect.left) / rect.width, 0), 1);
+                      const y = Math.min(Math.max((e.clientY - rect.top) / rect.height, 0), 1);
+                      scanRegionDragStart.current = { x, y };
+                      setScanRegionDraft({ x, y, w: 0, h: 0 });
+                    }}
+                    onMouseMove={e => {
+                      const start = scanRegionDragStart.current;
+                      if (!start) return;
+                      const rect = e.currentTarget.getBoundingClientRect();
+                      const x = Math.min(Math.max((e.clientX - rect.left) / rect.width, 0), 1);
+                      const y = Math.min(Math.max((e.clientY - rect.top) / rect.height, 0), 1);
+                      setScanRegionDraft({ x: Math.min(start.x, x), y: Math.min(start.y, y), w: Math.abs(x - start.x), h: Math.abs(y - start.y) });
+                    }}
+                    onMouseUp={() => { scanRegionDragStart.current = null; }}
+                    onMouseLeave={() => { scanRegionDragStart.current = null; }}
+                  >
+                    <img src={`data:image/png;base64,${scanRegionPreview.image_base64}`} draggable={false} style={{ display: 'block', maxWidth: '90vw', maxHeight: '75vh' }} />
+                    {scanRegionDraft && (
+                      <div style={{
+                        position: 'absolute',
+                        left: `${scanRegionDraft.x * 100}%`, top: `${scanRegionDraft.y * 100}%`,
+                        width: `${scanRegionDraft.w * 100}%`, height: `${scanRegionDraft.h * 100}%`,
+                        border: '2px solid #ffcd19', boxSizing: 'border-box', pointerEvents: 'none'
+                      }}>
+                        {[1, 2].map(i => (
+                          <div key={i} style={{ position: 'absolute', left: `${i * 100 / 3}%`, top: 0, bottom: 0, borderLeft: '1px dashed #ffcd19' }} />
+                        ))}
+                      </div>
+                    )}
+                  </div>
+                  <div style={{ display: 'flex', gap: '10px' }}>
+                    <button className="bulk-btn" disabled={!scanRegionDraft || scanRegionDraft.w < 0.05 || scanRegionDraft.h < 0.05} onClick={() => {
+                      invoke('set_monster_scan_region', { region: scanRegionDraft }).then(() => {
+                        setScanRegionPreview(null);
+                      }).catch(e => {
+                        setErrorMessage(`保存识别区域失败: ${e}`);
+                        setTimeout(() => setErrorMessage(null), 5000);
+                      });
+                    }}>保存</button>
+                    <button className="bulk-btn" onClick={() => setScanRegionPreview(null)}>取消</button>
+                  </div>
+                </div>
+              )}
+
+              <div className="setting-divider" style={{ borderTop: '1px solid rgba(255,255,255,0.1)', margin: '15px 0' }}></div>
+
+              {/* 配置导入/导出（经剪贴板） */}
+              <div className="setting-item">
+                <div style={{ display: 'flex', justifyContent: 'space-between', marginBottom: '8px' }}>
+                  <label>配置备份</label>
+                  <div style={{ display: 'flex', gap: '6px' }}>
+                    <button className="bulk-btn" style={{ padding: '2px 8px' }} onClick={async () => {
+                      try {
+                        const json = await invoke<string>('export_settings');
+                        await navigator.clipboard.writeText(json);
+                        setSettingsTransferStatus('✅ 已复制到剪贴板');
+                        setTimeout(() => setSettingsTransferStatus(null), 2000);
+                      } catch (err) {
+                        setErrorMessage(`导出配置失败: ${err}`);
+                        setTimeout(() => setErrorMessage(null), 5000);
+                      }
+                    }}>导出</button>
+                    <button className="bulk-btn" style={{ padding: '2px 8px' }} onClick={async () => {
+                      try {
+                        const json = await navigator.clipboard.readText();
+                        await invoke('import_settings', { json });
+                        setSettingsTransferStatus('✅ 已导入，重启后完全生效');
+                        setTimeout(() => setSettingsTransferStatus(null), 3000);
+                      } catch (err) {
+                        setErrorMessage(`导入配置失败: ${err}`);
+                        setTimeout(() => setErrorMessage(null), 5000);
+                      }
+                    }}>从剪贴板导入</button>
+                  </div>
+                </div>
+                <div style={{ fontSize: '11px', color: '#888', marginTop: '4px' }}>
+                  {settingsTransferStatus ?? '热键与偏好设置导出为 JSON，换机器或重装后导入即可恢复（不含手牌/仓库）'}
+                </div>
+              </div>
+
+              <div className="setting-item">
+                <div style={{ display: 'flex', justifyContent: 'space-between', marginBottom: '8px' }}>
+                  <label>识别诊断信息</label>
+                  <button className="bulk-btn" style={{ padding: '2px 8px' }} onClick={async () => {
+                    try {
+                      const diagnostics = await invoke<unknown>('get_recognition_diagnostics');
+                      if (diagnostics === null) {
+                        setErrorMessage('还没有识别记录，请先用热键识别一次怪物');
+                        setTimeout(() => setErrorMessage(null), 5000);
+                        return;
+                      }
+                      await navigator.clipboard.writeText(JSON.stringify({ version: currentVersion, ...(diagnostics as object) }, null, 2));
+                      setDiagnosticsCopied(true);
+                      setTimeout(() => setDiagnosticsCopied(false), 2000);
+                    } catch (err) {
+                      setErrorMessage(`获取诊断信息失败: ${err}`);
+                      setTimeout(() => setErrorMessage(null), 5000);
+                    }
+                  }}>{diagnosticsCopied ? '✅ 已复制' : '复制'}</button>
+                </div>
+                <div style={{ fontSize: '11px', color: '#888', marginTop: '4px' }}>
+                  最近一次识别的截图/特征提取/比对耗时与匹配分数，反馈问题时请一并附上
+                </div>
+              </div>
+
               <div className="setting-divider" style={{ borderTop: '1px solid rgba(255,255,255,0.1)', margin: '15px 0' }}></div>
 
               <div className="setting-item">
@@ -3272,14 +3658,26 @@ export default function App() {
                         {["Day 6", "Day 7", "Day 8", "Day 9", "Day 10+"].map(d => (
                           <div key={d} className={`day-tab ${selectedDay === d ? 'active' : ''}`} onClick={() => {
                             setSelectedDay(d);
-                            // 将 Day 10+ 映射为 10，其余提取数字部分
-                            const dayNum = d === "Day 10+" ? 10 : parseInt(d.split(" ")[1]);
+                            // 提取数字部分（Day 10+ -> 10）
+                            const dayNum = parseInt(d.replace("+", "").split(" ")[1]);
                             handleDayChange(dayNum);
                           }}>{d}</div>
                         ))}
                       </div>
                     </div>
 
+                    {monsterVariants && (
+                      <div className="monster-variants" style={{ display: 'flex', flexWrap: 'wrap', alignItems: 'center', gap: '6px', fontSize: '12px', marginTop: '6px' }}>
+                        <span style={{ opacity: 0.8 }}>{monsterVariants.name} 还出现在</span>
+                        {monsterVariants.days.map(d => (
+                          <div key={d} className={`day-tab ${selectedDay === dayBucketLabel(d) ? 'active' : ''}`} onClick={() => {
+                            setSelectedDay(dayBucketLabel(d));
+                            handleDayChange(d);
+                          }}>Day {d}</div>
+                        ))}
+                      </div>
+                    )}
+
                     {/* 原识别按钮已移除 */}
 
                     {!templateLoading.is_complete && templateLoading.total > 0 && (
@@ -3344,6 +3742,26 @@ export default function App() {
               </>
             ) : (
                 <>
+                  {activeTab === "items" && (syncData.hand_items.length + syncData.stash_items.length) > 0 && (
+                    <div style={{ display: 'flex', justifyContent: 'flex-end', padding: '4px 8px' }}>
+                      <button
+                        className="bulk-btn"
+                        onClick={async () => {
+                          try {
+                            const build = await invoke<string>("export_current_build");
+                            await navigator.clipboard.writeText(build);
+                            setBuildCopied(true);
+                            setTimeout(() => setBuildCopied(false), 2000);
+                          } catch (err) {
+                            setErrorMessage(`导出 build 失败: ${err}`);
+                            setTimeout(() => setErrorMessage(null), 5000);
+                          }
+                        }}
+                      >
+                        {buildCopied ? "✅ 已复制" : "📋 复制当前 Build"}
+                      </button>
+                    </div>
+                  )}
                   <div className="card-list">
                     {(() => {
                         let source: ItemData[] = [];
@@ -3570,6 +3988,11 @@ export default function App() {
                   {activeTab === "items" && (syncData.hand_items.length + syncData.stash_items.length) === 0 && (
                     <div className="empty-tip">当前暂无数据，请在游戏中操作相应卡牌</div>
                   )}
+                  {activeTab === "items" && (syncData.unresolved_template_ids?.length ?? 0) > 0 && (
+                    <div className="empty-tip" title={syncData.unresolved_template_ids!.join("\n")}>
+                      ⚠️ {syncData.unresolved_template_ids!.length} 个已追踪物品无法匹配数据库
+                    </div>
+                  )}
                 </div>
               </>
             )}
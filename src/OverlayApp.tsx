@@ -108,6 +108,10 @@ interface EventChoice {
     displayIcon?: string;       // 加载后的本地图片路径
 }
 
+// 事件识别的 Top-3 候选（含完整事件数据），用于在相似事件之间切换
+type EventAlternative = { id: string, name: string, match_count: number, confidence: number, data: any };
+type RightClickResult = { type: 'item' | 'monster' | 'event', data: any, alternatives?: EventAlternative[] };
+
 interface EventData {
     Id: string;
     InternalName?: string;
@@ -317,8 +321,29 @@ export default function OverlayApp() {
     const [hoveredMonsterItem, setHoveredMonsterItem] = useState<MonsterSubItem | null>(null);
     const [expandedMonsterItem, setExpandedMonsterItem] = useState<MonsterSubItem | null>(null);
     const [yoloStats, setYoloStats] = useState<{total: number, items: number, events: number, monsters: number, skills: number} | null>(null);
+    // 详情框颜色与不透明度由后端持久化，设置变化时通过 overlay-style-update 推送
+    const [overlayStyle, setOverlayStyle] = useState<{box_color: string, opacity: number}>({ box_color: '#FFCD19', opacity: 0.98 });
+    useEffect(() => {
+        invoke<{box_color: string, opacity: number}>('get_overlay_style').then(setOverlayStyle).catch(console.error);
+        const unlisten = listen<{box_color: string, opacity: number}>('overlay-style-update', (event) => {
+            setOverlayStyle(event.payload);
+        });
+        return () => { unlisten.then(u => u()); };
+    }, []);
+    // 当前是否点击穿透，后端每次切换时推送 overlay-ignore-cursor-changed
+    const [ignoreCursor, setIgnoreCursor] = useState(true);
+    useEffect(() => {
+        invoke<boolean>('get_overlay_ignore_cursor').then(setIgnoreCursor).catch(console.error);
+        const unlisten = listen<boolean>('overlay-ignore-cursor-changed', (event) => {
+            setIgnoreCursor(event.payload);
+        });
+        return () => { unlisten.then(u => u()); };
+    }, []);
     
     const winMousePos = useRef({ x: 0, y: 0 });
+    // 右键命中多个重叠框时的候选菜单（显示在详情页位置）
+    const [clickChoices, setClickChoices] = useState<{ results: RightClickResult[] } | null>(null);
+    const [eventAlternatives, setEventAlternatives] = useState<EventAlternative[]>([]);
     useEffect(() => {
         const track = (e: MouseEvent) => { winMousePos.current = { x: e.clientX, y: e.clientY }; };
         window.addEventListener('mousemove', track);
@@ -604,6 +629,36 @@ export default function OverlayApp() {
         };
     };
 
+    // 右键识别结果 -> 详情页内容（候选菜单点选时复用）
+    const applyRightClickResult = async (res: RightClickResult) => {
+        if (res.type === 'item') {
+            const item = res.data as ItemData;
+            item.displayImg = await getImg(`images/${item.uuid || item.name}.webp`);
+            setYoloResult({ type: 'item', data: item });
+            // Update draggable position to mouse position if it's a fresh open (optional, but keep consistent with user intent)
+            // If user wants manual drag, we shouldn't force move it unless it was closed.
+            // But since we are switching, keeping position is better.
+        } else if (res.type === 'monster') {
+            const monster = await processMonsterImages(res.data as MonsterData);
+            setYoloResult({ type: 'monster', data: monster });
+            // 发送事件到主窗口更新野怪匹配状态
+            try {
+                await invoke('send_overlay_message', { 
+                    event: 'monster-matched', 
+                    payload: { name: monster.name, name_zh: monster.name_zh } 
+                });
+            } catch (e) {
+                console.error('Failed to emit monster-matched event:', e);
+            }
+        } else if (res.type === 'event') {
+            const eventData = await processEventImages(res.data as EventData);
+            setYoloResult({ type: 'event', data: eventData });
+            setEventAlternatives(res.alternatives ?? []);
+        }
+        // 注意：这里不重置 pos，保留用户上次拖拽的位置（或者保持默认相对位置）
+        // 也不修改 ignore_cursor，用户可能正在操作
+    };
+
     // Removed duplicate trigger_yolo_scan listener - now handled in App.tsx with enable-yolo-auto check
 
     useEffect(() => {
@@ -615,38 +670,18 @@ export default function OverlayApp() {
                 // 停止正在进行的 YOLO 扫描，并立马响应右键点击
                 await invoke("abort_yolo_scan").catch(() => {});
                 
-                const res = await invoke<{type: 'item' | 'monster' | 'event', data: any} | null>("handle_overlay_right_click", { 
+                const res = await invoke<{type: 'item' | 'monster' | 'event' | 'candidates', data: any} | null>("handle_overlay_right_click", { 
                     x: Math.round(coords.x), 
                     y: Math.round(coords.y) 
                 });
                 
-                if (res) {
-                    // 命中了新的目标：直接更新内容/切换显示
-                    if (res.type === 'item') {
-                        const item = res.data as ItemData;
-                        item.displayImg = await getImg(`images/${item.uuid || item.name}.webp`);
-                        setYoloResult({ type: 'item', data: item });
-                        // Update draggable position to mouse position if it's a fresh open (optional, but keep consistent with user intent)
-                        // If user wants manual drag, we shouldn't force move it unless it was closed.
-                        // But since we are switching, keeping position is better.
-                    } else if (res.type === 'monster') {
-                        const monster = await processMonsterImages(res.data as MonsterData);
-                        setYoloResult({ type: 'monster', data: monster });
-                        // 发送事件到主窗口更新野怪匹配状态
-                        try {
-                            await invoke('emit_to_main', { 
-                                event: 'monster-matched', 
-                                payload: { name: monster.name, name_zh: monster.name_zh } 
-                            });
-                        } catch (e) {
-                            console.error('Failed to emit monster-matched event:', e);
-                        }
-                    } else if (res.type === 'event') {
-                        const eventData = await processEventImages(res.data as EventData);
-                        setYoloResult({ type: 'event', data: eventData });
-                    }
-                    // 注意：这里不重置 pos，保留用户上次拖拽的位置（或者保持默认相对位置）
-                    // 也不修改 ignore_cursor，用户可能正在操作
+                if (res && res.type === 'candidates') {
+                    // 多个重叠框都识别成功：弹出选择菜单，由用户点选
+                    setClickChoices({ results: res.data as RightClickResult[] });
+                    invoke("set_overlay_ignore_cursor", { ignore: false }).catch(() => {});
+                } else if (res) {
+                    setClickChoices(null);
+                    await applyRightClickResult(res as RightClickResult);
                 } else {
                     // 未命中任何目标
                     // 如果当前有显示的卡牌，则认为是用户的"关闭"意图
@@ -1125,10 +1160,15 @@ export default function OverlayApp() {
                 width: '100vw', 
                 height: '100vh', 
                 position: 'relative', 
-                pointerEvents: yoloResult ? 'auto' : 'none',
+                pointerEvents: (yoloResult || clickChoices) ? 'auto' : 'none',
                 overflow: 'hidden'
             }}
             onClick={(e) => {
+                if (clickChoices && e.target === e.currentTarget) {
+                    setClickChoices(null);
+                    if (!yoloResult) invoke("set_overlay_ignore_cursor", { ignore: true }).catch(() => {});
+                    return;
+                }
                 // 左键点击空白处关闭详情页
                 if (yoloResult && e.target === e.currentTarget) {
                     setYoloResult(null);
@@ -1136,6 +1176,37 @@ export default function OverlayApp() {
                 }
             }}
         >
+            {clickChoices && (
+                <div style={{
+                    position: 'absolute',
+                    left: `${detailPosition.x}%`,
+                    top: `${detailPosition.y}%`,
+                    transform: 'translate(-50%, -50%)',
+                    zIndex: 1000,
+                    pointerEvents: 'auto',
+                    background: 'rgba(20,15,10,0.98)',
+                    border: `1px solid ${overlayStyle.box_color}`,
+                    borderRadius: '8px',
+                    padding: '4px 0',
+                    minWidth: '160px',
+                    boxShadow: '0 8px 24px rgba(0,0,0,0.8)'
+                }}>
+                    <div style={{ fontSize: '11px', color: 'rgba(255,255,255,0.6)', padding: '4px 12px' }}>选择要查看的目标</div>
+                    {clickChoices.results.map((r, i) => (
+                        <div key={i}
+                            style={{ padding: '6px 12px', cursor: 'pointer', color: '#fff', fontSize: '13px' }}
+                            onMouseEnter={(e) => { e.currentTarget.style.background = 'rgba(255,205,25,0.15)'; }}
+                            onMouseLeave={(e) => { e.currentTarget.style.background = 'transparent'; }}
+                            onClick={async () => {
+                                setClickChoices(null);
+                                await applyRightClickResult(r);
+                            }}
+                        >
+                            {r.type === 'item' ? '🃏' : r.type === 'monster' ? '👹' : '❓'} {r.data?.name_cn || r.data?.name_zh || r.data?.name || r.data?.Id}
+                        </div>
+                    ))}
+                </div>
+            )}
             {yoloResult && (
                 <div 
                     ref={containerRef}
@@ -1152,8 +1223,8 @@ export default function OverlayApp() {
                         zIndex: 999,
                         overflow: 'visible',
                         minWidth: '200px',
-                        background: 'rgba(20,15,10,0.98)',
-                        border: '1px solid var(--c-golden)',
+                        background: `rgba(20,15,10,${overlayStyle.opacity})`,
+                        border: `1px solid ${overlayStyle.box_color}`,
                         borderRadius: '12px',
                         boxShadow: '0 15px 45px rgba(0,0,0,0.9), 0 0 20px rgba(255,205,25,0.1)',
                         display: 'flex',
@@ -1272,6 +1343,32 @@ export default function OverlayApp() {
                             </div>
                         ) : yoloResult.type === 'event' ? (
                             <div className="event-card-container" style={{ border: 'none', boxShadow: 'none', background: 'transparent', margin: 0, padding: 0 }}>
+                                {eventAlternatives.length > 1 && (
+                                    <div style={{ display: 'flex', gap: '6px', flexWrap: 'wrap', marginBottom: '10px' }}>
+                                        {eventAlternatives.map(alt => {
+                                            const active = (yoloResult.data as EventData).Id === alt.id;
+                                            return (
+                                                <div
+                                                    key={alt.id}
+                                                    style={{
+                                                        cursor: 'pointer',
+                                                        padding: '3px 8px',
+                                                        borderRadius: '4px',
+                                                        fontSize: '12px',
+                                                        border: `1px solid ${active ? overlayStyle.box_color : 'rgba(255,255,255,0.2)'}`,
+                                                        color: active ? overlayStyle.box_color : '#ccc'
+                                                    }}
+                                                    onClick={async () => {
+                                                        const eventData = await processEventImages(alt.data as EventData);
+                                                        setYoloResult({ type: 'event', data: eventData });
+                                                    }}
+                                                >
+                                                    {alt.name} · {alt.match_count} 点
+                                                </div>
+                                            );
+                                        })}
+                                    </div>
+                                )}
                                 {(() => {
                                     const e = yoloResult.data as EventData;
                                     const eventTitle = e.name || e.name_en || e.InternalName || '';
@@ -2028,7 +2125,10 @@ export default function OverlayApp() {
                         alignItems: 'center'
                     }}
                 >
-                     <span>⚡ YOLO {localStorage.getItem('use-gpu-acceleration') !== 'false' ? 'GPU' : 'CPU'}</span>
+                     <span>
+                        ⚡ YOLO {localStorage.getItem('use-gpu-acceleration') !== 'false' ? 'GPU' : 'CPU'}
+                        <span title={ignoreCursor ? '点击穿透中' : '可交互'} style={{ marginLeft: '6px' }}>{ignoreCursor ? '👻' : '🖱️'}</span>
+                     </span>
                      <div style={{ display: 'flex', alignItems: 'center', gap: '6px' }}>
                         <button 
                             onMouseDown={(e) => e.stopPropagation()}